@@ -16,6 +16,7 @@ fn main() {
     let options = MergeOptions {
         input_paths: input_files.iter().map(PathBuf::from).collect(),
         output_path: output_path.clone(),
+        ..Default::default()
     };
 
     println!("Merging {} PDFs...", input_files.len());
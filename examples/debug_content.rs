@@ -14,6 +14,7 @@ fn main() {
         header_font: None,
         footer_font: None,
         mask: MaskOptions::new(),
+        ..Default::default()
     };
     
     // Simulate what should happen for each page
@@ -24,6 +24,7 @@ fn main() {
     let options = MergeOptions {
         input_paths: test_files.iter().map(PathBuf::from).collect(),
         output_path: output_path.clone(),
+        ..Default::default()
     };
 
     println!("Merging PDFs...");
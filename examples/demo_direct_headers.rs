@@ -46,6 +46,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let merge_options = MergeOptions {
         input_paths,
         output_path: merged_path.to_path_buf(),
+        ..Default::default()
     };
 
     merge_pdfs(&merge_options)?;
@@ -63,7 +64,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // - [font bold]...[/font] = bold text
     //
     // FontSpec allows specifying: bold, italic, size, family, and color
-    // Format: "[bold] [italic] [size[pt]] [family_name] [#rrggbb]"
+    // Format: "[bold] [italic] [size[pt]] [family_name] [color]"
     // Example: "bold 24pt Liberation_Serif #333333"
     let header_footer_options = HeaderFooterOptions {
         title: Some("Bridge Class Handout".to_string()),
@@ -76,10 +77,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         title_font_size: 24.0,
         footer_font_size: 14.0,
         // Use FontSpec to set header color to dark gray
-        header_font: Some(FontSpec::parse("24pt #333333")),
+        header_font: Some(FontSpec::parse("24pt #333333")?),
         // Use FontSpec to set footer color to a slightly lighter gray
-        footer_font: Some(FontSpec::parse("14pt #555555")),
+        footer_font: Some(FontSpec::parse("14pt #555555")?),
         mask: MaskOptions::new(),
+        ..Default::default()
     };
 
     add_headers_footers(merged_path, final_output_path, &header_footer_options)?;
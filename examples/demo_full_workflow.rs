@@ -47,6 +47,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let merge_options = MergeOptions {
         input_paths,
         output_path: merged_path.to_path_buf(),
+        ..Default::default()
     };
 
     merge_pdfs(&merge_options)?;
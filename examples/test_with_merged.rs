@@ -31,6 +31,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         header_font: None,
         footer_font: None,
         mask: MaskOptions::new(),
+        ..Default::default()
     };
 
     println!("\nAdding headers/footers...");
@@ -2,15 +2,19 @@
 //!
 //! A command-line tool for merging PDFs and adding headers/footers.
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use glob::glob;
 use std::path::PathBuf;
 use std::process;
 
 use pdf_handouts::pdf::{
     merge_pdfs, add_headers_footers,
-    MergeOptions, HeaderFooterOptions, FontSpec, MaskOptions,
+    MergeOptions, MergeReport, HeaderFooterOptions, FontSpec, MaskOptions, LayerOptions, Color, TextAlign, ColumnDirections,
+    PageTemplate, ElementPlacement, Stamp, FitOptions, CanvasOptions, PaperScaling, InputBookmarks,
+    InputPassword, password_for, OutputEncryption, OutputPermissions, EncryptionAlgorithm, CoverOptions, TocOptions,
 };
+use pdf_handouts::layout::{Length, PageDimensions};
+use pdf_handouts::pages::PageSelection;
 use pdf_handouts::date::{parse_date_expression, resolve_date};
 
 /// PDF Handouts - Merge PDFs and add headers/footers
@@ -25,7 +29,9 @@ use pdf_handouts::date::{parse_date_expression, resolve_date};
 
 OPTIONS (for build and headers commands):
     -o, --output <FILE>          Output PDF file path (required)
-    --title <TEXT>               Title centered at top of first page
+    --title <TEXT>               Title at top of first page (| for line breaks)
+    --subtitle <TEXT>            Subtitle below the title
+    --title-align <ALIGN>        Title alignment: left, center, right [default: center]
     --footer-left <TEXT>         Footer left section
     --footer-center <TEXT>       Footer center section
     --footer-right <TEXT>        Footer right section
@@ -33,13 +39,56 @@ OPTIONS (for build and headers commands):
     --font <SPEC>                Font for both header and footer
     --header-font <SPEC>         Font for header only (overrides --font)
     --footer-font <SPEC>         Font for footer only (overrides --font)
+    --subtitle-font <SPEC>       Font for the subtitle
+    --font-file <PATH>           Font for non-ASCII/right-to-left text
+    --direction <DIR>            auto, ltr, rtl or per block: title=rtl,right=ltr
+    --landscape-footer-left <TEXT>    Footer left section on landscape pages
+    --landscape-footer-center <TEXT>  Footer center section on landscape pages
+    --landscape-footer-right <TEXT>   Footer right section on landscape pages
+    --landscape-footer-font <SPEC>    Footer font on landscape pages
+    --landscape-single-line      Single-line footer sections on landscape pages
     --mask-header <INCHES>       Mask header on first page only
     --mask-footer <INCHES>       Mask footer on first page only
     --mask-header-all <INCHES>   Mask header on all pages
     --mask-footer-all <INCHES>   Mask footer on all pages
     --mask-color <COLOR>         Mask color [default: #ffffff]
+    --placement <SPEC>           over, under or per element: masks=under,footer=over
+    --stamp <SPEC>               Text/image stamp (repeatable, see STAMPS)
+    --fit                        Shrink page content to make room for headers/footers
+    --fit-header <LENGTH>        Header reserve when fitting [default: 1in]
+    --fit-footer <LENGTH>        Footer reserve when fitting [default: 1in]
+    --extend-top <LENGTH>        Add a blank band above each page
+    --extend-bottom <LENGTH>     Add a blank band below each page
+    --extend-paper <PAPER>       Normalize pages to a paper size between the bands
+    --blank-page-text [TEXT]     Notice on inserted blank pages (see --duplex)
+    --cover                      Put title, subtitle and date on a cover page (build only)
+    --presenter <NAME>           Presenter name for the cover page
+    --logo <IMAGE>               PNG or JPEG logo for the cover page
+    --toc [HEADING]              Table of contents with a linked line per input (build only)
+    --layer [NAME]               Put headers/footers in an optional content layer
+    --layer-hidden               Hide the layer on screen by default
+    --layer-no-print             Leave the layer out when printing
+    --lang <TAG>                 Document language (e.g., en-US)
+    --debug-layout               Outline page boxes, safe area, columns, masks, baselines
+
+OPTIONS (for build and merge commands):
+    <INPUT>:<PAGES>              Pages to take from an input: 1-3,7,9-  -1 (last)  odd  even
+    --paper <SIZE>               Put every page on one paper size: letter, a4, legal,
+                                 tabloid, half-letter, a3, a5 or WxH (e.g., 8.5x11in)
+    --paper-no-scale             With --paper, center pages without scaling
+    --bookmarks <MODE>           Bookmark per input: none, filename, title [default: none]
+    --no-dedup                   Keep duplicate fonts and images from separate inputs
+    --duplex                     Start each input on a right-hand page (adds blank pages)
+    --pad-to <N>                 Add blank pages at the end up to a multiple of N (2 or 4)
+    --password <FILE=PASSWORD>   Open an encrypted input (repeatable; also for headers, info)
     --open                       Open output file after creation
 
+OUTPUT ENCRYPTION (for build, merge and headers commands):
+    --user-password <PASSWORD>   Password needed to open the output
+    --owner-password <PASSWORD>  Password that lifts the permission limits (default: random)
+    --encryption <ALGORITHM>     aes128 or aes256 [default: aes256]
+    --deny <ACTIONS>             Forbid print, copy, modify and/or annotate (e.g., modify,copy)
+
 PLACEHOLDERS (use in footer text):
     [page]    Current page number
     [pages]   Total page count
@@ -47,20 +96,43 @@ PLACEHOLDERS (use in footer text):
     |         Line break (or use [br])
 
 FONT SPEC FORMAT:
-    \"[bold] [italic] [size[pt]] [family] [#rrggbb]\"
+    \"[bold] [italic] [size[pt]] [family] [color]\"
     Examples: \"14pt\", \"bold 16pt #333333\", \"italic 12pt Liberation_Serif\"
 
+COLORS (fonts and --mask-color):
+    #rgb, #rrggbb, #rrggbbaa    Hex RGB (optional alpha)
+    gray(0.4), gray(40%,0.5)    Grayscale (optional alpha)
+    rgb(51,51,51), rgba(0,0,0,0.5)
+    cmyk(0,0,0,1)               CMYK (optional fifth alpha component)
+    navy, darkslategray, ...    CSS color names
+
+STAMPS (--stamp, space-separated key=value settings):
+    text=<TEXT> | image=<PNG/JPEG>   What to draw (unquoted text runs to the end)
+    page=<PAGES>                 Pages: 4, 1-3,7, 5-, last, odd, even [default: all]
+    x=<LEN> y=<LEN>              Offsets from the anchor (pt, in, mm, cm)
+    anchor=<ANCHOR>              top-left, top, top-right, left, center, right,
+                                 bottom-left [default], bottom, bottom-right
+    font=<SPEC> direction=<DIR> width=<LEN> height=<LEN> placement=over|under
+    Quote values containing spaces: font='bold 12pt red'
+    Example: --stamp \"page=4 x=0.5in y=0.5in anchor=top-right text=Hand 3\"
+
 DATE EXPRESSIONS:
     today, 2026-01-14, 01/14/2026, Tuesday, Tuesday+1
 
 INLINE STYLING:
     [font italic]text[/font]    Italic text
     [font bold]text[/font]      Bold text
+    [font 10pt #ff0000]text[/font]  Any font spec (size, family, color)
+    [font underline]text[/font] Underlined (also: strike)
+    \\[  \\]  \\|  \\\\          Literal [, ], | and \\
 
 EXAMPLES:
     # Merge PDFs and add footer
     pdf-handouts build -o output.pdf --footer-center \"Page [page]\" *.pdf
 
+    # Take pages 1-3 and the last page of an article
+    pdf-handouts merge -o out.pdf intro.pdf article.pdf:1-3,-1
+
     # Add headers with date
     pdf-handouts headers input.pdf -o output.pdf --title \"My Doc\" --date today
 
@@ -78,7 +150,7 @@ struct Cli {
 enum Commands {
     /// Merge multiple PDF files into one
     Merge {
-        /// Input PDF files (in order). Supports glob patterns like "*.pdf"
+        /// Input PDF files (in order). Supports glob patterns like "*.pdf" and page selections like "file.pdf:1-3,-1"
         #[arg(required = true)]
         inputs: Vec<String>,
 
@@ -86,6 +158,15 @@ enum Commands {
         #[arg(short, long)]
         output: PathBuf,
 
+        #[command(flatten)]
+        merge: MergeArgs,
+
+        #[command(flatten)]
+        passwords: PasswordArgs,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+
         /// Open the output file after creation
         #[arg(long)]
         open: bool,
@@ -100,176 +181,430 @@ enum Commands {
         #[arg(short, long)]
         output: PathBuf,
 
-        /// Title text (displayed centered at top of first page)
-        #[arg(long)]
-        title: Option<String>,
-
-        /// Footer left section (use | or [br] for line breaks)
-        #[arg(long)]
-        footer_left: Option<String>,
+        #[command(flatten)]
+        headers: HeaderArgs,
 
-        /// Footer center section (use | or [br] for line breaks)
-        #[arg(long)]
-        footer_center: Option<String>,
+        #[command(flatten)]
+        passwords: PasswordArgs,
 
-        /// Footer right section (use | or [br] for line breaks)
-        #[arg(long)]
-        footer_right: Option<String>,
+        #[command(flatten)]
+        encryption: EncryptionArgs,
 
-        /// Date for [date] placeholder (e.g., "today", "next tuesday", "2026-01-14")
-        #[arg(long)]
-        date: Option<String>,
-
-        /// Font specification for both header and footer
-        /// Format: "[bold] [italic] [size[pt]] [family] [#rrggbb]"
-        /// Example: "14pt Liberation_Serif #333333"
+        /// Open the output file after creation
         #[arg(long)]
-        font: Option<String>,
+        open: bool,
+    },
 
-        /// Font specification for header only (overrides --font)
-        #[arg(long)]
-        header_font: Option<String>,
+    /// Merge PDFs and add headers/footers in one step
+    Build {
+        /// Input PDF files (in order). Supports glob patterns like "*.pdf" and page selections like "file.pdf:1-3,-1"
+        #[arg(required = true)]
+        inputs: Vec<String>,
 
-        /// Font specification for footer only (overrides --font)
-        #[arg(long)]
-        footer_font: Option<String>,
+        /// Output PDF file path
+        #[arg(short, long)]
+        output: PathBuf,
 
-        /// Mask header area on first page only (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_header: Option<f32>,
+        #[command(flatten)]
+        headers: HeaderArgs,
 
-        /// Mask footer area on first page only (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_footer: Option<f32>,
+        #[command(flatten)]
+        merge: MergeArgs,
 
-        /// Mask header area on all pages (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_header_all: Option<f32>,
+        #[command(flatten)]
+        front_matter: FrontMatterArgs,
 
-        /// Mask footer area on all pages (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_footer_all: Option<f32>,
+        #[command(flatten)]
+        passwords: PasswordArgs,
 
-        /// Mask color (default: white). Format: "#rrggbb" or "#rgb"
-        #[arg(long, value_name = "COLOR", default_value = "#ffffff")]
-        mask_color: String,
+        #[command(flatten)]
+        encryption: EncryptionArgs,
 
         /// Open the output file after creation
         #[arg(long)]
         open: bool,
     },
 
-    /// Merge PDFs and add headers/footers in one step
-    Build {
-        /// Input PDF files (in order). Supports glob patterns like "*.pdf"
-        #[arg(required = true)]
-        inputs: Vec<String>,
+    /// Show information about a PDF file
+    Info {
+        /// PDF file to inspect
+        input: PathBuf,
 
-        /// Output PDF file path
-        #[arg(short, long)]
-        output: PathBuf,
+        #[command(flatten)]
+        passwords: PasswordArgs,
+    },
+}
 
-        /// Title text (displayed centered at top of first page)
-        #[arg(long)]
-        title: Option<String>,
+/// Header, footer and page layout options (for build and headers)
+#[derive(Args)]
+struct HeaderArgs {
+    /// Title text at top of first page (use | or [br] for line breaks)
+    #[arg(long)]
+    title: Option<String>,
 
-        /// Footer left section (use | or [br] for line breaks)
-        #[arg(long)]
-        footer_left: Option<String>,
+    /// Subtitle text below the title (use | or [br] for line breaks)
+    #[arg(long)]
+    subtitle: Option<String>,
 
-        /// Footer center section (use | or [br] for line breaks)
-        #[arg(long)]
-        footer_center: Option<String>,
+    /// Title and subtitle alignment: left, center or right
+    #[arg(long, value_name = "ALIGN", default_value = "center")]
+    title_align: String,
 
-        /// Footer right section (use | or [br] for line breaks)
-        #[arg(long)]
-        footer_right: Option<String>,
+    /// Footer left section (use | or [br] for line breaks)
+    #[arg(long)]
+    footer_left: Option<String>,
 
-        /// Date for [date] placeholder (e.g., "today", "next tuesday", "2026-01-14")
-        #[arg(long)]
-        date: Option<String>,
+    /// Footer center section (use | or [br] for line breaks)
+    #[arg(long)]
+    footer_center: Option<String>,
 
-        /// Font specification for both header and footer
-        /// Format: "[bold] [italic] [size[pt]] [family] [#rrggbb]"
-        /// Example: "14pt Liberation_Serif #333333"
-        #[arg(long)]
-        font: Option<String>,
+    /// Footer right section (use | or [br] for line breaks)
+    #[arg(long)]
+    footer_right: Option<String>,
 
-        /// Font specification for header only (overrides --font)
-        #[arg(long)]
-        header_font: Option<String>,
+    /// Date for [date] placeholder (e.g., "today", "next tuesday", "2026-01-14")
+    #[arg(long)]
+    date: Option<String>,
 
-        /// Font specification for footer only (overrides --font)
-        #[arg(long)]
-        footer_font: Option<String>,
+    /// Font specification for both header and footer
+    /// Format: "[bold] [italic] [size[pt]] [family] [color]"
+    /// Example: "14pt Liberation_Serif #333333"
+    #[arg(long)]
+    font: Option<String>,
 
-        /// Mask header area on first page only (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_header: Option<f32>,
+    /// Font specification for header only (overrides --font)
+    #[arg(long)]
+    header_font: Option<String>,
 
-        /// Mask footer area on first page only (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_footer: Option<f32>,
+    /// Font specification for footer only (overrides --font)
+    #[arg(long)]
+    footer_font: Option<String>,
 
-        /// Mask header area on all pages (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_header_all: Option<f32>,
+    /// Font specification for the subtitle (default: two thirds of the title size)
+    #[arg(long)]
+    subtitle_font: Option<String>,
 
-        /// Mask footer area on all pages (height in inches, e.g., "0.5")
-        #[arg(long, value_name = "INCHES")]
-        mask_footer_all: Option<f32>,
+    /// TrueType font file for non-ASCII and right-to-left text (e.g., a Devanagari font)
+    #[arg(long, value_name = "PATH")]
+    font_file: Option<PathBuf>,
 
-        /// Mask color (default: white). Format: "#rrggbb" or "#rgb"
-        #[arg(long, value_name = "COLOR", default_value = "#ffffff")]
-        mask_color: String,
+    /// Text direction: auto, ltr or rtl, or per block (e.g., "title=rtl,right=rtl")
+    #[arg(long, value_name = "DIR", default_value = "auto")]
+    direction: String,
 
-        /// Open the output file after creation
-        #[arg(long)]
-        open: bool,
-    },
+    /// Footer left section on landscape pages (default: --footer-left)
+    #[arg(long)]
+    landscape_footer_left: Option<String>,
 
-    /// Show information about a PDF file
-    Info {
-        /// PDF file to inspect
-        input: PathBuf,
-    },
+    /// Footer center section on landscape pages (default: --footer-center)
+    #[arg(long)]
+    landscape_footer_center: Option<String>,
+
+    /// Footer right section on landscape pages (default: --footer-right)
+    #[arg(long)]
+    landscape_footer_right: Option<String>,
+
+    /// Footer font specification on landscape pages (default: --footer-font)
+    #[arg(long)]
+    landscape_footer_font: Option<String>,
+
+    /// Put each footer section on a single line on landscape pages
+    #[arg(long)]
+    landscape_single_line: bool,
+
+    /// Mask header area on first page only (height in inches, e.g., "0.5")
+    #[arg(long, value_name = "INCHES")]
+    mask_header: Option<f32>,
+
+    /// Mask footer area on first page only (height in inches, e.g., "0.5")
+    #[arg(long, value_name = "INCHES")]
+    mask_footer: Option<f32>,
+
+    /// Mask header area on all pages (height in inches, e.g., "0.5")
+    #[arg(long, value_name = "INCHES")]
+    mask_header_all: Option<f32>,
+
+    /// Mask footer area on all pages (height in inches, e.g., "0.5")
+    #[arg(long, value_name = "INCHES")]
+    mask_footer_all: Option<f32>,
+
+    /// Mask color (default: white). Format: "#rrggbb", "gray(0.9)", "cmyk(0,0,0,0)", a color name, ...
+    #[arg(long, value_name = "COLOR", default_value = "#ffffff")]
+    mask_color: String,
+
+    /// Draw over or under the page content: over, under, or per element (e.g., "masks=under")
+    #[arg(long, value_name = "SPEC", default_value = "over")]
+    placement: String,
+
+    /// Text or image stamp (repeatable), e.g. "page=4 x=1in y=1in anchor=top-right text=Hand 3"
+    #[arg(long = "stamp", value_name = "SPEC")]
+    stamps: Vec<String>,
+
+    /// Shrink the original page content to make room for headers and footers
+    #[arg(long)]
+    fit: bool,
+
+    /// Space reserved for the header when fitting (e.g., "1in", "20mm")
+    #[arg(long, value_name = "LENGTH", default_value = "1in")]
+    fit_header: String,
+
+    /// Space reserved for the footer when fitting (e.g., "1in", "20mm")
+    #[arg(long, value_name = "LENGTH", default_value = "1in")]
+    fit_footer: String,
+
+    /// Add a blank band of this height above each page (e.g., "0.75in")
+    #[arg(long, value_name = "LENGTH")]
+    extend_top: Option<String>,
+
+    /// Add a blank band of this height below each page (e.g., "0.75in")
+    #[arg(long, value_name = "LENGTH")]
+    extend_bottom: Option<String>,
+
+    /// Also normalize every page to this paper size (see --paper), fitting the content between the bands
+    #[arg(long, value_name = "PAPER")]
+    extend_paper: Option<String>,
+
+    /// Show a notice on inserted blank pages instead of leaving them empty (default: "This page intentionally left blank")
+    #[arg(long, value_name = "TEXT", num_args = 0..=1, default_missing_value = "This page intentionally left blank")]
+    blank_page_text: Option<String>,
+
+    /// Put headers/footers in an optional content layer (default name: "Handout Header/Footer")
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "Handout Header/Footer")]
+    layer: Option<String>,
+
+    /// Hide the header/footer layer on screen when the document is opened
+    #[arg(long, requires = "layer")]
+    layer_hidden: bool,
+
+    /// Leave the header/footer layer out when printing
+    #[arg(long, requires = "layer")]
+    layer_no_print: bool,
+
+    /// Document language for accessibility (e.g., "en-US"), written to the catalog
+    #[arg(long, value_name = "TAG")]
+    lang: Option<String>,
+
+    /// Outline page boxes, safe area, column budgets, masks and baselines
+    #[arg(long)]
+    debug_layout: bool,
+}
+
+/// How inputs are combined (for build and merge)
+#[derive(Args)]
+struct MergeArgs {
+    /// Put every page on this paper size: letter, a4, legal, tabloid, half-letter, a3, a5 or WxH (e.g., 8.5x11in)
+    #[arg(long, value_name = "SIZE")]
+    paper: Option<String>,
+
+    /// With --paper, center pages without scaling (padding only)
+    #[arg(long, requires = "paper")]
+    paper_no_scale: bool,
+
+    /// Add a bookmark for each input: none, filename or title (Info Title, else file name)
+    #[arg(long, value_name = "MODE", default_value = "none")]
+    bookmarks: String,
+
+    /// Keep duplicate fonts, images and other shared objects instead of storing them once
+    #[arg(long)]
+    no_dedup: bool,
+
+    /// Insert a blank page after each input with an odd page count, so every input starts on a right-hand page
+    #[arg(long)]
+    duplex: bool,
+
+    /// Add blank pages at the end to make the page count a multiple of N (e.g., 2 or 4)
+    #[arg(long, value_name = "N")]
+    pad_to: Option<usize>,
+}
+
+/// Passwords for encrypted inputs
+#[derive(Args)]
+struct PasswordArgs {
+    /// Password for an encrypted input: FILE=PASSWORD (repeatable; empty passwords need none)
+    #[arg(long, value_name = "FILE=PASSWORD")]
+    password: Vec<String>,
+}
+
+/// Output encryption (for build, merge and headers)
+#[derive(Args)]
+struct EncryptionArgs {
+    /// Password needed to open the output (encrypts it)
+    #[arg(long, value_name = "PASSWORD")]
+    user_password: Option<String>,
+
+    /// Password that lifts the output's permission limits (encrypts it; default: random)
+    #[arg(long, value_name = "PASSWORD")]
+    owner_password: Option<String>,
+
+    /// Encryption for the output: aes128 or aes256
+    #[arg(long, value_name = "ALGORITHM", default_value = "aes256")]
+    encryption: String,
+
+    /// Forbid these without the owner password: print, copy, modify, annotate (encrypts the output)
+    #[arg(long, value_name = "ACTIONS")]
+    deny: Option<String>,
+}
+
+/// Pages generated in front of the handout (for build)
+#[derive(Args)]
+struct FrontMatterArgs {
+    /// Start with a cover page showing the title, subtitle, presenter, date and logo (instead of the title on page 1)
+    #[arg(long)]
+    cover: bool,
+
+    /// Presenter name(s) for the cover page
+    #[arg(long, value_name = "NAME", requires = "cover")]
+    presenter: Option<String>,
+
+    /// PNG or JPEG logo for the cover page
+    #[arg(long, value_name = "IMAGE", requires = "cover")]
+    logo: Option<PathBuf>,
+
+    /// Add a table of contents listing each input with its first page (default heading: "Contents")
+    #[arg(long, value_name = "HEADING", num_args = 0..=1, default_missing_value = "Contents")]
+    toc: Option<String>,
+}
+
+impl HeaderArgs {
+    /// Library options for these flags; passwords, encryption and front matter are left to the command
+    fn header_footer_options(self) -> Result<HeaderFooterOptions, Box<dyn std::error::Error>> {
+        // Parse date expression
+        let resolved_date = self.date.as_deref()
+            .and_then(|d| parse_date_expression(d).ok())
+            .and_then(|expr| resolve_date(&expr));
+
+        // Parse font specifications
+        let base_font = self.font.as_deref().map(FontSpec::parse).transpose()?;
+        let header_spec = self.header_font.as_deref().map(FontSpec::parse).transpose()?.or_else(|| base_font.clone());
+        let footer_spec = self.footer_font.as_deref().map(FontSpec::parse).transpose()?.or(base_font);
+        let subtitle_spec = self.subtitle_font.as_deref().map(FontSpec::parse).transpose()?;
+
+        // Landscape pages get their own footer layout when any --landscape-* option is set
+        let landscape_spec = self.landscape_footer_font.as_deref().map(FontSpec::parse).transpose()?;
+        let landscape = (self.landscape_footer_left.is_some()
+            || self.landscape_footer_center.is_some()
+            || self.landscape_footer_right.is_some()
+            || landscape_spec.is_some()
+            || self.landscape_single_line)
+            .then(|| PageTemplate {
+                footer_left: self.landscape_footer_left.or_else(|| self.footer_left.clone()),
+                footer_center: self.landscape_footer_center.or_else(|| self.footer_center.clone()),
+                footer_right: self.landscape_footer_right.or_else(|| self.footer_right.clone()),
+                footer_font: landscape_spec,
+                single_line: self.landscape_single_line,
+            });
+
+        // Build mask options
+        let mask = MaskOptions {
+            header_height: self.mask_header,
+            footer_height: self.mask_footer,
+            header_all_height: self.mask_header_all,
+            footer_all_height: self.mask_footer_all,
+            color: Color::parse(&self.mask_color)?,
+        };
+
+        Ok(HeaderFooterOptions {
+            title: self.title,
+            subtitle: self.subtitle,
+            title_align: TextAlign::parse(&self.title_align)?,
+            footer_left: self.footer_left,
+            footer_center: self.footer_center,
+            footer_right: self.footer_right,
+            date: resolved_date,
+            show_page_numbers: false,
+            show_total_page_count: false,
+            title_font_size: header_spec.as_ref().and_then(|f| f.size).unwrap_or(24.0),
+            footer_font_size: footer_spec.as_ref().and_then(|f| f.size).unwrap_or(14.0),
+            header_font: header_spec,
+            footer_font: footer_spec,
+            subtitle_font: subtitle_spec,
+            mask,
+            layer: self.layer.map(|name| LayerOptions {
+                name,
+                visible: !self.layer_hidden,
+                printable: !self.layer_no_print,
+            }),
+            lang: self.lang,
+            directions: ColumnDirections::parse(&self.direction)?,
+            font_file: self.font_file,
+            landscape,
+            placement: ElementPlacement::parse(&self.placement)?,
+            stamps: self.stamps.iter().map(|spec| Stamp::parse(spec)).collect::<Result<_, _>>()?,
+            debug_layout: self.debug_layout,
+            fit: fit_options(self.fit, &self.fit_header, &self.fit_footer)?,
+            canvas: canvas_options(self.extend_top, self.extend_bottom, self.extend_paper)?,
+            password: None,
+            encryption: None,
+            blank_page_text: self.blank_page_text,
+            cover: None,
+            toc: None,
+        })
+    }
+}
+
+impl MergeArgs {
+    /// Library options for merging `inputs` (glob-expanded, each with its page selection)
+    fn merge_options(
+        &self,
+        inputs: Vec<(PathBuf, PageSelection)>,
+        output_path: PathBuf,
+        passwords: Vec<InputPassword>,
+    ) -> Result<MergeOptions, Box<dyn std::error::Error>> {
+        let (input_paths, page_selections) = inputs.into_iter().unzip();
+        Ok(MergeOptions {
+            input_paths,
+            page_selections,
+            output_path,
+            paper: self.paper.as_deref().map(PageDimensions::parse).transpose()?,
+            // Scale-to-fit unless only padding was asked for
+            paper_scaling: if self.paper_no_scale { PaperScaling::None } else { PaperScaling::Fit },
+            bookmarks: InputBookmarks::parse(&self.bookmarks)?,
+            deduplicate: !self.no_dedup,
+            passwords,
+            encryption: None,
+            duplex: self.duplex,
+            pad_to_multiple: self.pad_to,
+        })
+    }
+}
+
+impl PasswordArgs {
+    /// Parse `--password FILE=PASSWORD` values
+    fn parse(&self) -> Result<Vec<InputPassword>, Box<dyn std::error::Error>> {
+        Ok(self.password.iter().map(|value| InputPassword::parse(value)).collect::<pdf_handouts::Result<_>>()?)
+    }
+}
+
+impl EncryptionArgs {
+    /// Output encryption, if any of the passwords or `--deny` was given
+    fn output_encryption(self) -> Result<Option<OutputEncryption>, Box<dyn std::error::Error>> {
+        if self.user_password.is_none() && self.owner_password.is_none() && self.deny.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(OutputEncryption {
+            user_password: self.user_password.unwrap_or_default(),
+            owner_password: self.owner_password.unwrap_or_default(),
+            algorithm: EncryptionAlgorithm::parse(&self.encryption)?,
+            permissions: self.deny.as_deref().map(OutputPermissions::deny).transpose()?.unwrap_or_default(),
+        }))
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
 
     let result = match cli.command {
-        Commands::Merge { inputs, output, open } => {
-            cmd_merge(inputs, output, open)
+        Commands::Merge { inputs, output, merge, passwords, encryption, open } => {
+            cmd_merge(inputs, output, merge, passwords, encryption, open)
         }
-        Commands::Headers {
-            input, output, title, footer_left, footer_center, footer_right,
-            date, font, header_font, footer_font,
-            mask_header, mask_footer, mask_header_all, mask_footer_all, mask_color,
-            open,
-        } => {
-            cmd_headers(
-                input, output, title, footer_left, footer_center, footer_right,
-                date, font, header_font, footer_font,
-                mask_header, mask_footer, mask_header_all, mask_footer_all, mask_color,
-                open,
-            )
+        Commands::Headers { input, output, headers, passwords, encryption, open } => {
+            cmd_headers(input, output, headers, passwords, encryption, open)
         }
-        Commands::Build {
-            inputs, output, title, footer_left, footer_center, footer_right,
-            date, font, header_font, footer_font,
-            mask_header, mask_footer, mask_header_all, mask_footer_all, mask_color,
-            open,
-        } => {
-            cmd_build(
-                inputs, output, title, footer_left, footer_center, footer_right,
-                date, font, header_font, footer_font,
-                mask_header, mask_footer, mask_header_all, mask_footer_all, mask_color,
-                open,
-            )
+        Commands::Build { inputs, output, headers, merge, front_matter, passwords, encryption, open } => {
+            cmd_build(inputs, output, headers, merge, front_matter, passwords, encryption, open)
         }
-        Commands::Info { input } => {
-            cmd_info(input)
+        Commands::Info { input, passwords } => {
+            cmd_info(input, passwords)
         }
     };
 
@@ -279,18 +614,21 @@ fn main() {
     }
 }
 
-/// Expand glob patterns in input paths
-fn expand_globs(patterns: Vec<String>) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+/// Expand glob patterns in input paths, keeping each input's page selection
+/// (`file.pdf:1-3`) with every path it matches
+fn expand_globs(inputs: Vec<String>) -> Result<Vec<(PathBuf, PageSelection)>, Box<dyn std::error::Error>> {
     let mut paths = Vec::new();
 
-    for pattern in patterns {
+    for input in inputs {
+        let (pattern, selection) = PageSelection::split_input(&input)?;
+        let selection = selection.unwrap_or_default();
         // Check if pattern contains glob characters
         if pattern.contains('*') || pattern.contains('?') || pattern.contains('[') {
             let mut matched = false;
-            for entry in glob(&pattern)? {
+            for entry in glob(pattern)? {
                 match entry {
                     Ok(path) => {
-                        paths.push(path);
+                        paths.push((path, selection.clone()));
                         matched = true;
                     }
                     Err(e) => eprintln!("Warning: glob error for {}: {}", pattern, e),
@@ -301,12 +639,12 @@ fn expand_globs(patterns: Vec<String>) -> Result<Vec<PathBuf>, Box<dyn std::erro
             }
         } else {
             // No glob characters, treat as literal path
-            paths.push(PathBuf::from(pattern));
+            paths.push((PathBuf::from(pattern), selection));
         }
     }
 
     // Sort paths for consistent ordering
-    paths.sort();
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
 
     Ok(paths)
 }
@@ -334,26 +672,87 @@ fn open_file(path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Merge multiple PDFs into one
-fn cmd_merge(inputs: Vec<String>, output: PathBuf, open: bool) -> Result<(), Box<dyn std::error::Error>> {
-    // Expand glob patterns
-    let inputs = expand_globs(inputs)?;
+/// Summarize what the merge kept and removed
+fn print_merge_report(report: &MergeReport) {
+    eprintln!(
+        "  {} pages, {} objects; removed {} unused objects (~{:.1} KB)",
+        report.pages,
+        report.objects,
+        report.objects_removed,
+        report.bytes_removed as f64 / 1024.0
+    );
+    if report.blank_pages > 0 {
+        eprintln!("  added {} blank pages", report.blank_pages);
+    }
+    if report.duplicates_removed > 0 {
+        eprintln!(
+            "  shared {} duplicate objects (~{:.1} KB)",
+            report.duplicates_removed,
+            report.bytes_deduplicated as f64 / 1024.0
+        );
+    }
+}
 
-    // Validate inputs exist
-    for path in &inputs {
+/// Shrink the content into the room left by headers and footers (`--fit`)
+fn fit_options(fit: bool, fit_header: &str, fit_footer: &str) -> Result<Option<FitOptions>, Box<dyn std::error::Error>> {
+    if !fit {
+        return Ok(None);
+    }
+    Ok(Some(FitOptions {
+        header_reserve: Length::parse(fit_header)?,
+        footer_reserve: Length::parse(fit_footer)?,
+    }))
+}
+
+/// Extra page bands, optionally on a common paper size (`--extend-*`)
+fn canvas_options(
+    extend_top: Option<String>,
+    extend_bottom: Option<String>,
+    extend_paper: Option<String>,
+) -> Result<Option<CanvasOptions>, Box<dyn std::error::Error>> {
+    if extend_top.is_none() && extend_bottom.is_none() && extend_paper.is_none() {
+        return Ok(None);
+    }
+    let zero = || Length::from_mm(0.0);
+    Ok(Some(CanvasOptions {
+        top: extend_top.as_deref().map(Length::parse).transpose()?.unwrap_or_else(zero),
+        bottom: extend_bottom.as_deref().map(Length::parse).transpose()?.unwrap_or_else(zero),
+        paper: extend_paper.as_deref().map(PageDimensions::parse).transpose()?,
+        ..Default::default()
+    }))
+}
+
+/// Expand the inputs' glob patterns and check that every file exists
+fn resolve_inputs(inputs: Vec<String>) -> Result<Vec<(PathBuf, PageSelection)>, Box<dyn std::error::Error>> {
+    let inputs = expand_globs(inputs)?;
+    for (path, _) in &inputs {
         if !path.exists() {
             return Err(format!("Input file not found: {}", path.display()).into());
         }
     }
+    Ok(inputs)
+}
+
+/// Merge multiple PDFs into one
+fn cmd_merge(
+    inputs: Vec<String>,
+    output: PathBuf,
+    merge: MergeArgs,
+    passwords: PasswordArgs,
+    encryption: EncryptionArgs,
+    open: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let inputs = resolve_inputs(inputs)?;
 
     eprintln!("Merging {} PDF files...", inputs.len());
 
     let options = MergeOptions {
-        input_paths: inputs,
-        output_path: output.clone(),
+        encryption: encryption.output_encryption()?,
+        ..merge.merge_options(inputs, output.clone(), passwords.parse()?)?
     };
 
-    merge_pdfs(&options)?;
+    let report = merge_pdfs(&options)?;
+    print_merge_report(&report);
 
     eprintln!("Merged to: {}", output.display());
 
@@ -364,84 +763,23 @@ fn cmd_merge(inputs: Vec<String>, output: PathBuf, open: bool) -> Result<(), Box
     Ok(())
 }
 
-/// Parse a hex color string to RGB tuple (0.0-1.0 for each component)
-fn parse_mask_color(color: &str) -> (f32, f32, f32) {
-    let hex = color.trim_start_matches('#');
-
-    if hex.len() == 6 {
-        // Full hex: #rrggbb
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(255);
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(255);
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(255);
-        (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
-    } else if hex.len() == 3 {
-        // Short hex: #rgb -> #rrggbb
-        let r = u8::from_str_radix(&hex[0..1].repeat(2), 16).unwrap_or(255);
-        let g = u8::from_str_radix(&hex[1..2].repeat(2), 16).unwrap_or(255);
-        let b = u8::from_str_radix(&hex[2..3].repeat(2), 16).unwrap_or(255);
-        (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
-    } else {
-        // Default to white
-        (1.0, 1.0, 1.0)
-    }
-}
-
 /// Add headers and footers to a PDF
 fn cmd_headers(
     input: PathBuf,
     output: PathBuf,
-    title: Option<String>,
-    footer_left: Option<String>,
-    footer_center: Option<String>,
-    footer_right: Option<String>,
-    date: Option<String>,
-    font: Option<String>,
-    header_font: Option<String>,
-    footer_font: Option<String>,
-    mask_header: Option<f32>,
-    mask_footer: Option<f32>,
-    mask_header_all: Option<f32>,
-    mask_footer_all: Option<f32>,
-    mask_color: String,
+    headers: HeaderArgs,
+    passwords: PasswordArgs,
+    encryption: EncryptionArgs,
     open: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !input.exists() {
         return Err(format!("Input file not found: {}", input.display()).into());
     }
 
-    // Parse date expression
-    let resolved_date = date.as_deref()
-        .and_then(|d| parse_date_expression(d).ok())
-        .and_then(|expr| resolve_date(&expr));
-
-    // Parse font specifications
-    let base_font = font.as_deref().map(FontSpec::parse);
-    let header_spec = header_font.as_deref().map(FontSpec::parse).or_else(|| base_font.clone());
-    let footer_spec = footer_font.as_deref().map(FontSpec::parse).or(base_font);
-
-    // Build mask options
-    let mask = MaskOptions {
-        header_height: mask_header,
-        footer_height: mask_footer,
-        header_all_height: mask_header_all,
-        footer_all_height: mask_footer_all,
-        color: parse_mask_color(&mask_color),
-    };
-
-    // Build options
     let options = HeaderFooterOptions {
-        title,
-        footer_left,
-        footer_center,
-        footer_right,
-        date: resolved_date,
-        show_page_numbers: false,
-        show_total_page_count: false,
-        title_font_size: header_spec.as_ref().and_then(|f| f.size).unwrap_or(24.0),
-        footer_font_size: footer_spec.as_ref().and_then(|f| f.size).unwrap_or(14.0),
-        header_font: header_spec,
-        footer_font: footer_spec,
-        mask,
+        password: password_for(&passwords.parse()?, &input).map(String::from),
+        encryption: encryption.output_encryption()?,
+        ..headers.header_footer_options()?
     };
 
     eprintln!("Adding headers/footers...");
@@ -460,84 +798,57 @@ fn cmd_headers(
 fn cmd_build(
     inputs: Vec<String>,
     output: PathBuf,
-    title: Option<String>,
-    footer_left: Option<String>,
-    footer_center: Option<String>,
-    footer_right: Option<String>,
-    date: Option<String>,
-    font: Option<String>,
-    header_font: Option<String>,
-    footer_font: Option<String>,
-    mask_header: Option<f32>,
-    mask_footer: Option<f32>,
-    mask_header_all: Option<f32>,
-    mask_footer_all: Option<f32>,
-    mask_color: String,
+    headers: HeaderArgs,
+    merge: MergeArgs,
+    front_matter: FrontMatterArgs,
+    passwords: PasswordArgs,
+    encryption: EncryptionArgs,
     open: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Expand glob patterns
-    let inputs = expand_globs(inputs)?;
-
-    // Validate inputs exist
-    for path in &inputs {
-        if !path.exists() {
-            return Err(format!("Input file not found: {}", path.display()).into());
-        }
-    }
+    let inputs = resolve_inputs(inputs)?;
 
-    // Create temporary file for merged PDF
-    let temp_dir = std::env::temp_dir();
-    let temp_merged = temp_dir.join("pdf-handouts-merged-temp.pdf");
+    // The merged PDF holds decrypted inputs: keep it in a private temporary
+    // file that is removed however this function returns
+    let temp_merged = tempfile::Builder::new()
+        .prefix("pdf-handouts-merged-")
+        .suffix(".pdf")
+        .tempfile()?;
 
     eprintln!("Step 1: Merging {} PDF files...", inputs.len());
 
-    let merge_options = MergeOptions {
-        input_paths: inputs,
-        output_path: temp_merged.clone(),
-    };
+    // Encrypted in step 2, when the final file is written
+    let merge_options = merge.merge_options(inputs, temp_merged.path().to_path_buf(), passwords.parse()?)?;
+    let report = merge_pdfs(&merge_options)?;
+    print_merge_report(&report);
 
-    merge_pdfs(&merge_options)?;
-
-    // Parse date expression
-    let resolved_date = date.as_deref()
-        .and_then(|d| parse_date_expression(d).ok())
-        .and_then(|expr| resolve_date(&expr));
-
-    // Parse font specifications
-    let base_font = font.as_deref().map(FontSpec::parse);
-    let header_spec = header_font.as_deref().map(FontSpec::parse).or_else(|| base_font.clone());
-    let footer_spec = footer_font.as_deref().map(FontSpec::parse).or(base_font);
-
-    // Build mask options
-    let mask = MaskOptions {
-        header_height: mask_header,
-        footer_height: mask_footer,
-        header_all_height: mask_header_all,
-        footer_all_height: mask_footer_all,
-        color: parse_mask_color(&mask_color),
+    let mut options = HeaderFooterOptions {
+        // The merged file is already decrypted
+        password: None,
+        encryption: encryption.output_encryption()?,
+        ..headers.header_footer_options()?
     };
 
-    // Build options
-    let options = HeaderFooterOptions {
-        title,
-        footer_left,
-        footer_center,
-        footer_right,
-        date: resolved_date,
-        show_page_numbers: false,
-        show_total_page_count: false,
-        title_font_size: header_spec.as_ref().and_then(|f| f.size).unwrap_or(24.0),
-        footer_font_size: footer_spec.as_ref().and_then(|f| f.size).unwrap_or(14.0),
-        header_font: header_spec,
-        footer_font: footer_spec,
-        mask,
-    };
+    // Table of contents from where each input landed in the merge
+    options.toc = front_matter.toc.map(|heading| TocOptions {
+        heading,
+        blank_back: merge.duplex,
+        ..TocOptions::from_sections(&report.sections)
+    });
+
+    // With a cover page, the title and subtitle go on the cover instead of page 1
+    if front_matter.cover {
+        options.cover = Some(CoverOptions {
+            title: options.title.take(),
+            subtitle: options.subtitle.take(),
+            presenter: front_matter.presenter,
+            date: options.date,
+            logo: front_matter.logo,
+            blank_back: merge.duplex,
+        });
+    }
 
     eprintln!("Step 2: Adding headers/footers...");
-    add_headers_footers(&temp_merged, &output, &options)?;
-
-    // Clean up temp file
-    let _ = std::fs::remove_file(&temp_merged);
+    add_headers_footers(temp_merged.path(), &output, &options)?;
 
     eprintln!("Output: {}", output.display());
 
@@ -549,12 +860,13 @@ fn cmd_build(
 }
 
 /// Show information about a PDF
-fn cmd_info(input: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_info(input: PathBuf, passwords: PasswordArgs) -> Result<(), Box<dyn std::error::Error>> {
     if !input.exists() {
         return Err(format!("Input file not found: {}", input.display()).into());
     }
 
-    let metadata = pdf_handouts::pdf::extract_metadata(&input)?;
+    let passwords = passwords.parse()?;
+    let metadata = pdf_handouts::pdf::extract_metadata_with_password(&input, password_for(&passwords, &input))?;
 
     println!("File: {}", input.display());
     println!("Pages: {}", metadata.page_count);
@@ -37,6 +37,30 @@ pub enum Error {
     #[error("PDF has no pages: {}", .0.display())]
     EmptyPdf(PathBuf),
 
+    /// Encrypted PDF that could not be decrypted (no password, or a wrong one)
+    #[error("PDF is encrypted and needs a password: {}", .0.display())]
+    Encrypted(PathBuf),
+
+    /// Invalid color specification
+    #[error("Invalid color: {0}")]
+    InvalidColor(String),
+
+    /// Invalid page selection
+    #[error("Invalid page selection: {0}")]
+    InvalidPageSelection(String),
+
+    /// Invalid stamp specification
+    #[error("Invalid stamp: {0}")]
+    InvalidStamp(String),
+
+    /// Invalid length (e.g. "1in", "2.5cm")
+    #[error("Invalid length: {0}")]
+    InvalidLength(String),
+
+    /// Invalid paper size (e.g. "a4", "8.5x11in")
+    #[error("Invalid paper size: {0}")]
+    InvalidPaperSize(String),
+
     /// Font error
     #[error("Font error: {0}")]
     Font(String),
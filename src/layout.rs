@@ -1,5 +1,7 @@
 //! Page layout calculations
 
+use crate::error::{Error, Result};
+
 /// Simple length type in millimeters
 /// We'll integrate with krilla's types when implementing PDF creation
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -16,6 +18,33 @@ impl Length {
         Length(inches * 25.4)
     }
 
+    /// Create a length from points (1/72 inch)
+    pub fn from_pt(pt: f64) -> Self {
+        Length(pt * 25.4 / 72.0)
+    }
+
+    /// Parse a length with an optional unit: `pt` (default), `in`, `mm` or `cm`
+    ///
+    /// Examples: `"72"`, `"1in"`, `"2.5cm"`, `"-10mm"`
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let split = value
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+        let number: f64 = number
+            .trim()
+            .parse()
+            .map_err(|_| Error::InvalidLength(value.to_string()))?;
+        match unit.to_lowercase().as_str() {
+            "" | "pt" => Ok(Length::from_pt(number)),
+            "in" => Ok(Length::from_inches(number)),
+            "mm" => Ok(Length::from_mm(number)),
+            "cm" => Ok(Length::from_mm(number * 10.0)),
+            _ => Err(Error::InvalidLength(value.to_string())),
+        }
+    }
+
     /// Get the value in millimeters
     pub fn mm(&self) -> f64 {
         self.0
@@ -50,6 +79,78 @@ impl PageDimensions {
             height: Length::from_mm(297.0),
         }
     }
+
+    /// US Legal size (8.5" × 14")
+    pub fn legal() -> Self {
+        Self {
+            width: Length::from_inches(8.5),
+            height: Length::from_inches(14.0),
+        }
+    }
+
+    /// Tabloid size (11" × 17")
+    pub fn tabloid() -> Self {
+        Self {
+            width: Length::from_inches(11.0),
+            height: Length::from_inches(17.0),
+        }
+    }
+
+    /// Half Letter size (5.5" × 8.5")
+    pub fn half_letter() -> Self {
+        Self {
+            width: Length::from_inches(5.5),
+            height: Length::from_inches(8.5),
+        }
+    }
+
+    /// A3 size (297mm × 420mm)
+    pub fn a3() -> Self {
+        Self {
+            width: Length::from_mm(297.0),
+            height: Length::from_mm(420.0),
+        }
+    }
+
+    /// A5 size (148mm × 210mm)
+    pub fn a5() -> Self {
+        Self {
+            width: Length::from_mm(148.0),
+            height: Length::from_mm(210.0),
+        }
+    }
+
+    /// Parse a paper size: a name (`letter`, `legal`, `tabloid`, `half-letter`,
+    /// `a3`, `a4`, `a5`) or `WxH` with units (`8.5x11in`, `210mmx297mm`).
+    /// A unit given only on the height applies to both sides.
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPaperSize(value.to_string());
+        let name = value.trim().to_lowercase();
+        let named = match name.as_str() {
+            "letter" => Some(Self::letter()),
+            "legal" => Some(Self::legal()),
+            "tabloid" => Some(Self::tabloid()),
+            "half-letter" | "halfletter" | "statement" => Some(Self::half_letter()),
+            "a3" => Some(Self::a3()),
+            "a4" => Some(Self::a4()),
+            "a5" => Some(Self::a5()),
+            _ => None,
+        };
+        if let Some(dimensions) = named {
+            return Ok(dimensions);
+        }
+
+        let (width, height) = name.split_once('x').ok_or_else(invalid)?;
+        let (width, height) = (width.trim(), height.trim());
+        let unit = height.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let width = if width.parse::<f64>().is_ok() { format!("{}{}", width, unit) } else { width.to_string() };
+        let width = Length::parse(&width).map_err(|_| invalid())?;
+        let height = Length::parse(height).map_err(|_| invalid())?;
+        if width.pt() <= 0.0 || height.pt() <= 0.0 {
+            return Err(invalid());
+        }
+        Ok(Self { width, height })
+    }
 }
 
 /// Margins for page content
@@ -122,6 +223,33 @@ mod tests {
         assert!((len.pt() - 72.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_length_parse() {
+        assert!((Length::parse("1in").unwrap().pt() - 72.0).abs() < 0.001);
+        assert!((Length::parse("36").unwrap().pt() - 36.0).abs() < 0.001);
+        assert!((Length::parse("2.5cm").unwrap().mm() - 25.0).abs() < 0.001);
+        assert!((Length::parse("-10mm").unwrap().mm() + 10.0).abs() < 0.001);
+        assert!(Length::parse("1ft").is_err());
+        assert!(Length::parse("in").is_err());
+    }
+
+    #[test]
+    fn test_paper_size_parse() {
+        let legal = PageDimensions::parse("Legal").unwrap();
+        assert!((legal.height.pt() - 1008.0).abs() < 0.01);
+        let a5 = PageDimensions::parse("a5").unwrap();
+        assert!((a5.width.mm() - 148.0).abs() < 0.01);
+        let custom = PageDimensions::parse("8.5x11in").unwrap();
+        assert!((custom.width.pt() - 612.0).abs() < 0.01);
+        assert!((custom.height.pt() - 792.0).abs() < 0.01);
+        let mixed = PageDimensions::parse("210mm x 11in").unwrap();
+        assert!((mixed.width.mm() - 210.0).abs() < 0.01);
+        assert!((mixed.height.pt() - 792.0).abs() < 0.01);
+        for value in ["b5", "8.5", "0x11in", "axb"] {
+            assert!(PageDimensions::parse(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+
     #[test]
     fn test_letter_size() {
         let letter = PageDimensions::letter();
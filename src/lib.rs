@@ -7,6 +7,7 @@
 //! - Create watermark PDFs with headers and footers
 //! - Parse flexible date expressions
 //! - Calculate page layouts
+//! - Select pages by number and range
 //!
 //! # Example
 //!
@@ -20,6 +21,7 @@
 //!         PathBuf::from("2. advanced.pdf"),
 //!     ],
 //!     output_path: PathBuf::from("merged.pdf"),
+//!     ..Default::default()
 //! };
 //!
 //! merge_pdfs(&options).expect("Failed to merge PDFs");
@@ -29,6 +31,7 @@ pub mod error;
 pub mod pdf;
 pub mod date;
 pub mod layout;
+pub mod pages;
 
 // Re-export commonly used items
 pub use error::{Error, Result};
@@ -0,0 +1,233 @@
+//! Page selections
+//!
+//! A page selection is a comma-separated list of page numbers and ranges,
+//! counted from 1: `4`, `1-3,7`, `5-` (to the end), `last`, `2-last`,
+//! `odd`, `even` or `all`. Negative numbers count from the end: `-1` is the
+//! last page and `2--2` runs from page 2 to the second-to-last page.
+//!
+//! Merge inputs can carry a selection after a colon: `article.pdf:1-3,9-`.
+
+use crate::error::{Error, Result};
+
+/// One end of a page range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageRef {
+    /// A page number, counted from 1
+    Number(usize),
+    /// The last page
+    Last,
+    /// A page counted from the end: 1 is the last page
+    FromEnd(usize),
+}
+
+impl PageRef {
+    /// Page number counted from 1 (0 if the page is before the first page)
+    fn resolve(self, page_count: usize) -> usize {
+        match self {
+            PageRef::Number(n) => n,
+            PageRef::Last => page_count,
+            PageRef::FromEnd(n) => (page_count + 1).saturating_sub(n),
+        }
+    }
+
+    fn parse(value: &str, spec: &str) -> Result<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("last") {
+            return Ok(PageRef::Last);
+        }
+        let (from_end, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        match digits.parse::<usize>() {
+            Ok(n) if n >= 1 && from_end => Ok(PageRef::FromEnd(n)),
+            Ok(n) if n >= 1 => Ok(PageRef::Number(n)),
+            _ => Err(Error::InvalidPageSelection(spec.to_string())),
+        }
+    }
+
+    /// Split one range item into its ends (`3`, `-1`, `2-5`, `5-`, `2--1`)
+    fn parse_range(part: &str, spec: &str) -> Result<PageRange> {
+        // The separator is the first '-' after the first character, so a
+        // leading '-' stays with the start
+        let split = part.char_indices().skip(1).find(|&(_, c)| c == '-').map(|(i, _)| i);
+        let range = match split {
+            None => {
+                let page = PageRef::parse(part, spec)?;
+                PageRange::Range(page, page)
+            }
+            Some(i) => {
+                let (start, end) = (&part[..i], &part[i + 1..]);
+                let start = PageRef::parse(start, spec)?;
+                if end.trim().is_empty() {
+                    PageRange::Range(start, PageRef::Last)
+                } else {
+                    PageRange::Range(start, PageRef::parse(end, spec)?)
+                }
+            }
+        };
+        Ok(range)
+    }
+}
+
+/// One item of a page selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageRange {
+    /// Every page
+    All,
+    /// Odd-numbered pages
+    Odd,
+    /// Even-numbered pages
+    Even,
+    /// Pages `start..=end`
+    Range(PageRef, PageRef),
+}
+
+/// A set of pages, resolved against the page count when used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSelection {
+    pub ranges: Vec<PageRange>,
+}
+
+impl Default for PageSelection {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl PageSelection {
+    /// Select every page
+    pub fn all() -> Self {
+        Self { ranges: vec![PageRange::All] }
+    }
+
+    /// Select a single page
+    pub fn page(number: usize) -> Self {
+        Self { ranges: vec![PageRange::Range(PageRef::Number(number), PageRef::Number(number))] }
+    }
+
+    /// Parse a page selection such as `1-3,5,last`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut ranges = Vec::new();
+        for part in spec.split(',').map(str::trim) {
+            if part.is_empty() {
+                return Err(Error::InvalidPageSelection(spec.to_string()));
+            }
+            let range = match part.to_lowercase().as_str() {
+                "all" => PageRange::All,
+                "odd" => PageRange::Odd,
+                "even" => PageRange::Even,
+                _ => PageRef::parse_range(part, spec)?,
+            };
+            ranges.push(range);
+        }
+        Ok(Self { ranges })
+    }
+
+    /// Whether a page (counted from 1) is selected in a document of `page_count` pages
+    pub fn contains(&self, page: usize, page_count: usize) -> bool {
+        page >= 1
+            && page <= page_count
+            && self.ranges.iter().any(|range| match *range {
+                PageRange::All => true,
+                PageRange::Odd => !page.is_multiple_of(2),
+                PageRange::Even => page.is_multiple_of(2),
+                PageRange::Range(start, end) => {
+                    (start.resolve(page_count)..=end.resolve(page_count)).contains(&page)
+                }
+            })
+    }
+
+    /// Selected page numbers in selection order (ranges may repeat pages)
+    pub fn pages(&self, page_count: usize) -> Vec<usize> {
+        let mut pages = Vec::new();
+        for range in &self.ranges {
+            match *range {
+                PageRange::All => pages.extend(1..=page_count),
+                PageRange::Odd => pages.extend((1..=page_count).step_by(2)),
+                PageRange::Even => pages.extend((2..=page_count).step_by(2)),
+                PageRange::Range(start, end) => {
+                    let start = start.resolve(page_count).max(1);
+                    let end = end.resolve(page_count).min(page_count);
+                    pages.extend(start..=end);
+                }
+            }
+        }
+        pages
+    }
+
+    /// Whether every page is selected, whatever the page count
+    pub fn is_all(&self) -> bool {
+        self.ranges.contains(&PageRange::All)
+    }
+
+    /// Split an input such as `article.pdf:1-3,7` into the path and its
+    /// selection. Without a selection suffix the whole input is the path.
+    pub fn split_input(input: &str) -> Result<(&str, Option<Self>)> {
+        if let Some((path, spec)) = input.rsplit_once(':') {
+            // Only a `.pdf` path takes a selection; anything else (such as a
+            // Windows drive letter) is part of the path
+            if path.to_lowercase().ends_with(".pdf") && !std::path::Path::new(input).exists() {
+                return Ok((path, Some(Self::parse(spec)?)));
+            }
+        }
+        Ok((input, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_contains() {
+        let selection = PageSelection::parse("1-3, 7, last").unwrap();
+        let selected: Vec<usize> = (1..=10).filter(|&p| selection.contains(p, 10)).collect();
+        assert_eq!(selected, vec![1, 2, 3, 7, 10]);
+
+        let selection = PageSelection::parse("5-").unwrap();
+        assert!(!selection.contains(4, 8));
+        assert!(selection.contains(8, 8));
+        assert!(!selection.contains(9, 8));
+
+        let even = PageSelection::parse("even").unwrap();
+        assert_eq!(even.pages(5), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_pages_keep_selection_order() {
+        let selection = PageSelection::parse("3,1-2,last").unwrap();
+        assert_eq!(selection.pages(4), vec![3, 1, 2, 4]);
+        // Pages past the end are dropped
+        assert_eq!(PageSelection::parse("2-9").unwrap().pages(3), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_pages_from_end() {
+        assert_eq!(PageSelection::parse("-1").unwrap().pages(5), vec![5]);
+        assert_eq!(PageSelection::parse("2--2").unwrap().pages(5), vec![2, 3, 4]);
+        assert_eq!(PageSelection::parse("-2-").unwrap().pages(5), vec![4, 5]);
+        // Counting past the first page selects nothing
+        assert!(PageSelection::parse("-9").unwrap().pages(5).is_empty());
+        assert!(!PageSelection::parse("-9").unwrap().contains(1, 5));
+        assert!(PageSelection::parse("-1").unwrap().contains(5, 5));
+    }
+
+    #[test]
+    fn test_split_input() {
+        let (path, selection) = PageSelection::split_input("article.pdf:1-3,7,9-").unwrap();
+        assert_eq!(path, "article.pdf");
+        assert_eq!(selection.unwrap().pages(10), vec![1, 2, 3, 7, 9, 10]);
+
+        assert_eq!(PageSelection::split_input("handout.pdf").unwrap(), ("handout.pdf", None));
+        assert_eq!(PageSelection::split_input("C:\\docs\\a.pdf").unwrap(), ("C:\\docs\\a.pdf", None));
+        assert!(PageSelection::split_input("article.pdf:x").is_err());
+    }
+
+    #[test]
+    fn test_invalid_selections() {
+        for spec in ["", "0", "a-b", "1,,2", "3-x", "-0", "--1"] {
+            assert!(PageSelection::parse(spec).is_err(), "{:?} should be rejected", spec);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Blank pages inserted for double-sided printing
+//!
+//! Inserted pages copy the size and rotation of the page before them and are
+//! marked in their `/PieceInfo` dictionary, so that adding headers and footers
+//! later (even as a separate `headers` run) can leave them out or give them a
+//! notice instead.
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+
+/// Our key in `/PieceInfo`
+const PIECE_INFO_KEY: &[u8] = b"PdfHandouts";
+
+/// Page attributes a blank copies from its neighbour
+const SIZE_ATTRIBUTES: [&[u8]; 4] = [b"MediaBox", b"CropBox", b"Rotate", b"UserUnit"];
+
+/// A marked blank page with the same size and rotation as `neighbour`
+///
+/// The page has no `/Parent`; the caller puts it into a page tree.
+pub(crate) fn blank_page_like(neighbour: &Dictionary) -> Dictionary {
+    let modified = Object::string_literal(chrono::Utc::now().format("D:%Y%m%d%H%M%SZ").to_string());
+    let mut page = dictionary! {
+        "Type" => "Page",
+        "Resources" => Dictionary::new(),
+        "LastModified" => modified.clone(),
+        "PieceInfo" => dictionary! {
+            PIECE_INFO_KEY => dictionary! {
+                "LastModified" => modified,
+                "Private" => dictionary! { "Blank" => true },
+            },
+        },
+    };
+    for key in SIZE_ATTRIBUTES {
+        if let Ok(value) = neighbour.get(key) {
+            page.set(key, value.clone());
+        }
+    }
+    if page.get(b"MediaBox").is_err() {
+        page.set("MediaBox", vec![0.into(), 0.into(), 612.into(), 792.into()]);
+    }
+    page
+}
+
+/// A one-page document holding a blank like [`blank_page_like`], for `insert_pages`
+pub(crate) fn blank_document_like(neighbour: &Dictionary) -> Document {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut page = blank_page_like(neighbour);
+    page.set("Parent", pages_id);
+    let page_id = doc.add_object(page);
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![page_id.into()],
+        "Count" => 1,
+    }));
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+/// Whether a page is a blank inserted by [`blank_page_like`]
+pub(crate) fn is_inserted_blank(doc: &Document, page_id: ObjectId) -> bool {
+    let marker = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"PieceInfo"))
+        .and_then(|info| doc.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+        .and_then(|info| info.get(PIECE_INFO_KEY))
+        .and_then(|data| doc.dereference(data))
+        .and_then(|(_, data)| data.as_dict())
+        .and_then(|data| data.get(b"Private"))
+        .and_then(|private| private.as_dict())
+        .and_then(|private| private.get(b"Blank"));
+    matches!(marker, Ok(Object::Boolean(true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blank_page_copies_size_and_is_marked() {
+        let neighbour = dictionary! {
+            "Type" => "Page",
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Rotate" => 90,
+            "Contents" => (7, 0),
+        };
+        let mut doc = Document::with_version("1.7");
+        let blank_id = doc.add_object(blank_page_like(&neighbour));
+        let content_page = doc.add_object(neighbour);
+
+        let blank = doc.get_dictionary(blank_id).unwrap();
+        assert_eq!(blank.get(b"MediaBox").unwrap(), doc.get_dictionary(content_page).unwrap().get(b"MediaBox").unwrap());
+        assert_eq!(blank.get(b"Rotate").unwrap().as_i64().unwrap(), 90);
+        assert!(blank.get(b"Contents").is_err());
+
+        assert!(is_inserted_blank(&doc, blank_id));
+        assert!(!is_inserted_blank(&doc, content_page));
+    }
+}
@@ -0,0 +1,349 @@
+//! Page canvas changes: extra margin bands and paper size normalization
+//!
+//! Instead of shrinking content to make room for headers and footers, the
+//! page itself can grow: bands are added above and below the visible area so
+//! the header and footer live in new white space. Pages can also be moved
+//! onto a common paper size.
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::error::{Error, Result};
+use crate::layout::{Length, PageDimensions};
+
+use super::geometry::{Matrix, PageGeometry, Rect};
+use super::headers::count_graphics_state_imbalance;
+
+/// How page content is placed on a new paper size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaperScaling {
+    /// Scale (up or down) to fit the paper, keeping the aspect ratio, and center
+    #[default]
+    Fit,
+    /// Keep the original size and center it; the rest is padding
+    None,
+}
+
+/// Page canvas options
+#[derive(Debug, Clone, Copy)]
+pub struct CanvasOptions {
+    /// Band added above the visible page
+    pub top: Length,
+    /// Band added below the visible page
+    pub bottom: Length,
+    /// Normalize every page to this paper size (None = keep each page's size).
+    /// The paper is turned to match each page's orientation.
+    pub paper: Option<PageDimensions>,
+    /// How content is placed on the paper (only used with `paper`)
+    pub scaling: PaperScaling,
+}
+
+impl Default for CanvasOptions {
+    fn default() -> Self {
+        Self {
+            top: Length::from_mm(0.0),
+            bottom: Length::from_mm(0.0),
+            paper: None,
+            scaling: PaperScaling::default(),
+        }
+    }
+}
+
+/// Apply canvas options to a page
+///
+/// Without a paper size, the page's MediaBox and CropBox grow by the top and
+/// bottom bands (as displayed, after `/Rotate`) and the content stays where
+/// it is. With a paper size, the page gets fresh boxes of that size and its
+/// content (and annotation rectangles) are transformed into the area between
+/// the bands. Inherited boxes are resolved and written onto the page.
+pub fn apply_canvas(doc: &mut Document, page_id: ObjectId, options: &CanvasOptions) -> Result<()> {
+    let geometry = PageGeometry::from_page(doc, page_id)?;
+    let (top, bottom) = (options.top.pt() as f32, options.bottom.pt() as f32);
+    let (width, height) = (geometry.display_width(), geometry.display_height());
+
+    let Some(paper) = options.paper else {
+        // Grow the visible box in display coordinates, then map it back to user space
+        let display = Rect::new(0.0, -bottom, width, height + top);
+        let crop_box = geometry.display_matrix().apply_rect(&display);
+        let media_box = Rect::new(
+            geometry.media_box.x0.min(crop_box.x0),
+            geometry.media_box.y0.min(crop_box.y0),
+            geometry.media_box.x1.max(crop_box.x1),
+            geometry.media_box.y1.max(crop_box.y1),
+        );
+        set_page_boxes(doc, page_id, media_box, crop_box)?;
+        return Ok(());
+    };
+
+    // Turn the paper to match the page's orientation
+    let (mut paper_width, mut paper_height) = (paper.width.pt() as f32, paper.height.pt() as f32);
+    if geometry.is_landscape() != (paper_width > paper_height) {
+        std::mem::swap(&mut paper_width, &mut paper_height);
+    }
+
+    let area = Rect {
+        x0: 0.0,
+        y0: bottom,
+        x1: paper_width,
+        y1: paper_height - top,
+    };
+    if area.width() <= 0.0 || area.height() <= 0.0 {
+        return Err(Error::General(format!(
+            "Margin bands ({} + {} pt) leave no room on a {} x {} pt page",
+            top, bottom, paper_width, paper_height
+        )));
+    }
+
+    let scale = match options.scaling {
+        PaperScaling::Fit => (area.width() / width).min(area.height() / height),
+        PaperScaling::None => 1.0,
+    };
+    let placement = Matrix([
+        scale,
+        0.0,
+        0.0,
+        scale,
+        area.x0 + (area.width() - width * scale) / 2.0,
+        area.y0 + (area.height() - height * scale) / 2.0,
+    ]);
+
+    // New boxes start at the origin; sideways pages keep their /Rotate, so
+    // their user space is the paper turned by a quarter
+    let new_box = if geometry.is_rotated_sideways() {
+        Rect::new(0.0, 0.0, paper_height, paper_width)
+    } else {
+        Rect::new(0.0, 0.0, paper_width, paper_height)
+    };
+    let new_geometry = PageGeometry {
+        media_box: new_box,
+        crop_box: new_box,
+        rotation: geometry.rotation,
+    };
+
+    // Old user space → old display → placed on the paper → new user space
+    let transform = geometry.display_matrix().inverse()
+        .then(&placement)
+        .then(&new_geometry.display_matrix());
+
+    transform_page_content(doc, page_id, transform)?;
+    transform_annotations(doc, page_id, transform)?;
+    set_page_boxes(doc, page_id, new_box, new_box)?;
+
+    // Other boxes no longer match the content; drop them rather than mislead
+    let page = doc.get_dictionary_mut(page_id)?;
+    for key in [b"TrimBox".as_slice(), b"BleedBox", b"ArtBox"] {
+        page.remove(key);
+    }
+    Ok(())
+}
+
+/// Write MediaBox and CropBox directly onto the page
+fn set_page_boxes(doc: &mut Document, page_id: ObjectId, media_box: Rect, crop_box: Rect) -> Result<()> {
+    let page = doc.get_dictionary_mut(page_id)?;
+    page.set("MediaBox", media_box.to_object());
+    page.set("CropBox", crop_box.to_object());
+    Ok(())
+}
+
+/// Wrap the page's content streams in `q <matrix> cm ... Q`
+fn transform_page_content(doc: &mut Document, page_id: ObjectId, matrix: Matrix) -> Result<()> {
+    let contents = match doc.get_dictionary(page_id)?.get(b"Contents") {
+        Ok(Object::Array(items)) => items.clone(),
+        Ok(item @ Object::Reference(_)) => vec![item.clone()],
+        _ => Vec::new(),
+    };
+
+    // Close any graphics states the original content leaves open
+    let imbalance: i32 = contents.iter()
+        .filter_map(|item| item.as_reference().ok())
+        .filter_map(|id| doc.get_object(id).and_then(Object::as_stream).ok())
+        .map(|stream| {
+            let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+            count_graphics_state_imbalance(&content)
+        })
+        .sum();
+
+    let open = doc.add_object(Stream::new(
+        Dictionary::new(),
+        format!("q {} cm\n", matrix.to_operands()).into_bytes(),
+    ));
+    let close = doc.add_object(Stream::new(
+        Dictionary::new(),
+        "Q\n".repeat(imbalance.max(0) as usize + 1).into_bytes(),
+    ));
+
+    let mut new_contents = vec![Object::Reference(open)];
+    new_contents.extend(contents);
+    new_contents.push(Object::Reference(close));
+    doc.get_dictionary_mut(page_id)?.set("Contents", Object::Array(new_contents));
+    Ok(())
+}
+
+/// Annotation keys holding flat lists of x, y coordinates
+const POINT_LIST_KEYS: [&[u8]; 4] = [b"QuadPoints", b"Vertices", b"L", b"CL"];
+
+/// Move annotation rectangles and point lists along with the content
+pub(crate) fn transform_annotations(doc: &mut Document, page_id: ObjectId, matrix: Matrix) -> Result<()> {
+    let annots = match doc.get_dictionary(page_id)?.get(b"Annots") {
+        Ok(Object::Array(items)) => items.clone(),
+        Ok(Object::Reference(id)) => match doc.get_object(*id) {
+            Ok(Object::Array(items)) => items.clone(),
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    for annot in annots {
+        let Ok(annot_id) = annot.as_reference() else { continue };
+        let rect = match doc.get_dictionary(annot_id).and_then(|a| a.get(b"Rect")) {
+            Ok(object) => Rect::from_object(doc, &object.clone()),
+            Err(_) => None,
+        };
+        if let Some(rect) = rect {
+            doc.get_dictionary_mut(annot_id)?.set("Rect", matrix.apply_rect(&rect).to_object());
+        }
+
+        let annot = doc.get_dictionary_mut(annot_id)?;
+        for key in POINT_LIST_KEYS {
+            let points = match annot.get(key) {
+                Ok(Object::Array(items)) => transform_points(&matrix, items),
+                _ => None,
+            };
+            if let Some(points) = points {
+                annot.set(key, points);
+            }
+        }
+        // Ink annotations have one point list per stroke
+        let strokes = match annot.get(b"InkList") {
+            Ok(Object::Array(strokes)) => strokes
+                .iter()
+                .map(|stroke| stroke.as_array().ok().and_then(|points| transform_points(&matrix, points)).map(Object::Array))
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+        if let Some(strokes) = strokes {
+            annot.set("InkList", strokes);
+        }
+    }
+    Ok(())
+}
+
+/// Transform a flat list of x, y coordinates (None if it holds anything else)
+fn transform_points(matrix: &Matrix, items: &[Object]) -> Option<Vec<Object>> {
+    if !items.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut points = Vec::with_capacity(items.len());
+    for pair in items.chunks(2) {
+        let (x, y) = matrix.apply(pair[0].as_float().ok()?, pair[1].as_float().ok()?);
+        points.push(Object::Real(x));
+        points.push(Object::Real(y));
+    }
+    Some(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// A page inheriting its MediaBox (with a non-zero origin) from the page tree
+    fn document_with_inherited_box(rotation: i64) -> (Document, ObjectId) {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), b"q 1 0 0 1 0 0 cm\n".to_vec()));
+        let annot_id = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => vec![110.into(), 120.into(), 210.into(), 140.into()],
+        });
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Rotate" => rotation,
+            "Contents" => content_id,
+            "Annots" => vec![annot_id.into()],
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "MediaBox" => vec![100.into(), 100.into(), 400.into(), 500.into()],
+        }));
+        (doc, page_id)
+    }
+
+    #[test]
+    fn test_extend_grows_boxes_in_display_orientation() {
+        let options = CanvasOptions {
+            top: Length::from_pt(40.0),
+            bottom: Length::from_pt(30.0),
+            ..Default::default()
+        };
+
+        let (mut doc, page_id) = document_with_inherited_box(0);
+        apply_canvas(&mut doc, page_id, &options).unwrap();
+        let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+        assert!((geometry.crop_box.y0 - 70.0).abs() < 1e-3);
+        assert!((geometry.crop_box.y1 - 540.0).abs() < 1e-3);
+        assert!((geometry.media_box.y1 - 540.0).abs() < 1e-3);
+        assert_eq!(geometry.crop_box.x0, 100.0);
+
+        // Rotated a quarter turn, the displayed top is the user-space left edge
+        let (mut doc, page_id) = document_with_inherited_box(90);
+        apply_canvas(&mut doc, page_id, &options).unwrap();
+        let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+        assert!((geometry.crop_box.x0 - 60.0).abs() < 1e-3);
+        assert!((geometry.crop_box.x1 - 430.0).abs() < 1e-3);
+        assert_eq!(geometry.crop_box.y0, 100.0);
+        assert!((geometry.display_height() - 370.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_normalize_to_paper_with_bands() {
+        let options = CanvasOptions {
+            top: Length::from_pt(72.0),
+            bottom: Length::from_pt(72.0),
+            paper: Some(PageDimensions::letter()),
+            scaling: PaperScaling::Fit,
+        };
+        let (mut doc, page_id) = document_with_inherited_box(0);
+        apply_canvas(&mut doc, page_id, &options).unwrap();
+
+        let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+        assert!((geometry.media_box.width() - 612.0).abs() < 0.01);
+        assert!((geometry.media_box.height() - 792.0).abs() < 0.01);
+        assert_eq!(geometry.media_box.x0, 0.0);
+
+        // 300 x 400 content scaled into 612 x 648: height-limited at 1.62
+        let page = doc.get_dictionary(page_id).unwrap();
+        let contents = page.get(b"Contents").unwrap().as_array().unwrap();
+        let open = doc.get_object(contents[0].as_reference().unwrap()).unwrap().as_stream().unwrap();
+        let ops = String::from_utf8_lossy(&open.content).to_string();
+        let operands: Vec<f32> = ops.trim_start_matches("q ").trim_end_matches(" cm\n")
+            .split(' ').map(|v| v.parse().unwrap()).collect();
+        assert!((operands[0] - 1.62).abs() < 1e-4, "{}", ops);
+
+        // The original content left one q open, so two Q close the wrapper
+        let close = doc.get_object(contents[2].as_reference().unwrap()).unwrap().as_stream().unwrap();
+        assert_eq!(close.content, b"Q\nQ\n");
+
+        // The link moved with the content
+        let annot_id = page.get(b"Annots").unwrap().as_array().unwrap()[0].as_reference().unwrap();
+        let rect = Rect::from_object(&doc, doc.get_dictionary(annot_id).unwrap().get(b"Rect").unwrap()).unwrap();
+        let expected_x0 = (612.0 - 300.0 * 1.62) / 2.0 + 10.0 * 1.62;
+        assert!((rect.x0 - expected_x0).abs() < 0.01, "{:?}", rect);
+        assert!((rect.y0 - (72.0 + 20.0 * 1.62)).abs() < 0.01, "{:?}", rect);
+    }
+
+    #[test]
+    fn test_bands_larger_than_paper() {
+        let options = CanvasOptions {
+            top: Length::from_inches(6.0),
+            bottom: Length::from_inches(6.0),
+            paper: Some(PageDimensions::letter()),
+            scaling: PaperScaling::None,
+        };
+        let (mut doc, page_id) = document_with_inherited_box(0);
+        assert!(apply_canvas(&mut doc, page_id, &options).is_err());
+    }
+}
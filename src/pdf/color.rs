@@ -0,0 +1,450 @@
+//! Color values for text, masks and decorations
+//!
+//! Colors can be given in any of the PDF device color spaces (DeviceGray,
+//! DeviceRGB, DeviceCMYK) with an optional opacity. Print shops prefer pure
+//! CMYK black (`cmyk(0,0,0,1)`) for crisp text.
+
+use crate::error::{Error, Result};
+
+/// Color components in one of the PDF device color spaces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorValue {
+    /// DeviceGray level (0.0 = black, 1.0 = white)
+    Gray(f32),
+    /// DeviceRGB components (0.0-1.0 each)
+    Rgb(f32, f32, f32),
+    /// DeviceCMYK components (0.0-1.0 each)
+    Cmyk(f32, f32, f32, f32),
+}
+
+/// A device color with opacity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    /// Color components
+    pub value: ColorValue,
+    /// Opacity from 0.0 (transparent) to 1.0 (opaque), applied through an ExtGState
+    pub alpha: f32,
+}
+
+impl Color {
+    /// Opaque black (DeviceGray)
+    pub const BLACK: Color = Color { value: ColorValue::Gray(0.0), alpha: 1.0 };
+
+    /// Opaque white (DeviceGray)
+    pub const WHITE: Color = Color { value: ColorValue::Gray(1.0), alpha: 1.0 };
+
+    /// Create an opaque DeviceGray color
+    pub fn gray(level: f32) -> Self {
+        Self { value: ColorValue::Gray(level), alpha: 1.0 }
+    }
+
+    /// Create an opaque DeviceRGB color (0.0-1.0 for each component)
+    pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { value: ColorValue::Rgb(r, g, b), alpha: 1.0 }
+    }
+
+    /// Create an opaque DeviceCMYK color (0.0-1.0 for each component)
+    pub fn cmyk(c: f32, m: f32, y: f32, k: f32) -> Self {
+        Self { value: ColorValue::Cmyk(c, m, y, k), alpha: 1.0 }
+    }
+
+    /// Return the same color with a different opacity
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self { alpha, ..self }
+    }
+
+    /// Parse a color string
+    ///
+    /// Supported formats:
+    /// - `#rgb`, `#rrggbb`, `#rrggbbaa` (hex RGB, optional alpha)
+    /// - `gray(0.5)`, `gray(50%, 0.3)` (DeviceGray, optional alpha)
+    /// - `rgb(51,51,51)`, `rgba(255,0,0,0.5)` (DeviceRGB, 0-255 or percentages)
+    /// - `cmyk(0,0,0,1)`, `cmyk(0%,0%,0%,100%,0.8)` (DeviceCMYK, optional alpha)
+    /// - CSS named colors such as `navy`, `darkslategray` or `rebeccapurple`
+    ///
+    /// Component lists use commas without spaces so a color stays a single
+    /// token inside a font specification.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let trimmed = spec.trim();
+        let lower = trimmed.to_lowercase();
+        let invalid = || Error::InvalidColor(trimmed.to_string());
+
+        if let Some(hex) = lower.strip_prefix('#') {
+            return parse_hex(hex).ok_or_else(invalid);
+        }
+
+        if let Some((name, args)) = split_function(&lower) {
+            return parse_function(name, &args).ok_or_else(invalid);
+        }
+
+        named_color(&lower).ok_or_else(invalid)
+    }
+
+    /// Check whether a token should be read as a color rather than a font family
+    ///
+    /// Hex values and functional forms are always colors (and must then parse),
+    /// as are CSS color names.
+    pub fn is_color_token(token: &str) -> bool {
+        let lower = token.to_lowercase();
+        lower.starts_with('#') || lower.contains('(') || named_color(&lower).is_some()
+    }
+
+    /// Whether the color is fully opaque
+    pub fn is_opaque(&self) -> bool {
+        self.alpha >= 1.0
+    }
+
+    /// PDF operator setting this as the fill (non-stroking) color
+    pub fn fill_operator(&self) -> String {
+        match self.value {
+            ColorValue::Gray(g) => format!("{:.3} g", g),
+            ColorValue::Rgb(r, g, b) => format!("{:.3} {:.3} {:.3} rg", r, g, b),
+            ColorValue::Cmyk(c, m, y, k) => format!("{:.3} {:.3} {:.3} {:.3} k", c, m, y, k),
+        }
+    }
+
+    /// PDF operator setting this as the stroke color
+    pub fn stroke_operator(&self) -> String {
+        match self.value {
+            ColorValue::Gray(g) => format!("{:.3} G", g),
+            ColorValue::Rgb(r, g, b) => format!("{:.3} {:.3} {:.3} RG", r, g, b),
+            ColorValue::Cmyk(c, m, y, k) => format!("{:.3} {:.3} {:.3} {:.3} K", c, m, y, k),
+        }
+    }
+
+    /// Approximate RGB equivalent (0.0-1.0 for each component)
+    ///
+    /// Uses the naive device conversions; good enough for renderers that only
+    /// accept RGB.
+    pub fn to_rgb(&self) -> (f32, f32, f32) {
+        match self.value {
+            ColorValue::Gray(g) => (g, g, g),
+            ColorValue::Rgb(r, g, b) => (r, g, b),
+            ColorValue::Cmyk(c, m, y, k) => (
+                (1.0 - c) * (1.0 - k),
+                (1.0 - m) * (1.0 - k),
+                (1.0 - y) * (1.0 - k),
+            ),
+        }
+    }
+}
+
+impl Default for Color {
+    /// Opaque black, the initial PDF fill color
+    fn default() -> Self {
+        Self::BLACK
+    }
+}
+
+/// Parse hex digits (without `#`): `rgb`, `rrggbb` or `rrggbbaa`
+fn parse_hex(hex: &str) -> Option<Color> {
+    let byte = |s: &str| u8::from_str_radix(s, 16).ok().map(|v| v as f32 / 255.0);
+
+    match hex.len() {
+        3 => {
+            // Short hex: #rgb -> #rrggbb
+            let r = byte(&hex[0..1].repeat(2))?;
+            let g = byte(&hex[1..2].repeat(2))?;
+            let b = byte(&hex[2..3].repeat(2))?;
+            Some(Color::rgb(r, g, b))
+        }
+        6 | 8 => {
+            let r = byte(hex.get(0..2)?)?;
+            let g = byte(hex.get(2..4)?)?;
+            let b = byte(hex.get(4..6)?)?;
+            let alpha = match hex.get(6..8) {
+                Some(a) => byte(a)?,
+                None => 1.0,
+            };
+            Some(Color::rgb(r, g, b).with_alpha(alpha))
+        }
+        _ => None,
+    }
+}
+
+/// Split `name(a,b,c)` into the function name and its arguments
+fn split_function(spec: &str) -> Option<(&str, Vec<&str>)> {
+    let open = spec.find('(')?;
+    let inner = spec[open + 1..].strip_suffix(')')?;
+    let args = inner.split(',').map(str::trim).collect();
+    Some((spec[..open].trim(), args))
+}
+
+/// Parse the functional color forms
+fn parse_function(name: &str, args: &[&str]) -> Option<Color> {
+    let alpha_at = |index: usize| match args.get(index) {
+        Some(a) => parse_unit(a),
+        None => Some(1.0),
+    };
+
+    match (name, args.len()) {
+        ("gray" | "grey", 1 | 2) => {
+            Some(Color::gray(parse_unit(args[0])?).with_alpha(alpha_at(1)?))
+        }
+        ("rgb" | "rgba", 3 | 4) => {
+            let r = parse_byte(args[0])?;
+            let g = parse_byte(args[1])?;
+            let b = parse_byte(args[2])?;
+            Some(Color::rgb(r, g, b).with_alpha(alpha_at(3)?))
+        }
+        ("cmyk", 4 | 5) => {
+            let c = parse_unit(args[0])?;
+            let m = parse_unit(args[1])?;
+            let y = parse_unit(args[2])?;
+            let k = parse_unit(args[3])?;
+            Some(Color::cmyk(c, m, y, k).with_alpha(alpha_at(4)?))
+        }
+        _ => None,
+    }
+}
+
+/// Parse a 0.0-1.0 component, also accepting percentages
+fn parse_unit(value: &str) -> Option<f32> {
+    let v = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => value.parse::<f32>().ok()?,
+    };
+    (0.0..=1.0).contains(&v).then_some(v)
+}
+
+/// Parse a 0-255 component, also accepting percentages
+fn parse_byte(value: &str) -> Option<f32> {
+    if value.ends_with('%') {
+        return parse_unit(value);
+    }
+    let v = value.parse::<f32>().ok()?;
+    (0.0..=255.0).contains(&v).then_some(v / 255.0)
+}
+
+/// Look up a CSS named color
+fn named_color(name: &str) -> Option<Color> {
+    NAMED_COLORS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, rgb)| {
+            let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+            Color::rgb(channel(16), channel(8), channel(0))
+        })
+}
+
+/// CSS Color Module Level 4 named colors
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_color() {
+        // Full hex
+        let (r, g, b) = Color::parse("#ff8800").unwrap().to_rgb();
+        assert!((r - 1.0).abs() < 0.01);
+        assert!((g - 0.533).abs() < 0.01);
+        assert!(b.abs() < 0.01);
+
+        // Short hex
+        let (r, g, b) = Color::parse("#f80").unwrap().to_rgb();
+        assert!((r - 1.0).abs() < 0.01);
+        assert!((g - 0.533).abs() < 0.01);
+        assert!(b.abs() < 0.01);
+
+        // Hex with alpha
+        let color = Color::parse("#00000080").unwrap();
+        assert!((color.alpha - 0.502).abs() < 0.01);
+
+        // Invalid lengths and digits
+        assert!(Color::parse("#12345").is_err()); // Wrong length
+        assert!(Color::parse("#1234567").is_err()); // Too long
+        assert!(Color::parse("#12").is_err()); // Too short
+        assert!(Color::parse("#gggggg").is_err()); // Not hex
+    }
+
+    #[test]
+    fn test_parse_functional_colors() {
+        assert_eq!(Color::parse("gray(0.5)").unwrap(), Color::gray(0.5));
+        assert_eq!(Color::parse("grey(50%)").unwrap(), Color::gray(0.5));
+        assert_eq!(Color::parse("cmyk(0,0,0,1)").unwrap(), Color::cmyk(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(
+            Color::parse("cmyk(0%,100%,0%,0%,0.25)").unwrap(),
+            Color::cmyk(0.0, 1.0, 0.0, 0.0).with_alpha(0.25)
+        );
+        assert_eq!(Color::parse("rgb(255,0,0)").unwrap(), Color::rgb(1.0, 0.0, 0.0));
+        assert_eq!(
+            Color::parse("rgba(0,0,255,0.5)").unwrap(),
+            Color::rgb(0.0, 0.0, 1.0).with_alpha(0.5)
+        );
+    }
+
+    #[test]
+    fn test_parse_named_colors() {
+        assert_eq!(Color::parse("red").unwrap(), Color::rgb(1.0, 0.0, 0.0));
+        assert_eq!(Color::parse("Navy").unwrap(), Color::rgb(0.0, 0.0, 128.0 / 255.0));
+        assert!(Color::is_color_token("rebeccapurple"));
+        assert!(!Color::is_color_token("Liberation_Serif"));
+    }
+
+    #[test]
+    fn test_parse_invalid_colors() {
+        assert!(matches!(Color::parse("nocolor"), Err(Error::InvalidColor(_))));
+        assert!(Color::parse("cmyk(0,0,0)").is_err()); // Too few components
+        assert!(Color::parse("gray(1.5)").is_err()); // Out of range
+        assert!(Color::parse("rgb(300,0,0)").is_err()); // Out of range
+        assert!(Color::parse("rgb(1,2,3").is_err()); // Unclosed
+    }
+
+    #[test]
+    fn test_color_operators() {
+        assert_eq!(Color::cmyk(0.0, 0.0, 0.0, 1.0).fill_operator(), "0.000 0.000 0.000 1.000 k");
+        assert_eq!(Color::cmyk(0.0, 0.0, 0.0, 1.0).stroke_operator(), "0.000 0.000 0.000 1.000 K");
+        assert_eq!(Color::gray(0.5).fill_operator(), "0.500 g");
+        assert_eq!(Color::rgb(1.0, 0.0, 0.0).stroke_operator(), "1.000 0.000 0.000 RG");
+    }
+}
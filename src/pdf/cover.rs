@@ -0,0 +1,240 @@
+//! Generated cover pages
+//!
+//! A cover sheet shows the title, subtitle, presenter, date and an optional
+//! logo, centered on a page the size of the document's first page. It is
+//! drawn with krilla (like the watermark PDFs in `create.rs`) and inserted
+//! in front of the document after headers and footers are added, so it gets
+//! none of its own and page numbers start on the first real page.
+
+use std::path::{Path, PathBuf};
+use chrono::NaiveDate;
+use lopdf::Document;
+use crate::date::format_date;
+use crate::error::{Error, Result};
+use super::blanks::blank_document_like;
+use super::create::{load_default_font, measure_text_width, parse_multiline_text};
+use super::geometry::PageGeometry;
+use super::merge::insert_pages;
+
+/// Font sizes of the cover's text blocks, in points
+const TITLE_SIZE: f32 = 32.0;
+const SUBTITLE_SIZE: f32 = 20.0;
+const PRESENTER_SIZE: f32 = 16.0;
+const DATE_SIZE: f32 = 14.0;
+
+/// Text may use this share of the page width before it is scaled down
+const TEXT_WIDTH: f32 = 0.8;
+
+/// Largest logo, as shares of the page width and height
+const LOGO_WIDTH: f32 = 0.4;
+const LOGO_HEIGHT: f32 = 0.25;
+
+/// Contents of a generated cover page
+#[derive(Debug, Clone, Default)]
+pub struct CoverOptions {
+    /// Title (use | or [br] for line breaks)
+    pub title: Option<String>,
+    /// Subtitle below the title
+    pub subtitle: Option<String>,
+    /// Presenter or author name(s)
+    pub presenter: Option<String>,
+    /// Date below the presenter
+    pub date: Option<NaiveDate>,
+    /// PNG or JPEG logo above the title
+    pub logo: Option<PathBuf>,
+    /// Follow the cover with a blank page, so the document still starts on a
+    /// right-hand page when printed double-sided
+    pub blank_back: bool,
+}
+
+/// Create a one-page cover PDF of the given size (in points)
+pub fn create_cover_pdf(output: &Path, options: &CoverOptions, page_size: (f32, f32)) -> Result<()> {
+    std::fs::write(output, render_cover(options, page_size)?)?;
+    Ok(())
+}
+
+/// Put a cover page (and its blank back, if asked for) in front of a document
+///
+/// The cover is sized like the first page as displayed.
+pub(crate) fn insert_cover(doc: &mut Document, options: &CoverOptions) -> Result<()> {
+    let first_page = *doc
+        .get_pages()
+        .values()
+        .next()
+        .ok_or_else(|| Error::General("Document has no pages".to_string()))?;
+    let geometry = PageGeometry::from_page(doc, first_page)?;
+    let page_size = (geometry.display_width(), geometry.display_height());
+
+    let cover = Document::load_mem(&render_cover(options, page_size)?)?;
+    let pages = insert_pages(doc, cover, 0)?;
+
+    if options.blank_back {
+        let back = blank_document_like(doc.get_dictionary(pages[0])?);
+        insert_pages(doc, back, 1)?;
+    }
+    Ok(())
+}
+
+/// Draw the cover with krilla and return the PDF bytes
+fn render_cover(options: &CoverOptions, page_size: (f32, f32)) -> Result<Vec<u8>> {
+    use krilla::color::rgb;
+    use krilla::geom::{Size, Transform};
+    use krilla::path::Fill;
+    use krilla::surface::TextDirection;
+    use krilla::{Document, PageSettings};
+    use tiny_skia_path::Point;
+
+    let (page_width, page_height) = page_size;
+    let font = load_default_font()?;
+    let logo = options.logo.as_deref().map(load_logo).transpose()?;
+
+    let mut document = Document::new();
+    let mut page = document.start_page_with(PageSettings::new(page_width, page_height));
+    let mut surface = page.surface();
+
+    // Krilla measures y downward from the top of the page
+    let mut y = page_height * 0.15;
+    if let Some(logo) = logo {
+        let size = logo.size();
+        let scale = (page_width * LOGO_WIDTH / size.width())
+            .min(page_height * LOGO_HEIGHT / size.height())
+            .min(1.0);
+        let (width, height) = (size.width() * scale, size.height() * scale);
+        if let Some(size) = Size::from_wh(width, height) {
+            surface.push_transform(&Transform::from_translate((page_width - width) / 2.0, y));
+            surface.draw_image(logo, size);
+            surface.pop();
+        }
+        y += height;
+    }
+
+    // The title block starts at least a third of the way down the page
+    y = y.max(page_height / 3.0);
+    let date = options.date.as_ref().map(format_date);
+    let blocks = [
+        (options.title.as_deref(), TITLE_SIZE),
+        (options.subtitle.as_deref(), SUBTITLE_SIZE),
+        (options.presenter.as_deref(), PRESENTER_SIZE),
+        (date.as_deref(), DATE_SIZE),
+    ];
+    for (text, size) in blocks {
+        let Some(text) = text else { continue };
+        let lines = parse_multiline_text(text);
+
+        // Long lines shrink the whole block so it stays within the text width
+        let widest = lines.iter().map(|line| measure_text_width(line, size)).fold(0.0, f32::max);
+        let size = if widest > page_width * TEXT_WIDTH { size * page_width * TEXT_WIDTH / widest } else { size };
+
+        y += size;
+        for line in &lines {
+            let x = (page_width - measure_text_width(line, size)) / 2.0;
+            surface.fill_text(
+                Point::from_xy(x, y),
+                Fill {
+                    paint: rgb::Color::new(0, 0, 0).into(),
+                    ..Default::default()
+                },
+                font.clone(),
+                size,
+                &[],
+                line,
+                false,
+                TextDirection::Auto,
+            );
+            y += size * 1.2;
+        }
+        // A gap between blocks
+        y += size * 0.8;
+    }
+
+    surface.finish();
+    page.finish();
+
+    document.finish()
+        .map_err(|e| Error::General(format!("Failed to generate cover page: {:?}", e)))
+}
+
+fn load_logo(path: &Path) -> Result<krilla::image::Image> {
+    if !path.exists() {
+        return Err(Error::FileNotFound(path.to_path_buf()));
+    }
+    let data = std::fs::read(path)?;
+    let image = if data.starts_with(&[0xFF, 0xD8]) {
+        krilla::image::Image::from_jpeg(&data)
+    } else if data.starts_with(b"\x89PNG") {
+        krilla::image::Image::from_png(&data)
+    } else {
+        None
+    };
+    image.ok_or_else(|| Error::General(format!(
+        "Unsupported logo image (expected PNG or JPEG): {}",
+        path.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Object};
+
+    fn two_page_doc() -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..2).map(|_| doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }).into()).collect();
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => 2 }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    #[test]
+    fn test_cover_matches_first_page_size() {
+        let mut doc = two_page_doc();
+        let options = CoverOptions {
+            title: Some("Jacoby Transfers|Week 3".to_string()),
+            presenter: Some("Rick Wilson".to_string()),
+            date: NaiveDate::from_ymd_opt(2026, 3, 14),
+            blank_back: true,
+            ..Default::default()
+        };
+        insert_cover(&mut doc, &options).unwrap();
+
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+        assert_eq!(pages.len(), 4);
+        for &page_id in &pages[..2] {
+            let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+            assert_eq!((geometry.display_width(), geometry.display_height()), (595.0, 842.0));
+        }
+        // The cover has text; its back is an empty page
+        assert!(!doc.get_page_content(pages[0]).unwrap().is_empty());
+        assert!(doc.get_dictionary(pages[1]).unwrap().get(b"Contents").is_err());
+    }
+
+    #[test]
+    fn test_cover_ignores_rotated_page_tree() {
+        let mut doc = two_page_doc();
+        let pages_id = doc.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(pages_id).unwrap().set("Rotate", 90);
+        let options = CoverOptions { title: Some("Stayman".to_string()), blank_back: true, ..Default::default() };
+        insert_cover(&mut doc, &options).unwrap();
+
+        // The cover is sized like the rotated first page, but is not rotated itself
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+        for &page_id in &pages[..2] {
+            let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+            assert!(!geometry.is_rotated_sideways());
+            assert_eq!((geometry.display_width(), geometry.display_height()), (842.0, 595.0));
+        }
+        assert!(PageGeometry::from_page(&doc, pages[2]).unwrap().is_rotated_sideways());
+    }
+
+    #[test]
+    fn test_missing_logo() {
+        let options = CoverOptions { logo: Some(PathBuf::from("no-such-logo.png")), ..Default::default() };
+        assert!(matches!(render_cover(&options, (612.0, 792.0)), Err(Error::FileNotFound(_))));
+    }
+}
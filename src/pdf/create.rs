@@ -61,7 +61,9 @@ impl Default for WatermarkOptions {
 /// The resulting PDF can be overlaid onto another PDF using merge functionality.
 ///
 /// Note: This is a Phase 2 implementation that creates overlay PDFs. It does not
-/// scale the source content, so headers/footers may overlap existing content.
+/// scale the source content, so headers/footers may overlap existing content;
+/// [`add_headers_footers`](super::add_headers_footers) with
+/// [`FitOptions`](super::FitOptions) shrinks the content to make room.
 pub fn create_watermark_pdf(
     output: &Path,
     options: &WatermarkOptions,
@@ -229,7 +231,7 @@ pub fn create_watermark_pdf(
 /// - `|` (pipe character)
 /// - `[br]` (bracket tag)
 /// - `<br>`, `<BR>`, `<br/>`, `<BR/>`, `<br />`, `<BR />` (HTML-style tags for backward compatibility)
-fn parse_multiline_text(text: &str) -> Vec<String> {
+pub(crate) fn parse_multiline_text(text: &str) -> Vec<String> {
     // Split on various line break markers
     text.split('\n')
         .flat_map(|line| line.split('|'))
@@ -284,7 +286,7 @@ fn draw_multiline_text(
 }
 
 /// Measure the width of text in points using rustybuzz for accurate text shaping
-fn measure_text_width(text: &str, font_size: f32) -> f32 {
+pub(crate) fn measure_text_width(text: &str, font_size: f32) -> f32 {
     use rustybuzz::{Face, UnicodeBuffer};
 
     // Use the embedded font data
@@ -319,7 +321,7 @@ fn measure_text_width(text: &str, font_size: f32) -> f32 {
 ///
 /// Embeds Liberation Serif (a free/open-source font metrically compatible with Times New Roman).
 /// The font is compiled directly into the binary for cross-platform compatibility.
-fn load_default_font() -> Result<Font> {
+pub(crate) fn load_default_font() -> Result<Font> {
     // Embed Liberation Serif font at compile time
     const LIBERATION_SERIF: &[u8] = include_bytes!("../../assets/fonts/LiberationSerif-Regular.ttf");
 
@@ -0,0 +1,213 @@
+//! Deduplication of identical shared objects
+//!
+//! Handouts made from the same template embed the same fonts and logo images
+//! once per file. Streams and font-related dictionaries are hashed by their
+//! content (stream data plus the dictionary with sorted keys), duplicates are
+//! confirmed byte for byte, and every reference is pointed at one canonical
+//! copy. Passes repeat until nothing changes, so a font whose embedded font
+//! file was merged in one pass can itself be merged in the next.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Dictionary types that are safe to share between pages
+const SHAREABLE_TYPES: [&[u8]; 4] = [b"Font", b"FontDescriptor", b"ExtGState", b"Encoding"];
+
+/// Stream types that belong to the file structure rather than the content
+const STRUCTURAL_STREAM_TYPES: [&[u8]; 2] = [b"XRef", b"ObjStm"];
+
+/// Upper bound on passes; each pass merges at least one more level of nesting
+const MAX_PASSES: usize = 8;
+
+/// Replace duplicate objects with references to one canonical copy
+///
+/// Returns how many objects were removed and their approximate size in bytes
+/// (stream data only).
+pub(crate) fn deduplicate(doc: &mut Document) -> (usize, usize) {
+    let mut removed = 0;
+    let mut bytes = 0;
+
+    for _ in 0..MAX_PASSES {
+        let duplicates = find_duplicates(doc);
+        if duplicates.is_empty() {
+            break;
+        }
+
+        for object in doc.objects.values_mut() {
+            rewrite_references(object, &duplicates);
+        }
+        for value in doc.trailer.iter_mut().map(|(_, value)| value) {
+            rewrite_references(value, &duplicates);
+        }
+        for id in duplicates.keys() {
+            if let Some(Object::Stream(stream)) = doc.objects.remove(id) {
+                bytes += stream.content.len();
+            }
+            removed += 1;
+        }
+    }
+    (removed, bytes)
+}
+
+/// Map each duplicate to the first object with the same content
+fn find_duplicates(doc: &Document) -> HashMap<ObjectId, ObjectId> {
+    let mut by_hash: HashMap<u64, Vec<ObjectId>> = HashMap::new();
+    let mut duplicates = HashMap::new();
+
+    for (&id, object) in doc.objects.iter().filter(|(_, object)| is_shareable(object)) {
+        let key = canonical_bytes(object);
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+
+        let candidates = by_hash.entry(hasher.finish()).or_default();
+        // Confirm the match; a hash collision must not merge different objects
+        let canonical = candidates
+            .iter()
+            .find(|candidate| canonical_bytes(&doc.objects[*candidate]) == key);
+        match canonical {
+            Some(&canonical) => {
+                duplicates.insert(id, canonical);
+            }
+            None => candidates.push(id),
+        }
+    }
+    duplicates
+}
+
+fn is_shareable(object: &Object) -> bool {
+    let type_name = |dict: &Dictionary| dict.get(b"Type").and_then(Object::as_name).ok().map(<[u8]>::to_vec);
+    match object {
+        Object::Stream(stream) => {
+            !matches!(type_name(&stream.dict), Some(name) if STRUCTURAL_STREAM_TYPES.contains(&name.as_slice()))
+        }
+        Object::Dictionary(dict) => {
+            matches!(type_name(dict), Some(name) if SHAREABLE_TYPES.contains(&name.as_slice()))
+        }
+        _ => false,
+    }
+}
+
+/// Serialize an object with dictionary keys sorted, so equal content gives equal bytes
+///
+/// A stream's `/Length` is left out: it may be an indirect object, and equal
+/// data already implies an equal length.
+fn canonical_bytes(object: &Object) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_canonical(object, &mut out);
+    out
+}
+
+fn write_canonical(object: &Object, out: &mut Vec<u8>) {
+    match object {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Boolean(value) => out.extend_from_slice(if *value { b"true" } else { b"false" }),
+        Object::Integer(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        Object::Real(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        Object::Name(name) => {
+            out.push(b'/');
+            out.extend_from_slice(name);
+        }
+        Object::String(bytes, _) => {
+            // Length-prefixed, so string contents cannot imitate other syntax
+            out.extend_from_slice(format!("({}:", bytes.len()).as_bytes());
+            out.extend_from_slice(bytes);
+            out.push(b')');
+        }
+        Object::Reference((id, generation)) => out.extend_from_slice(format!("{} {} R", id, generation).as_bytes()),
+        Object::Array(items) => {
+            out.push(b'[');
+            for item in items {
+                write_canonical(item, out);
+                out.push(b' ');
+            }
+            out.push(b']');
+        }
+        Object::Dictionary(dict) => write_dictionary(dict, &[], out),
+        Object::Stream(stream) => {
+            write_dictionary(&stream.dict, &[b"Length"], out);
+            out.extend_from_slice(format!("stream{}:", stream.content.len()).as_bytes());
+            out.extend_from_slice(&stream.content);
+        }
+    }
+}
+
+fn write_dictionary(dict: &Dictionary, skip: &[&[u8]], out: &mut Vec<u8>) {
+    let mut entries: Vec<(&Vec<u8>, &Object)> = dict.iter().filter(|(key, _)| !skip.contains(&key.as_slice())).collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    out.extend_from_slice(b"<<");
+    for (key, value) in entries {
+        out.push(b'/');
+        out.extend_from_slice(key);
+        out.push(b' ');
+        write_canonical(value, out);
+        out.push(b' ');
+    }
+    out.extend_from_slice(b">>");
+}
+
+fn rewrite_references(object: &mut Object, replacements: &HashMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(canonical) = replacements.get(id) {
+                *id = *canonical;
+            }
+        }
+        Object::Array(items) => items.iter_mut().for_each(|item| rewrite_references(item, replacements)),
+        Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| rewrite_references(value, replacements)),
+        Object::Stream(stream) => {
+            stream.dict.iter_mut().for_each(|(_, value)| rewrite_references(value, replacements))
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn test_nested_duplicates_collapse() {
+        let mut doc = Document::with_version("1.7");
+        let mut fonts = Vec::new();
+        for _ in 0..3 {
+            let file = doc.add_object(Stream::new(dictionary! { "Length1" => 4 }, b"font".to_vec()));
+            let descriptor = doc.add_object(dictionary! {
+                "Type" => "FontDescriptor",
+                "FontName" => "ABCDEF+Arial",
+                "FontFile2" => file,
+            });
+            fonts.push(doc.add_object(dictionary! {
+                "Type" => "Font",
+                "BaseFont" => "ABCDEF+Arial",
+                "FontDescriptor" => descriptor,
+            }));
+        }
+        let other = doc.add_object(Stream::new(dictionary! {}, b"logo".to_vec()));
+        let resources = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => fonts[0], "F2" => fonts[1], "F3" => fonts[2] },
+            "XObject" => dictionary! { "Im1" => other },
+        });
+
+        let (removed, bytes) = deduplicate(&mut doc);
+        // Two copies each of the font file, descriptor and font
+        assert_eq!(removed, 6);
+        assert_eq!(bytes, 8);
+
+        let fonts = doc.get_dictionary(resources).unwrap().get(b"Font").unwrap().as_dict().unwrap();
+        let ids: Vec<ObjectId> = fonts.iter().map(|(_, font)| font.as_reference().unwrap()).collect();
+        assert!(ids.iter().all(|&id| id == ids[0]));
+        assert!(doc.objects.contains_key(&other));
+    }
+
+    #[test]
+    fn test_key_order_does_not_matter() {
+        let a = Object::Dictionary(dictionary! { "Type" => "ExtGState", "CA" => 0.5 });
+        let b = Object::Dictionary(dictionary! { "CA" => 0.5, "Type" => "ExtGState" });
+        assert_eq!(canonical_bytes(&a), canonical_bytes(&b));
+        let c = Object::Dictionary(dictionary! { "CA" => 0.25, "Type" => "ExtGState" });
+        assert_ne!(canonical_bytes(&a), canonical_bytes(&c));
+    }
+}
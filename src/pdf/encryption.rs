@@ -0,0 +1,328 @@
+//! Password-protected PDFs: opening encrypted inputs and encrypting output
+//!
+//! lopdf decrypts files with an empty user password while loading (common for
+//! publishers' PDFs that only restrict printing or copying), but has no way to
+//! pass a password. For other files the objects are read with the trailer's
+//! `/Encrypt` key hidden, so they are parsed as they are stored, and then
+//! decrypted with `Document::decrypt`. Either way the document comes back
+//! fully decrypted, without an `/Encrypt` entry, ready to be saved as is.
+//!
+//! Output is encrypted with AES (128 or 256-bit) just before saving.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use lopdf::encryption::crypt_filters::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter};
+use lopdf::{Document, EncryptionState, EncryptionVersion, Object, Permissions, StringFormat};
+use crate::error::{Error, Result};
+
+/// A password for one input file (`file.pdf=secret`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputPassword {
+    /// The file the password is for; a bare file name matches that name in any directory
+    pub path: PathBuf,
+    /// User or owner password
+    pub password: String,
+}
+
+impl InputPassword {
+    /// Parse `FILE=PASSWORD` (the password may itself contain `=`)
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.split_once('=') {
+            Some((path, password)) if !path.trim().is_empty() => Ok(Self {
+                path: PathBuf::from(path.trim()),
+                password: password.to_string(),
+            }),
+            _ => Err(Error::General(format!(
+                "Invalid password: {} (expected FILE=PASSWORD)",
+                value
+            ))),
+        }
+    }
+
+    /// Whether this password is for `path`
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.path == path {
+            return true;
+        }
+        let bare_name = self.path.parent().is_none_or(|parent| parent.as_os_str().is_empty());
+        bare_name && path.file_name() == Some(self.path.as_os_str())
+    }
+}
+
+/// The password given for `path`, if any
+pub fn password_for<'a>(passwords: &'a [InputPassword], path: &Path) -> Option<&'a str> {
+    passwords.iter().find(|entry| entry.matches(path)).map(|entry| entry.password.as_str())
+}
+
+/// Load a PDF, decrypting it if it is encrypted
+///
+/// Files with an empty user password open without `password`. Otherwise a
+/// missing or wrong password gives `Error::Encrypted`.
+pub fn load_document(path: &Path, password: Option<&str>) -> Result<Document> {
+    let buffer = std::fs::read(path)?;
+    let mut doc = Document::load_mem(&buffer)?;
+    if doc.trailer.get(b"Encrypt").is_err() {
+        return Ok(doc);
+    }
+
+    // Opened with the empty password: only the encryption dictionary is left to drop
+    if doc.encryption_state.is_some() {
+        if let Some(Object::Reference(id)) = doc.trailer.remove(b"Encrypt") {
+            doc.objects.remove(&id);
+        }
+        doc.encryption_state = None;
+        return Ok(doc);
+    }
+
+    let password = password.ok_or_else(|| Error::Encrypted(path.to_path_buf()))?;
+    let mut doc = Document::load_mem(&hide_encrypt_key(&buffer))?;
+    if let Some(encrypt) = doc.trailer.remove(HIDDEN_KEY) {
+        doc.trailer.set("Encrypt", encrypt);
+    }
+    doc.decrypt(password).map_err(|_| Error::Encrypted(path.to_path_buf()))?;
+    doc.encryption_state = None;
+    Ok(doc)
+}
+
+/// Same length as `/Encrypt`, so byte offsets in the cross-reference table stay valid
+const HIDDEN_KEY: &[u8] = b"Encryp_";
+
+/// Rename `/Encrypt` keys (but not `/EncryptMetadata`) in the raw file
+fn hide_encrypt_key(buffer: &[u8]) -> Vec<u8> {
+    const KEY: &[u8] = b"/Encrypt";
+    let mut hidden = buffer.to_vec();
+    let mut i = 0;
+    while let Some(offset) = hidden[i..].windows(KEY.len()).position(|window| window == KEY) {
+        let start = i + offset;
+        let end = start + KEY.len();
+        if !hidden.get(end).is_some_and(u8::is_ascii_alphanumeric) {
+            hidden[start + 1..end].copy_from_slice(HIDDEN_KEY);
+        }
+        i = end;
+    }
+    hidden
+}
+
+/// AES key length for encrypted output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncryptionAlgorithm {
+    /// AES-128 (PDF 1.6; opens in older readers)
+    Aes128,
+    /// AES-256 (PDF 2.0)
+    #[default]
+    Aes256,
+}
+
+impl EncryptionAlgorithm {
+    /// Parse an algorithm name (`aes128`, `aes256`)
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().replace('-', "").as_str() {
+            "aes128" | "128" => Ok(EncryptionAlgorithm::Aes128),
+            "aes256" | "256" | "aes" => Ok(EncryptionAlgorithm::Aes256),
+            _ => Err(Error::General(format!(
+                "Invalid encryption: {} (expected aes128 or aes256)",
+                value
+            ))),
+        }
+    }
+}
+
+/// What readers may do with an encrypted PDF without the owner password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputPermissions {
+    /// Print (in high quality too)
+    pub print: bool,
+    /// Copy text and images
+    pub copy: bool,
+    /// Change the content and insert, delete or rotate pages
+    pub modify: bool,
+    /// Add comments and fill in form fields
+    pub annotate: bool,
+}
+
+impl Default for OutputPermissions {
+    fn default() -> Self {
+        Self { print: true, copy: true, modify: true, annotate: true }
+    }
+}
+
+impl OutputPermissions {
+    /// Everything allowed except the comma-separated actions in `denied`
+    /// (`print`, `copy`, `modify`, `annotate`)
+    pub fn deny(denied: &str) -> Result<Self> {
+        let mut permissions = Self::default();
+        for action in denied.split(',').map(|action| action.trim().to_lowercase()) {
+            match action.as_str() {
+                "print" => permissions.print = false,
+                "copy" => permissions.copy = false,
+                "modify" | "edit" => permissions.modify = false,
+                "annotate" | "comment" => permissions.annotate = false,
+                "" => {}
+                _ => return Err(Error::General(format!(
+                    "Invalid permission: {} (expected print, copy, modify or annotate)",
+                    action
+                ))),
+            }
+        }
+        Ok(permissions)
+    }
+
+    fn flags(&self) -> Permissions {
+        // Copying for accessibility (screen readers) stays allowed
+        let mut flags = Permissions::COPYABLE_FOR_ACCESSIBILITY;
+        if self.print {
+            flags |= Permissions::PRINTABLE | Permissions::PRINTABLE_IN_HIGH_QUALITY;
+        }
+        if self.copy {
+            flags |= Permissions::COPYABLE;
+        }
+        if self.modify {
+            flags |= Permissions::MODIFIABLE | Permissions::ASSEMBLABLE;
+        }
+        if self.annotate {
+            flags |= Permissions::ANNOTABLE | Permissions::FILLABLE;
+        }
+        flags
+    }
+}
+
+/// Encryption for an output PDF
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputEncryption {
+    /// Password needed to open the file (empty = opens without one)
+    pub user_password: String,
+    /// Password that lifts the permission limits (empty = a random one nobody knows)
+    pub owner_password: String,
+    /// AES key length
+    pub algorithm: EncryptionAlgorithm,
+    /// What readers may do without the owner password
+    pub permissions: OutputPermissions,
+}
+
+/// Encrypt a document in place; call this last, just before saving
+pub fn encrypt_document(doc: &mut Document, encryption: &OutputEncryption) -> Result<()> {
+    // An empty owner password would open the file with full rights
+    let owner_password = if encryption.owner_password.is_empty() {
+        random_bytes::<16>()?.iter().map(|byte| format!("{:02x}", byte)).collect()
+    } else {
+        encryption.owner_password.clone()
+    };
+
+    // The document ID feeds into the AES-128 key
+    if doc.trailer.get(b"ID").is_err() {
+        let id = Object::String(random_bytes::<16>()?.to_vec(), StringFormat::Hexadecimal);
+        doc.trailer.set("ID", vec![id.clone(), id]);
+    }
+
+    let permissions = encryption.permissions.flags();
+    let file_key = random_bytes::<32>()?;
+    let version = match encryption.algorithm {
+        EncryptionAlgorithm::Aes128 => EncryptionVersion::V4 {
+            document: doc,
+            encrypt_metadata: true,
+            crypt_filters: standard_filter(Arc::new(Aes128CryptFilter)),
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: &owner_password,
+            user_password: &encryption.user_password,
+            permissions,
+        },
+        EncryptionAlgorithm::Aes256 => EncryptionVersion::V5 {
+            encrypt_metadata: true,
+            crypt_filters: standard_filter(Arc::new(Aes256CryptFilter)),
+            file_encryption_key: &file_key,
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: &owner_password,
+            user_password: &encryption.user_password,
+            permissions,
+        },
+    };
+    let state = EncryptionState::try_from(version)?;
+    doc.encrypt(&state)?;
+
+    // AES-128 (V4 with AESV2) came with PDF 1.6, AES-256 (V5, R6) with PDF 2.0
+    let minimum_version = match encryption.algorithm {
+        EncryptionAlgorithm::Aes128 => "1.6",
+        EncryptionAlgorithm::Aes256 => "2.0",
+    };
+    raise_version(doc, minimum_version);
+    Ok(())
+}
+
+/// Set the header version to `minimum` if the document declares an older one
+pub(crate) fn raise_version(doc: &mut Document, minimum: &str) {
+    let parse = |version: &str| version.trim().parse::<f32>().unwrap_or(0.0);
+    if parse(&doc.version) < parse(minimum) {
+        doc.version = minimum.to_string();
+    }
+}
+
+fn standard_filter(filter: Arc<dyn CryptFilter>) -> BTreeMap<Vec<u8>, Arc<dyn CryptFilter>> {
+    BTreeMap::from([(b"StdCF".to_vec(), filter)])
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes).map_err(|e| Error::General(format!("No random numbers for encryption: {}", e)))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_input_password() {
+        let entry = InputPassword::parse("handout.pdf=a=b").unwrap();
+        assert_eq!(entry.path, PathBuf::from("handout.pdf"));
+        assert_eq!(entry.password, "a=b");
+        assert!(InputPassword::parse("secret").is_err());
+        assert!(InputPassword::parse("=secret").is_err());
+    }
+
+    #[test]
+    fn test_password_matching() {
+        let passwords = vec![
+            InputPassword::parse("handout.pdf=one").unwrap(),
+            InputPassword::parse("docs/notes.pdf=two").unwrap(),
+        ];
+        assert_eq!(password_for(&passwords, Path::new("handout.pdf")), Some("one"));
+        assert_eq!(password_for(&passwords, Path::new("class/handout.pdf")), Some("one"));
+        assert_eq!(password_for(&passwords, Path::new("docs/notes.pdf")), Some("two"));
+        assert_eq!(password_for(&passwords, Path::new("other/notes.pdf")), None);
+    }
+
+    #[test]
+    fn test_parse_output_encryption_settings() {
+        assert_eq!(EncryptionAlgorithm::parse("AES-128").unwrap(), EncryptionAlgorithm::Aes128);
+        assert_eq!(EncryptionAlgorithm::parse("aes256").unwrap(), EncryptionAlgorithm::Aes256);
+        assert!(EncryptionAlgorithm::parse("rc4").is_err());
+
+        let permissions = OutputPermissions::deny("modify, copy").unwrap();
+        assert!(permissions.print && permissions.annotate);
+        assert!(!permissions.modify && !permissions.copy);
+        let flags = permissions.flags();
+        assert!(flags.contains(Permissions::PRINTABLE));
+        assert!(!flags.contains(Permissions::MODIFIABLE));
+        assert!(OutputPermissions::deny("save").is_err());
+    }
+
+    #[test]
+    fn test_raise_version() {
+        let mut doc = Document::with_version("1.4");
+        raise_version(&mut doc, "1.6");
+        assert_eq!(doc.version, "1.6");
+        raise_version(&mut doc, "1.5");
+        assert_eq!(doc.version, "1.6");
+        raise_version(&mut doc, "2.0");
+        assert_eq!(doc.version, "2.0");
+    }
+
+    #[test]
+    fn test_hide_encrypt_key() {
+        let hidden = hide_encrypt_key(b"<< /Encrypt 5 0 R /Size 6 >> << /EncryptMetadata false >>");
+        assert_eq!(hidden, b"<< /Encryp_ 5 0 R /Size 6 >> << /EncryptMetadata false >>".to_vec());
+    }
+}
@@ -0,0 +1,286 @@
+//! Interactive form (AcroForm) fields across merges
+//!
+//! Each source's field hierarchy is pruned to the widgets on selected pages
+//! and added to one merged `/AcroForm`. Top-level fields whose names are
+//! already taken by an earlier source are renamed with the source's number
+//! (`Name` → `2_Name`), so the fields stay independent.
+
+use std::collections::BTreeSet;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use super::headers::text_string_object;
+use super::merge::collect_references;
+
+/// The merged `/AcroForm`, built up one source at a time
+#[derive(Debug, Default)]
+pub(crate) struct MergedForm {
+    fields: Vec<ObjectId>,
+    names: BTreeSet<String>,
+    default_resources: Dictionary,
+    need_appearances: bool,
+    sig_flags: i64,
+    calculation_order: Vec<Object>,
+    default_appearance: Option<Object>,
+    quadding: Option<Object>,
+}
+
+impl MergedForm {
+    /// Add a source's fields that have a widget on one of `pages`
+    ///
+    /// `number` is the source's position in the merge (from 1), used to
+    /// rename clashing fields. Returns the objects the form now refers to.
+    pub fn add_source(&mut self, doc: &mut Document, number: usize, pages: &[ObjectId]) -> Vec<ObjectId> {
+        let form = match doc.catalog().and_then(|catalog| catalog.get(b"AcroForm")) {
+            Ok(form) => match doc.dereference(form) {
+                Ok((_, Object::Dictionary(form))) => form.clone(),
+                _ => return Vec::new(),
+            },
+            Err(_) => return Vec::new(),
+        };
+
+        let widgets = page_annotations(doc, pages);
+        let page_set: BTreeSet<ObjectId> = pages.iter().copied().collect();
+        let top_level = match form.get(b"Fields").and_then(|fields| doc.dereference(fields)) {
+            Ok((_, Object::Array(fields))) => fields.clone(),
+            _ => Vec::new(),
+        };
+
+        let mut roots = Vec::new();
+        let mut source_names = BTreeSet::new();
+        for field in top_level {
+            let Ok(field_id) = field.as_reference() else { continue };
+            if !prune_field(doc, field_id, &widgets, &page_set, &mut BTreeSet::new()) {
+                continue;
+            }
+            if let Some(name) = field_name(doc, field_id) {
+                let name = self.unique_name(&name, number);
+                if let Ok(field) = doc.get_dictionary_mut(field_id) {
+                    field.set("T", text_string_object(&name));
+                }
+                source_names.insert(name);
+            }
+            self.fields.push(field_id);
+            roots.push(field_id);
+        }
+        self.names.extend(source_names);
+
+        // Form-wide settings: resources are pooled, flags combined, the rest kept from the first source
+        if let Ok((_, Object::Dictionary(resources))) = form.get(b"DR").and_then(|dr| doc.dereference(dr)) {
+            for (category, entries) in resources.iter() {
+                let entries = match doc.dereference(entries) {
+                    Ok((_, Object::Dictionary(entries))) => entries.clone(),
+                    _ => continue,
+                };
+                let merged = match self.default_resources.get_mut(category) {
+                    Ok(Object::Dictionary(merged)) => merged,
+                    _ => {
+                        self.default_resources.set(category.clone(), Dictionary::new());
+                        self.default_resources.get_mut(category).unwrap().as_dict_mut().unwrap()
+                    }
+                };
+                for (name, value) in entries.iter() {
+                    if merged.get(name).is_err() {
+                        collect_references(value, &mut roots);
+                        merged.set(name.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        if let Ok(Object::Boolean(true)) = form.get(b"NeedAppearances") {
+            self.need_appearances = true;
+        }
+        if let Ok(Object::Integer(flags)) = form.get(b"SigFlags") {
+            self.sig_flags |= flags;
+        }
+        if let Ok((_, Object::Array(order))) = form.get(b"CO").and_then(|co| doc.dereference(co)) {
+            let kept = order.iter().filter(|field| {
+                field.as_reference().is_ok_and(|id| roots.contains(&id) || is_kept_descendant(doc, id, &roots))
+            });
+            self.calculation_order.extend(kept.cloned());
+        }
+        if self.default_appearance.is_none() {
+            self.default_appearance = form.get(b"DA").ok().cloned();
+        }
+        if self.quadding.is_none() {
+            self.quadding = form.get(b"Q").ok().cloned();
+        }
+        roots
+    }
+
+    /// The merged `/AcroForm` dictionary, if any source had fields
+    pub fn to_dictionary(&self) -> Option<Dictionary> {
+        if self.fields.is_empty() {
+            return None;
+        }
+        let mut form = Dictionary::new();
+        form.set("Fields", self.fields.iter().map(|&id| Object::Reference(id)).collect::<Vec<_>>());
+        if !self.default_resources.is_empty() {
+            form.set("DR", self.default_resources.clone());
+        }
+        if self.need_appearances {
+            form.set("NeedAppearances", true);
+        }
+        if self.sig_flags != 0 {
+            form.set("SigFlags", self.sig_flags);
+        }
+        if !self.calculation_order.is_empty() {
+            form.set("CO", self.calculation_order.clone());
+        }
+        if let Some(ref appearance) = self.default_appearance {
+            form.set("DA", appearance.clone());
+        }
+        if let Some(ref quadding) = self.quadding {
+            form.set("Q", quadding.clone());
+        }
+        Some(form)
+    }
+
+    /// `name`, or `{number}_{name}` (then `{number}_{name}_2`, ...) if an earlier source took it
+    fn unique_name(&self, name: &str, number: usize) -> String {
+        if !self.names.contains(name) {
+            return name.to_string();
+        }
+        let prefixed = format!("{}_{}", number, name);
+        if !self.names.contains(&prefixed) {
+            return prefixed;
+        }
+        (2..)
+            .map(|n| format!("{}_{}", prefixed, n))
+            .find(|candidate| !self.names.contains(candidate))
+            .unwrap()
+    }
+}
+
+/// Annotations referenced from the pages' `/Annots`
+fn page_annotations(doc: &Document, pages: &[ObjectId]) -> BTreeSet<ObjectId> {
+    let mut annotations = BTreeSet::new();
+    for &page_id in pages {
+        let Ok(page) = doc.get_dictionary(page_id) else { continue };
+        if let Ok((_, Object::Array(annots))) = page.get(b"Annots").and_then(|annots| doc.dereference(annots)) {
+            annotations.extend(annots.iter().filter_map(|annot| annot.as_reference().ok()));
+        }
+    }
+    annotations
+}
+
+/// Drop the parts of a field tree without a widget on a selected page
+///
+/// Returns whether anything of the field is left.
+fn prune_field(
+    doc: &mut Document,
+    field_id: ObjectId,
+    widgets: &BTreeSet<ObjectId>,
+    pages: &BTreeSet<ObjectId>,
+    visited: &mut BTreeSet<ObjectId>,
+) -> bool {
+    if !visited.insert(field_id) {
+        return false;
+    }
+    let Ok(field) = doc.get_dictionary(field_id) else { return false };
+
+    let kids = match field.get(b"Kids").and_then(|kids| doc.dereference(kids)) {
+        Ok((_, Object::Array(kids))) => Some(kids.clone()),
+        _ => None,
+    };
+    let Some(kids) = kids else {
+        // A terminal field doubles as its own widget
+        let on_page = matches!(field.get(b"P"), Ok(Object::Reference(page)) if pages.contains(page));
+        return widgets.contains(&field_id) || on_page;
+    };
+
+    let kept: Vec<Object> = kids
+        .into_iter()
+        .filter(|kid| kid.as_reference().is_ok_and(|kid_id| prune_field(doc, kid_id, widgets, pages, visited)))
+        .collect();
+    let keep = !kept.is_empty();
+    if let Ok(field) = doc.get_dictionary_mut(field_id) {
+        field.set("Kids", kept);
+    }
+    keep
+}
+
+/// Whether `id` is a field below one of the kept top-level fields
+fn is_kept_descendant(doc: &Document, id: ObjectId, kept: &[ObjectId]) -> bool {
+    let mut current = id;
+    for _ in 0..64 {
+        match doc.get_dictionary(current).and_then(|field| field.get(b"Parent")) {
+            Ok(Object::Reference(parent)) if kept.contains(parent) => return true,
+            Ok(Object::Reference(parent)) => current = *parent,
+            _ => return false,
+        }
+    }
+    false
+}
+
+fn field_name(doc: &Document, field_id: ObjectId) -> Option<String> {
+    let name = doc.get_dictionary(field_id).ok()?.get(b"T").ok()?;
+    lopdf::decode_text_string(name).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// Two pages with a "Name" text field on page 1 and an "Answers" group
+    /// with one widget on each page
+    fn form_doc() -> (Document, Vec<ObjectId>) {
+        let mut doc = Document::with_version("1.7");
+        let pages: Vec<ObjectId> = (0..2).map(|_| doc.add_object(dictionary! { "Type" => "Page" })).collect();
+        let font = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" });
+
+        let name = doc.add_object(dictionary! {
+            "FT" => "Tx", "T" => Object::string_literal("Name"), "Subtype" => "Widget", "P" => pages[0],
+        });
+        let answers = doc.new_object_id();
+        let widgets: Vec<ObjectId> = pages.iter().map(|&page| doc.add_object(dictionary! {
+            "Subtype" => "Widget", "Parent" => answers, "P" => page,
+        })).collect();
+        doc.objects.insert(answers, Object::Dictionary(dictionary! {
+            "FT" => "Tx",
+            "T" => Object::string_literal("Answers"),
+            "Kids" => widgets.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+        }));
+        doc.get_dictionary_mut(pages[0]).unwrap().set("Annots", vec![name.into(), widgets[0].into()]);
+        doc.get_dictionary_mut(pages[1]).unwrap().set("Annots", vec![widgets[1].into()]);
+
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "AcroForm" => dictionary! {
+                "Fields" => vec![name.into(), answers.into()],
+                "DR" => dictionary! { "Font" => dictionary! { "Helv" => font } },
+                "NeedAppearances" => true,
+            },
+        });
+        doc.trailer.set("Root", catalog_id);
+        (doc, pages)
+    }
+
+    #[test]
+    fn test_duplicate_fields_are_renamed() {
+        let mut form = MergedForm::default();
+        let (mut first, pages) = form_doc();
+        form.add_source(&mut first, 1, &pages);
+        let (mut second, pages) = form_doc();
+        let roots = form.add_source(&mut second, 2, &pages);
+
+        let names: Vec<String> = roots.iter().filter_map(|&id| field_name(&second, id)).collect();
+        assert_eq!(names, vec!["2_Name", "2_Answers"]);
+
+        let merged = form.to_dictionary().unwrap();
+        assert_eq!(merged.get(b"Fields").unwrap().as_array().unwrap().len(), 4);
+        assert!(merged.get(b"NeedAppearances").unwrap().as_bool().unwrap());
+        assert!(merged.get(b"DR").unwrap().as_dict().unwrap().get(b"Font").unwrap().as_dict().unwrap().get(b"Helv").is_ok());
+    }
+
+    #[test]
+    fn test_fields_follow_page_selection() {
+        let mut form = MergedForm::default();
+        let (mut doc, pages) = form_doc();
+        let roots = form.add_source(&mut doc, 1, &pages[1..]);
+
+        // Only the group's widget on page 2 is left
+        assert_eq!(roots.iter().filter_map(|&id| field_name(&doc, id)).collect::<Vec<_>>(), vec!["Answers"]);
+        let kids = doc.get_dictionary(roots[0]).unwrap().get(b"Kids").unwrap().as_array().unwrap();
+        assert_eq!(kids.len(), 1);
+    }
+}
@@ -0,0 +1,335 @@
+//! Page geometry: effective boxes, rotation and display coordinates
+//!
+//! Pages inherit `/MediaBox`, `/CropBox` and `/Rotate` from their ancestors in
+//! the page tree. Stamped content is laid out in *display* coordinates (what
+//! the reader sees after cropping and rotation, origin at the bottom-left)
+//! and mapped back to the page's default user space with [`PageGeometry::display_matrix`].
+
+use lopdf::{Document, Object, ObjectId};
+
+use crate::error::{Error, Result};
+
+/// A rectangle in PDF user space, normalized so `x0 <= x1` and `y0 <= y1`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+impl Rect {
+    /// Create a normalized rectangle from two corners
+    pub fn new(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        Self {
+            x0: x0.min(x1),
+            y0: y0.min(y1),
+            x1: x0.max(x1),
+            y1: y0.max(y1),
+        }
+    }
+
+    /// Width in points
+    pub fn width(&self) -> f32 {
+        self.x1 - self.x0
+    }
+
+    /// Height in points
+    pub fn height(&self) -> f32 {
+        self.y1 - self.y0
+    }
+
+    /// Read a rectangle from a PDF array of four numbers
+    pub fn from_object(doc: &Document, object: &Object) -> Option<Self> {
+        let array = match object {
+            Object::Reference(id) => doc.get_object(*id).ok()?.as_array().ok()?,
+            other => other.as_array().ok()?,
+        };
+        if array.len() != 4 {
+            return None;
+        }
+        let mut values = [0.0f32; 4];
+        for (value, item) in values.iter_mut().zip(array) {
+            *value = match item {
+                Object::Reference(id) => doc.get_object(*id).ok()?.as_float().ok()?,
+                other => other.as_float().ok()?,
+            };
+        }
+        Some(Self::new(values[0], values[1], values[2], values[3]))
+    }
+
+    /// Convert to a PDF array
+    pub fn to_object(self) -> Object {
+        Object::Array(vec![
+            Object::Real(self.x0),
+            Object::Real(self.y0),
+            Object::Real(self.x1),
+            Object::Real(self.y1),
+        ])
+    }
+
+    /// Intersection with another rectangle (None if they don't overlap)
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let rect = Rect {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+        };
+        (rect.x0 < rect.x1 && rect.y0 < rect.y1).then_some(rect)
+    }
+}
+
+/// An affine transformation `[a b c d e f]` as used by the `cm` operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix(pub [f32; 6]);
+
+impl Matrix {
+    /// The identity transformation
+    pub const IDENTITY: Matrix = Matrix([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    /// Transform a point
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Apply `self` first, then `other` (the `cm` concatenation `self × other`)
+    pub fn then(&self, other: &Matrix) -> Matrix {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Matrix([
+            a * a2 + b * c2,
+            a * b2 + b * d2,
+            c * a2 + d * c2,
+            c * b2 + d * d2,
+            e * a2 + f * c2 + e2,
+            e * b2 + f * d2 + f2,
+        ])
+    }
+
+    /// The inverse transformation (identity if the matrix is singular)
+    pub fn inverse(&self) -> Matrix {
+        let [a, b, c, d, e, f] = self.0;
+        let det = a * d - b * c;
+        if det == 0.0 {
+            return Matrix::IDENTITY;
+        }
+        Matrix([
+            d / det,
+            -b / det,
+            -c / det,
+            a / det,
+            (c * f - d * e) / det,
+            (b * e - a * f) / det,
+        ])
+    }
+
+    /// Transform a rectangle, returning the bounding box of its corners
+    pub fn apply_rect(&self, rect: &Rect) -> Rect {
+        let (x0, y0) = self.apply(rect.x0, rect.y0);
+        let (x1, y1) = self.apply(rect.x1, rect.y1);
+        Rect::new(x0, y0, x1, y1)
+    }
+
+    /// Operands for the `cm` operator
+    pub fn to_operands(&self) -> String {
+        self.0.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Effective geometry of one page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageGeometry {
+    /// Effective `/MediaBox` (defaults to US Letter if missing)
+    pub media_box: Rect,
+    /// Effective `/CropBox`, clipped to the media box (defaults to the media box)
+    pub crop_box: Rect,
+    /// Effective `/Rotate`, normalized to 0, 90, 180 or 270
+    pub rotation: i64,
+}
+
+impl PageGeometry {
+    /// Read the effective geometry of a page, following inheritance through `/Parent`
+    pub fn from_page(doc: &Document, page_id: ObjectId) -> Result<Self> {
+        let media_box = inherited_attribute(doc, page_id, b"MediaBox")?
+            .and_then(|object| Rect::from_object(doc, &object))
+            .unwrap_or(Rect::new(0.0, 0.0, 612.0, 792.0));
+        let crop_box = inherited_attribute(doc, page_id, b"CropBox")?
+            .and_then(|object| Rect::from_object(doc, &object))
+            .and_then(|crop| crop.intersect(&media_box))
+            .unwrap_or(media_box);
+        let rotation = inherited_attribute(doc, page_id, b"Rotate")?
+            .and_then(|object| match object {
+                Object::Reference(id) => doc.get_object(id).ok()?.as_i64().ok(),
+                other => other.as_i64().ok(),
+            })
+            .unwrap_or(0);
+
+        Ok(Self {
+            media_box,
+            crop_box,
+            rotation: normalize_rotation(rotation),
+        })
+    }
+
+    /// Whether the page is displayed sideways (rotated by 90 or 270 degrees)
+    pub fn is_rotated_sideways(&self) -> bool {
+        self.rotation == 90 || self.rotation == 270
+    }
+
+    /// Width of the page as displayed, in points
+    pub fn display_width(&self) -> f32 {
+        if self.is_rotated_sideways() {
+            self.crop_box.height()
+        } else {
+            self.crop_box.width()
+        }
+    }
+
+    /// Height of the page as displayed, in points
+    pub fn display_height(&self) -> f32 {
+        if self.is_rotated_sideways() {
+            self.crop_box.width()
+        } else {
+            self.crop_box.height()
+        }
+    }
+
+    /// Whether the page is wider than tall as displayed
+    pub fn is_landscape(&self) -> bool {
+        self.display_width() > self.display_height()
+    }
+
+    /// Matrix mapping display coordinates to the page's default user space
+    ///
+    /// Display coordinates have their origin at the bottom-left corner of the
+    /// visible (cropped) page as the reader sees it after rotation.
+    pub fn display_matrix(&self) -> Matrix {
+        let Rect { x0, y0, x1, y1 } = self.crop_box;
+        match self.rotation {
+            // /Rotate turns the page clockwise when displayed
+            90 => Matrix([0.0, 1.0, -1.0, 0.0, x1, y0]),
+            180 => Matrix([-1.0, 0.0, 0.0, -1.0, x1, y1]),
+            270 => Matrix([0.0, -1.0, 1.0, 0.0, x0, y1]),
+            _ => Matrix([1.0, 0.0, 0.0, 1.0, x0, y0]),
+        }
+    }
+}
+
+/// Normalize a `/Rotate` value to 0, 90, 180 or 270
+fn normalize_rotation(rotation: i64) -> i64 {
+    let normalized = rotation.rem_euclid(360);
+    // Non-multiples of 90 are invalid; round to the nearest quarter turn
+    ((normalized + 45) / 90 % 4) * 90
+}
+
+/// Look up a page attribute, walking up the page tree for inheritable ones
+fn inherited_attribute(doc: &Document, page_id: ObjectId, key: &[u8]) -> Result<Option<Object>> {
+    let mut current = page_id;
+    // Guard against cycles in malformed page trees
+    for _ in 0..64 {
+        let node = doc.get_dictionary(current)?;
+        if let Ok(value) = node.get(key) {
+            return Ok(Some(value.clone()));
+        }
+        match node.get(b"Parent").and_then(Object::as_reference) {
+            Ok(parent) => current = parent,
+            Err(_) => return Ok(None),
+        }
+    }
+    Err(Error::General(format!(
+        "Page tree too deep or cyclic at object {} {}",
+        page_id.0, page_id.1
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Dictionary};
+
+    /// Build a document with a single page under one intermediate Pages node
+    fn document_with_page(parent: Dictionary, page: Dictionary) -> (Document, ObjectId) {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let mut page = page;
+        page.set("Type", "Page");
+        page.set("Parent", pages_id);
+        let page_id = doc.add_object(page);
+
+        let mut pages = parent;
+        pages.set("Type", "Pages");
+        pages.set("Kids", vec![Object::Reference(page_id)]);
+        pages.set("Count", 1);
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+        (doc, page_id)
+    }
+
+    #[test]
+    fn test_inherited_boxes_and_rotation() {
+        let (doc, page_id) = document_with_page(
+            dictionary! {
+                "MediaBox" => vec![0.into(), 0.into(), 842.into(), 595.into()],
+                "Rotate" => 450,
+            },
+            dictionary! {
+                "CropBox" => vec![10.into(), 20.into(), 810.into(), 580.into()],
+            },
+        );
+        let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+        assert_eq!(geometry.media_box, Rect::new(0.0, 0.0, 842.0, 595.0));
+        assert_eq!(geometry.crop_box, Rect::new(10.0, 20.0, 810.0, 580.0));
+        assert_eq!(geometry.rotation, 90);
+        assert_eq!(geometry.display_width(), 560.0);
+        assert_eq!(geometry.display_height(), 800.0);
+        assert!(!geometry.is_landscape());
+    }
+
+    #[test]
+    fn test_display_matrix_maps_corners() {
+        let crop = Rect::new(10.0, 20.0, 110.0, 220.0); // 100 x 200
+        for rotation in [0, 90, 180, 270] {
+            let geometry = PageGeometry { media_box: crop, crop_box: crop, rotation };
+            let matrix = geometry.display_matrix();
+            let (w, h) = (geometry.display_width(), geometry.display_height());
+
+            // Every display corner lands on a crop box corner
+            for (u, v) in [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)] {
+                let (x, y) = matrix.apply(u, v);
+                assert!(x == crop.x0 || x == crop.x1, "rotation {}: x {}", rotation, x);
+                assert!(y == crop.y0 || y == crop.y1, "rotation {}: y {}", rotation, y);
+            }
+        }
+
+        // Rotated 90 degrees clockwise, the displayed bottom edge is the user-space right edge
+        // and the displayed top-left corner is the user-space bottom-left corner
+        let geometry = PageGeometry { media_box: crop, crop_box: crop, rotation: 90 };
+        assert_eq!(geometry.display_matrix().apply(0.0, 0.0), (110.0, 20.0));
+        assert_eq!(geometry.display_matrix().apply(0.0, 100.0), (10.0, 20.0));
+
+        // Mapping back to display coordinates turns the crop box into the displayed page
+        let inverse = geometry.display_matrix().inverse();
+        assert_eq!(inverse.apply_rect(&crop), Rect::new(0.0, 0.0, 200.0, 100.0));
+
+        // A transform expressed in display coordinates, applied in user space
+        let scale = Matrix([0.5, 0.0, 0.0, 0.5, 0.0, 0.0]);
+        let in_user_space = inverse.then(&scale).then(&geometry.display_matrix());
+        let (x, y) = in_user_space.apply(110.0, 20.0); // display origin
+        assert_eq!((x, y), (110.0, 20.0));
+        assert_eq!(in_user_space.apply(10.0, 220.0), (60.0, 120.0)); // display (200, 100) → (100, 50)
+    }
+
+    #[test]
+    fn test_crop_box_clipped_to_media_box() {
+        let (doc, page_id) = document_with_page(
+            Dictionary::new(),
+            dictionary! {
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                "CropBox" => vec![(-10).into(), 0.into(), 700.into(), 792.into()],
+            },
+        );
+        let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+        assert_eq!(geometry.crop_box, Rect::new(0.0, 0.0, 612.0, 792.0));
+    }
+}
@@ -4,11 +4,36 @@
 //! without creating a separate watermark overlay file. This approach is simpler and more
 //! reliable than the overlay method.
 
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use lopdf::{Document, Object, ObjectId, Dictionary, Stream};
 use chrono::NaiveDate;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::date::format_date;
+use super::color::Color;
+use super::encryption::{encrypt_document, load_document, raise_version, OutputEncryption};
+use super::blanks::is_inserted_blank;
+use super::geometry::{Matrix, PageGeometry, Rect};
+use crate::layout::{calculate_safe_area, Length, PageDimensions};
+use super::shaping::{self, GlyphSet, ShapedGlyph, ShapingFont, TextDirection};
+use super::stamps::{Anchor, Stamp, StampContent, StampImage};
+use super::canvas::{apply_canvas, transform_annotations, CanvasOptions};
+use super::cover::{insert_cover, CoverOptions};
+use super::toc::{insert_toc, TocOptions};
+
+/// Name under which the header/footer layer is registered in page `/Properties`
+const LAYER_PROPERTY_NAME: &str = "HandoutLayer";
+
+/// XObject resource names for content drawn over and under the page content
+const OVERLAY_XOBJECT_NAME: &str = "HeaderFooter";
+const UNDERLAY_XOBJECT_NAME: &str = "HeaderFooterUnder";
+
+/// Font resource name of the Type0 font used for shaped text
+const SHAPED_FONT_RESOURCE: &str = "F4";
+
+/// Distance of left/right aligned text from the page edge (in points)
+const SIDE_MARGIN: f32 = 50.0;
 
 /// Options for masking existing header/footer content
 #[derive(Debug, Clone, Default)]
@@ -21,8 +46,8 @@ pub struct MaskOptions {
     pub header_all_height: Option<f32>,
     /// Height of footer mask on all pages (in inches)
     pub footer_all_height: Option<f32>,
-    /// Mask color as RGB tuple (0.0-1.0 for each component), defaults to white
-    pub color: (f32, f32, f32),
+    /// Mask color, defaults to opaque white
+    pub color: Color,
 }
 
 impl MaskOptions {
@@ -33,7 +58,7 @@ impl MaskOptions {
             footer_height: None,
             header_all_height: None,
             footer_all_height: None,
-            color: (1.0, 1.0, 1.0), // White
+            color: Color::WHITE,
         }
     }
 
@@ -73,8 +98,12 @@ impl MaskOptions {
 /// Options for adding headers and footers to a PDF
 #[derive(Debug, Clone)]
 pub struct HeaderFooterOptions {
-    /// Title to display on first page (centered at top)
+    /// Title to display on first page (at top; use | or [br] for line breaks)
     pub title: Option<String>,
+    /// Subtitle displayed below the title on the first page
+    pub subtitle: Option<String>,
+    /// Horizontal alignment of the title and subtitle
+    pub title_align: TextAlign,
     /// Footer left section content
     pub footer_left: Option<String>,
     /// Footer center section content
@@ -95,14 +124,298 @@ pub struct HeaderFooterOptions {
     pub header_font: Option<FontSpec>,
     /// Footer font specification (overrides footer_font_size if set)
     pub footer_font: Option<FontSpec>,
+    /// Subtitle font specification (defaults to two thirds of the title size)
+    pub subtitle_font: Option<FontSpec>,
     /// Masking options for covering existing header/footer content
     pub mask: MaskOptions,
+    /// Put the stamped headers/footers in an optional content layer (None = no layer)
+    pub layer: Option<LayerOptions>,
+    /// Natural language of the document (e.g. "en-US"), written to the catalog's `/Lang`
+    pub lang: Option<String>,
+    /// Base text direction of the title and each footer column
+    pub directions: ColumnDirections,
+    /// Font for shaped text (non-ASCII and right-to-left runs); defaults to the
+    /// bundled Liberation Serif, which covers Latin, Greek, Cyrillic and Hebrew
+    pub font_file: Option<PathBuf>,
+    /// Footer layout for landscape pages (None = same layout as portrait pages)
+    pub landscape: Option<PageTemplate>,
+    /// Whether masks, header and footer are drawn over or under the page content
+    pub placement: ElementPlacement,
+    /// Text and image stamps placed anywhere on selected pages
+    pub stamps: Vec<Stamp>,
+    /// Draw outlines of the page boxes, safe area, column budgets, masks and baselines
+    pub debug_layout: bool,
+    /// Shrink the original content into the area between header and footer (None = leave as is)
+    pub fit: Option<FitOptions>,
+    /// Add bands above and below each page (and optionally normalize its size)
+    /// so headers and footers get their own space
+    pub canvas: Option<CanvasOptions>,
+    /// Password for an encrypted input (files with an empty password open without one)
+    pub password: Option<String>,
+    /// Encrypt the output PDF (None = not encrypted)
+    pub encryption: Option<OutputEncryption>,
+    /// Notice centered on blank pages inserted by a duplex merge (None = leave them empty);
+    /// those pages never get headers, footers or stamps
+    pub blank_page_text: Option<String>,
+    /// Put a generated cover page in front (None = no cover); it is added after
+    /// the headers and footers, so it has none and page numbers skip it
+    pub cover: Option<CoverOptions>,
+    /// Put a table of contents in front (after the cover; None = no table); its
+    /// pages are numbered with the rest and the page numbers it lists count them
+    pub toc: Option<TocOptions>,
+}
+
+/// Footer layout for pages of one orientation
+///
+/// Portrait pages use the footer fields of [`HeaderFooterOptions`] directly;
+/// landscape pages (wider than tall as displayed, after `/Rotate`) use
+/// [`HeaderFooterOptions::landscape`] when it is set.
+#[derive(Debug, Clone, Default)]
+pub struct PageTemplate {
+    /// Footer left section content
+    pub footer_left: Option<String>,
+    /// Footer center section content
+    pub footer_center: Option<String>,
+    /// Footer right section content
+    pub footer_right: Option<String>,
+    /// Footer font specification (None = inherit `HeaderFooterOptions::footer_font`)
+    pub footer_font: Option<FontSpec>,
+    /// Join the lines of each column into one line, separated by `" | "`
+    pub single_line: bool,
+}
+
+impl PageTemplate {
+    /// Separator placed between joined lines in single-line mode
+    const LINE_SEPARATOR: &'static str = " | ";
+
+    /// Apply the template's line arrangement to a column's lines
+    fn arrange_lines(&self, lines: Vec<String>) -> Vec<String> {
+        if self.single_line && lines.len() > 1 {
+            vec![lines.join(Self::LINE_SEPARATOR)]
+        } else {
+            lines
+        }
+    }
+}
+
+/// Base text direction for each block of header/footer text
+///
+/// `Auto` picks the direction from the first strong character, so a Hebrew
+/// column and an English column can sit side by side without configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColumnDirections {
+    /// Title and subtitle
+    pub title: TextDirection,
+    /// Left footer column
+    pub left: TextDirection,
+    /// Center footer column
+    pub center: TextDirection,
+    /// Right footer column
+    pub right: TextDirection,
+}
+
+impl ColumnDirections {
+    /// Use the same direction everywhere
+    pub fn all(direction: TextDirection) -> Self {
+        Self { title: direction, left: direction, center: direction, right: direction }
+    }
+
+    /// Parse a direction specification
+    ///
+    /// Either a single direction for everything (`rtl`) or comma-separated
+    /// per-block settings (`title=rtl,right=rtl,left=ltr`). Blocks not
+    /// mentioned stay `auto`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        if !spec.contains('=') {
+            return Ok(Self::all(TextDirection::parse(spec)?));
+        }
+
+        let mut result = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=')
+                .ok_or_else(|| Error::General(format!("Invalid direction setting: {}", part)))?;
+            let direction = TextDirection::parse(value)?;
+            match key.trim().to_lowercase().as_str() {
+                "title" => result.title = direction,
+                "left" => result.left = direction,
+                "center" | "centre" => result.center = direction,
+                "right" => result.right = direction,
+                other => {
+                    return Err(Error::General(format!(
+                        "Unknown direction block: {} (expected title, left, center or right)",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Horizontal alignment of a text block between the page's side margins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    /// Flush with the left margin
+    Left,
+    /// Centered on the page
+    #[default]
+    Center,
+    /// Flush with the right margin
+    Right,
+}
+
+impl TextAlign {
+    /// Parse an alignment name (`left`, `center`/`centre`, `right`)
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "left" => Ok(TextAlign::Left),
+            "center" | "centre" => Ok(TextAlign::Center),
+            "right" => Ok(TextAlign::Right),
+            _ => Err(Error::General(format!("Invalid alignment: {} (expected left, center or right)", value))),
+        }
+    }
+}
+
+/// Where stamped content is drawn relative to the original page content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Placement {
+    /// Drawn after the page content, on top of it
+    #[default]
+    Over,
+    /// Drawn before the page content, underneath it (for background bands
+    /// and faint marks; hidden wherever the page paints an opaque background)
+    Under,
+}
+
+impl Placement {
+    /// Parse a placement name (`over`, `under`)
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "over" => Ok(Placement::Over),
+            "under" => Ok(Placement::Under),
+            _ => Err(Error::General(format!("Invalid placement: {} (expected over or under)", value))),
+        }
+    }
+}
+
+/// Placement of each kind of stamped element
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ElementPlacement {
+    /// Header and footer mask rectangles
+    pub masks: Placement,
+    /// Title and subtitle
+    pub header: Placement,
+    /// Footer columns
+    pub footer: Placement,
+}
+
+impl ElementPlacement {
+    /// Use the same placement for every element
+    pub fn all(placement: Placement) -> Self {
+        Self { masks: placement, header: placement, footer: placement }
+    }
+
+    /// Parse a placement specification
+    ///
+    /// Either a single placement for everything (`under`) or comma-separated
+    /// per-element settings (`masks=under,footer=over`). Elements not
+    /// mentioned stay `over`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        if !spec.contains('=') {
+            return Ok(Self::all(Placement::parse(spec)?));
+        }
+
+        let mut result = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=')
+                .ok_or_else(|| Error::General(format!("Invalid placement setting: {}", part)))?;
+            let placement = Placement::parse(value)?;
+            match key.trim().to_lowercase().as_str() {
+                "masks" | "mask" => result.masks = placement,
+                "header" | "title" => result.header = placement,
+                "footer" => result.footer = placement,
+                other => {
+                    return Err(Error::General(format!(
+                        "Unknown placement element: {} (expected masks, header or footer)",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Optional content group (layer) settings for stamped headers/footers
+///
+/// When set, the header/footer XObject is drawn inside a `/OC` marked-content
+/// section so viewers can hide it on screen or leave it out when printing.
+#[derive(Debug, Clone)]
+pub struct LayerOptions {
+    /// Layer name shown in the viewer's layers panel
+    pub name: String,
+    /// Whether the layer is visible on screen when the document is opened
+    pub visible: bool,
+    /// Whether the layer is included when the document is printed
+    pub printable: bool,
+}
+
+impl Default for LayerOptions {
+    fn default() -> Self {
+        Self {
+            name: "Handout Header/Footer".to_string(),
+            visible: true,
+            printable: true,
+        }
+    }
+}
+
+/// Shrink the original page content to leave room for headers and footers
+///
+/// The content is scaled (keeping its aspect ratio, never enlarged) into the
+/// safe area left by the reserves and centered there.
+#[derive(Debug, Clone, Copy)]
+pub struct FitOptions {
+    /// Space kept free at the top of the page for the header
+    pub header_reserve: Length,
+    /// Space kept free at the bottom of the page for the footer
+    pub footer_reserve: Length,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        Self {
+            header_reserve: Length::from_inches(1.0),
+            footer_reserve: Length::from_inches(1.0),
+        }
+    }
+}
+
+impl FitOptions {
+    /// Scale-and-translate matrix, in display coordinates, that moves a page of
+    /// `page_size` into the safe area
+    pub fn matrix(&self, page_size: (f32, f32)) -> Matrix {
+        let (width, height) = page_size;
+        let page = PageDimensions {
+            width: Length::from_pt(width as f64),
+            height: Length::from_pt(height as f64),
+        };
+        let (left, top, right, bottom) = calculate_safe_area(&page, self.header_reserve, self.footer_reserve);
+        let (left, top, right, bottom) = (left.pt() as f32, top.pt() as f32, right.pt() as f32, bottom.pt() as f32);
+
+        let scale = ((right - left) / width).min((top - bottom) / height).clamp(0.0, 1.0);
+        let x = left + (right - left - width * scale) / 2.0;
+        let y = bottom + (top - bottom - height * scale) / 2.0;
+        Matrix([scale, 0.0, 0.0, scale, x, y])
+    }
 }
 
 impl Default for HeaderFooterOptions {
     fn default() -> Self {
         Self {
             title: None,
+            subtitle: None,
+            title_align: TextAlign::Center,
             footer_left: None,
             footer_center: None,
             footer_right: None,
@@ -113,7 +426,23 @@ impl Default for HeaderFooterOptions {
             footer_font_size: 14.0,
             header_font: None,
             footer_font: None,
+            subtitle_font: None,
             mask: MaskOptions::new(),
+            layer: None,
+            lang: None,
+            directions: ColumnDirections::default(),
+            font_file: None,
+            landscape: None,
+            placement: ElementPlacement::default(),
+            stamps: Vec::new(),
+            debug_layout: false,
+            fit: None,
+            canvas: None,
+            password: None,
+            encryption: None,
+            blank_page_text: None,
+            cover: None,
+            toc: None,
         }
     }
 }
@@ -135,22 +464,47 @@ impl HeaderFooterOptions {
             .unwrap_or(self.footer_font_size)
     }
 
-    /// Get header color as PDF RGB string (e.g., "0 0 0" for black)
-    pub fn header_color_pdf(&self) -> String {
+    /// Get effective subtitle font size
+    pub fn effective_subtitle_font_size(&self) -> f32 {
+        self.subtitle_font
+            .as_ref()
+            .and_then(|f| f.size)
+            .unwrap_or(self.effective_header_font_size() * 2.0 / 3.0)
+    }
+
+    /// Get effective header color (black if not set)
+    pub fn header_color(&self) -> Color {
         self.header_font
             .as_ref()
             .and_then(|f| f.color)
-            .map(|(r, g, b)| format!("{:.3} {:.3} {:.3}", r, g, b))
-            .unwrap_or_else(|| "0 0 0".to_string())
+            .unwrap_or(Color::BLACK)
     }
 
-    /// Get footer color as PDF RGB string (e.g., "0 0 0" for black)
-    pub fn footer_color_pdf(&self) -> String {
+    /// Get effective footer color (black if not set)
+    pub fn footer_color(&self) -> Color {
         self.footer_font
             .as_ref()
             .and_then(|f| f.color)
-            .map(|(r, g, b)| format!("{:.3} {:.3} {:.3}", r, g, b))
-            .unwrap_or_else(|| "0 0 0".to_string())
+            .unwrap_or(Color::BLACK)
+    }
+
+    /// Footer layout for a portrait or landscape page
+    ///
+    /// A landscape template without its own font inherits `footer_font`.
+    pub fn footer_template(&self, landscape: bool) -> PageTemplate {
+        match self.landscape {
+            Some(ref template) if landscape => PageTemplate {
+                footer_font: template.footer_font.clone().or_else(|| self.footer_font.clone()),
+                ..template.clone()
+            },
+            _ => PageTemplate {
+                footer_left: self.footer_left.clone(),
+                footer_center: self.footer_center.clone(),
+                footer_right: self.footer_right.clone(),
+                footer_font: self.footer_font.clone(),
+                single_line: false,
+            },
+        }
     }
 }
 
@@ -190,15 +544,44 @@ pub fn add_headers_footers(
     options: &HeaderFooterOptions,
 ) -> Result<()> {
     // Load the PDF
-    let mut doc = Document::load(input_path)?;
+    let mut doc = load_document(input_path, options.password.as_deref())?;
 
     // Decompress for easier content stream parsing
     doc.decompress();
 
+    // The table of contents goes in first, so it gets headers, footers and page numbers too
+    if let Some(ref toc) = options.toc {
+        insert_toc(&mut doc, toc)?;
+    }
+
     let page_count = doc.get_pages().len();
 
-    // Embed Liberation Serif font for proper text rendering
-    let font_id = embed_liberation_serif(&mut doc)?;
+    // Embed Liberation Serif (plus the standard sans/mono faces) for text rendering
+    let fonts = FontResources::add_to_document(&mut doc)?;
+
+    // Font for shaped text; its Type0 font is written once all glyphs are known
+    let shaping_font = Rc::new(match options.font_file {
+        Some(ref path) => ShapingFont::from_file(path)?,
+        None => ShapingFont::liberation_serif(),
+    });
+    let mut used_glyphs = GlyphSet::new();
+
+    // Embed each stamp image once; every page using it shares the XObject
+    let mut images = HashMap::new();
+    for stamp in &options.stamps {
+        if let StampContent::Image(ref path) = stamp.content {
+            if !images.contains_key(path) {
+                images.insert(path.clone(), StampImage::embed(&mut doc, path)?);
+            }
+        }
+    }
+    let images = Rc::new(images);
+
+    // Register the optional content group once; every page references the same layer
+    let layer_id = match options.layer {
+        Some(ref layer) => Some(add_optional_content_group(&mut doc, layer)?),
+        None => None,
+    };
 
     // Collect page info first (to avoid borrow issues)
     let pages: Vec<(usize, ObjectId)> = doc.get_pages()
@@ -211,51 +594,124 @@ pub fn add_headers_footers(
     for (i, page_id) in pages.iter() {
         let page_number = i + 1;
 
+        // Grow the page first so the header and footer land in the new bands
+        if let Some(ref canvas) = options.canvas {
+            apply_canvas(&mut doc, *page_id, canvas)?;
+        }
+
+        // Lay out in display coordinates: the visible box, after rotation
+        let geometry = PageGeometry::from_page(&doc, *page_id)?;
+        let page_size = (geometry.display_width(), geometry.display_height());
+
         // Generate the content stream for this page's headers/footers
-        let content = generate_header_footer_content(
-            page_number,
-            page_count,
-            page_number == 1, // is_first_page
-            options,
-        );
+        let mut resources = ContentResources::new(Rc::clone(&shaping_font), Rc::clone(&images));
+        resources.layout = options.debug_layout.then(LayoutDebug::default);
+        let mut content = if is_inserted_blank(&doc, *page_id) {
+            generate_blank_page_notice(page_number, page_count, options, page_size, &mut resources)?
+        } else {
+            generate_header_footer_content(
+                page_number,
+                page_count,
+                page_number == 1, // is_first_page
+                options,
+                page_size,
+                &mut resources,
+            )?
+        };
+
+        // Layout outlines go on top of everything else
+        if let Some(ref layout) = resources.layout {
+            content.over.push_str(&generate_layout_debug(&geometry, layout));
+        }
 
         // Create a Form XObject (no inverse transform needed - we reset CTM with q/Q wrapper)
-        let xobject_id = create_form_xobject(&mut doc, content, font_id)?;
+        let xobject_id = create_form_xobject(&mut doc, content.over, fonts, &resources, page_size)?;
+        add_xobject_to_page_resources(&mut doc, *page_id, OVERLAY_XOBJECT_NAME, xobject_id)?;
+
+        // Content placed under the page gets its own XObject, drawn before the original content
+        let has_underlay = !content.under.is_empty();
+        if has_underlay {
+            let underlay_id = create_form_xobject(&mut doc, content.under, fonts, &resources, page_size)?;
+            add_xobject_to_page_resources(&mut doc, *page_id, UNDERLAY_XOBJECT_NAME, underlay_id)?;
+        }
+        shaping::merge_glyphs(&mut used_glyphs, std::mem::take(&mut resources.glyphs));
 
-        // Add the Form XObject to the page's Resources
-        add_xobject_to_page_resources(&mut doc, *page_id, xobject_id)?;
+        // The /OC marked-content tag refers to the layer through the page's /Properties
+        if let Some(ocg_id) = layer_id {
+            add_property_to_page_resources(&mut doc, *page_id, LAYER_PROPERTY_NAME, ocg_id)?;
+        }
 
         // Wrap original content in q/Q and append XObject invocation
         // This is the key: the Q resets the graphics state (including CTM),
-        // then we draw our XObject in clean page coordinates
-        wrap_content_and_append_xobject(&mut doc, *page_id)?;
+        // then we draw our XObject through geometry.display_matrix(), which maps
+        // display coordinates to the page's own
+        let matrix = geometry.display_matrix();
+        let in_layer = layer_id.is_some();
+        let underlay = if has_underlay {
+            xobject_invocation(UNDERLAY_XOBJECT_NAME, matrix, in_layer)
+        } else {
+            String::new()
+        };
+        let overlay = xobject_invocation(OVERLAY_XOBJECT_NAME, matrix, in_layer);
+
+        // Fitting works in display coordinates; convert it to the page's user space
+        let content_transform = options.fit.map(|fit| {
+            matrix.inverse().then(&fit.matrix(page_size)).then(&matrix)
+        });
+        wrap_content_and_append_xobject(&mut doc, *page_id, &underlay, &overlay, content_transform)?;
+        // Links and form widgets follow the content they cover
+        if let Some(transform) = content_transform {
+            transform_annotations(&mut doc, *page_id, transform)?;
+        }
+    }
+
+    if !used_glyphs.is_empty() {
+        let font_file = shaping_font.is_bundled().then_some(fonts.serif_file);
+        shaping_font.write_type0(&mut doc, fonts.shaped, &used_glyphs, font_file)?;
+    }
+
+    if let Some(ref cover) = options.cover {
+        insert_cover(&mut doc, cover)?;
+    }
+
+    // Tagged sources keep their /StructTreeRoot and /MarkInfo untouched: our
+    // content is marked as artifacts, so the structure tree stays valid as is
+    if let Some(ref lang) = options.lang {
+        let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+        doc.get_dictionary_mut(catalog_id)?.set("Lang", text_string_object(lang));
     }
 
     // Save the modified PDF
     doc.compress();
+    if let Some(ref encryption) = options.encryption {
+        encrypt_document(&mut doc, encryption)?;
+    }
     doc.save(output_path)?;
 
     Ok(())
 }
 
-/// Use Helvetica (standard PDF font - simpler than embedding)
-#[allow(dead_code)]
-fn use_helvetica_font(doc: &mut Document) -> Result<ObjectId> {
-    // Create a simple Type1 font using Helvetica (one of the 14 standard PDF fonts)
+/// Register one of the 14 standard PDF fonts (simpler than embedding)
+///
+/// Uses WinAnsiEncoding so it accepts the same single-byte strings as the
+/// embedded Liberation Serif font.
+fn add_standard_font(doc: &mut Document, base_font: &str) -> ObjectId {
     let mut font = Dictionary::new();
     font.set("Type", Object::Name(b"Font".to_vec()));
     font.set("Subtype", Object::Name(b"Type1".to_vec()));
-    font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+    font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+    font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
 
-    let font_id = doc.add_object(Object::Dictionary(font));
-    Ok(font_id)
+    doc.add_object(Object::Dictionary(font))
 }
 
 /// Embed Liberation Serif TrueType font with WinAnsiEncoding
 ///
 /// This embeds the font data directly in the PDF so it renders correctly
 /// on any system, regardless of whether the font is installed.
-fn embed_liberation_serif(doc: &mut Document) -> Result<ObjectId> {
+///
+/// Returns the font and the font file stream, which the shaped-text font reuses.
+fn embed_liberation_serif(doc: &mut Document) -> Result<(ObjectId, ObjectId)> {
     // Load the embedded font data
     const LIBERATION_SERIF: &[u8] = include_bytes!("../../assets/fonts/LiberationSerif-Regular.ttf");
 
@@ -312,7 +768,7 @@ fn embed_liberation_serif(doc: &mut Document) -> Result<ObjectId> {
     font.set("Widths", Object::Array(widths));
 
     let font_id = doc.add_object(Object::Dictionary(font));
-    Ok(font_id)
+    Ok((font_id, font_stream_id))
 }
 
 /// Create widths array for Liberation Serif (chars 32-255)
@@ -550,18 +1006,241 @@ fn create_liberation_serif_widths() -> Vec<Object> {
     widths.into_iter().map(Object::Integer).collect()
 }
 
+/// Graphics state resources referenced by generated content
+///
+/// Collected while generating a page's content so the Form XObject only
+/// declares the ExtGState entries it actually uses.
+#[derive(Debug)]
+struct ContentResources {
+    /// Opacities in thousandths, each backed by a `/GSa{n}` ExtGState
+    opacities: BTreeSet<u16>,
+    /// Font used for shaped text
+    font: Rc<ShapingFont>,
+    /// Glyphs drawn with the shaped-text font
+    glyphs: GlyphSet,
+    /// Embedded stamp images by source path
+    images: Rc<HashMap<PathBuf, StampImage>>,
+    /// Image XObjects drawn, by resource name
+    used_images: BTreeMap<String, ObjectId>,
+    /// Layout geometry, recorded only when drawing the layout debug overlay
+    layout: Option<LayoutDebug>,
+}
+
+impl Default for ContentResources {
+    fn default() -> Self {
+        Self::new(Rc::new(ShapingFont::liberation_serif()), Rc::default())
+    }
+}
+
+impl ContentResources {
+    fn new(font: Rc<ShapingFont>, images: Rc<HashMap<PathBuf, StampImage>>) -> Self {
+        Self {
+            opacities: BTreeSet::new(),
+            font,
+            glyphs: GlyphSet::new(),
+            images,
+            used_images: BTreeMap::new(),
+            layout: None,
+        }
+    }
+
+    /// Record a block of text lines for the layout overlay
+    ///
+    /// The block's budget spans `x_range` from one font size above the first
+    /// baseline to the descent below the last.
+    fn record_block(&mut self, zone: Zone, x_range: (f32, f32), baselines: &[f32], font_size: f32) {
+        let Some(ref mut layout) = self.layout else { return };
+        let (Some(first), Some(last)) = (baselines.first(), baselines.last()) else { return };
+        let (x0, x1) = x_range;
+        layout.columns.push((zone, Rect::new(x0, last - font_size * DESCENT, x1, first + font_size)));
+        layout.baselines.extend(baselines.iter().map(|&y| (x0, x1, y)));
+    }
+
+    /// Record a mask rectangle for the layout overlay
+    fn record_mask(&mut self, zone: Zone, rect: Rect) {
+        if let Some(ref mut layout) = self.layout {
+            layout.masks.push((zone, rect));
+        }
+    }
+
+    /// Shape a run and remember its glyphs for the document's Type0 font
+    fn shape(&mut self, text: &str, rtl: bool) -> Vec<ShapedGlyph> {
+        let glyphs = self.font.shape(text, rtl);
+        shaping::record_glyphs(&mut self.glyphs, &glyphs);
+        glyphs
+    }
+
+    /// Whether the shaped-text font is used by this content
+    fn uses_shaped_font(&self) -> bool {
+        !self.glyphs.is_empty()
+    }
+
+    /// Emit operators selecting `color` for both fill and stroke
+    ///
+    /// Translucent colors also select an ExtGState with matching `/ca` and
+    /// `/CA`, so callers should scope them in `q`/`Q`.
+    fn set_color(&mut self, content: &mut String, color: &Color) {
+        content.push_str(&format!("{}\n", color.fill_operator()));
+        content.push_str(&format!("{}\n", color.stroke_operator()));
+        if !color.is_opaque() {
+            let permille = (color.alpha.clamp(0.0, 1.0) * 1000.0).round() as u16;
+            self.opacities.insert(permille);
+            content.push_str(&format!("/GSa{} gs\n", permille));
+        }
+    }
+
+    /// Build the /ExtGState resource subdictionary (None if nothing is used)
+    fn ext_g_state_dictionary(&self) -> Option<Dictionary> {
+        if self.opacities.is_empty() {
+            return None;
+        }
+        let mut states = Dictionary::new();
+        for permille in &self.opacities {
+            let alpha = *permille as f32 / 1000.0;
+            let mut state = Dictionary::new();
+            state.set("Type", Object::Name(b"ExtGState".to_vec()));
+            state.set("ca", Object::Real(alpha));
+            state.set("CA", Object::Real(alpha));
+            states.set(format!("GSa{}", permille), Object::Dictionary(state));
+        }
+        Some(states)
+    }
+
+    /// Build the /XObject resource subdictionary for stamp images (None if nothing is used)
+    fn image_dictionary(&self) -> Option<Dictionary> {
+        if self.used_images.is_empty() {
+            return None;
+        }
+        let mut images = Dictionary::new();
+        for (name, id) in &self.used_images {
+            images.set(name.as_str(), Object::Reference(*id));
+        }
+        Some(images)
+    }
+}
+
+/// Approximate descent below the baseline, as a fraction of the font size
+const DESCENT: f32 = 0.25;
+
+/// Header or footer zone of the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zone {
+    Header,
+    Footer,
+}
+
+/// Layout geometry recorded while generating a page's content
+#[derive(Debug, Default)]
+struct LayoutDebug {
+    /// Budgets of the title block and footer columns
+    columns: Vec<(Zone, Rect)>,
+    /// Mask rectangles
+    masks: Vec<(Zone, Rect)>,
+    /// Text baselines as `(x0, x1, y)`
+    baselines: Vec<(f32, f32, f32)>,
+}
+
+impl LayoutDebug {
+    /// Lowest point reached by header content (None if there is none)
+    fn header_bottom(&self) -> Option<f32> {
+        self.zone_rects(Zone::Header).map(|r| r.y0).reduce(f32::min)
+    }
+
+    /// Highest point reached by footer content (None if there is none)
+    fn footer_top(&self) -> Option<f32> {
+        self.zone_rects(Zone::Footer).map(|r| r.y1).reduce(f32::max)
+    }
+
+    fn zone_rects(&self, zone: Zone) -> impl Iterator<Item = &Rect> {
+        self.columns.iter().chain(&self.masks)
+            .filter(move |(z, _)| *z == zone)
+            .map(|(_, rect)| rect)
+    }
+}
+
+/// Horizontal budget of footer column `index` (0 = left, 1 = center, 2 = right)
+///
+/// The space between the side margins is split into equal thirds.
+fn footer_column_budget(page_width: f32, index: usize) -> (f32, f32) {
+    let third = (page_width - 2.0 * SIDE_MARGIN) / 3.0;
+    let x0 = SIDE_MARGIN + third * index as f32;
+    (x0, x0 + third)
+}
+
+/// Draw thin outlines of the page layout (for `debug_layout`)
+///
+/// Blue: media box, green: crop box, dashed magenta: safe area between header
+/// and footer, orange: title and footer column budgets, red: masks,
+/// cyan: text baselines. All in display coordinates.
+fn generate_layout_debug(geometry: &PageGeometry, layout: &LayoutDebug) -> String {
+    let (page_width, page_height) = (geometry.display_width(), geometry.display_height());
+    let to_display = geometry.display_matrix().inverse();
+    let outline = |ops: &mut String, rect: &Rect| {
+        ops.push_str(&format!("{} {} {} {} re S\n", rect.x0, rect.y0, rect.width(), rect.height()));
+    };
+
+    let mut ops = String::from("0.5 w\n");
+    ops.push_str(&format!("{}\n", Color::rgb(0.0, 0.0, 1.0).stroke_operator()));
+    outline(&mut ops, &to_display.apply_rect(&geometry.media_box));
+    ops.push_str(&format!("{}\n", Color::rgb(0.0, 0.6, 0.0).stroke_operator()));
+    outline(&mut ops, &Rect::new(0.0, 0.0, page_width, page_height));
+
+    // Safe area: what's left between the header and footer zones
+    let page = PageDimensions {
+        width: Length::from_pt(page_width as f64),
+        height: Length::from_pt(page_height as f64),
+    };
+    let header_height = layout.header_bottom().map_or(0.0, |bottom| page_height - bottom);
+    let footer_height = layout.footer_top().unwrap_or(0.0);
+    let (left, top, right, bottom) = calculate_safe_area(
+        &page,
+        Length::from_pt(header_height as f64),
+        Length::from_pt(footer_height as f64),
+    );
+    ops.push_str(&format!("{}\n", Color::rgb(0.8, 0.0, 0.8).stroke_operator()));
+    ops.push_str("[4 2] 0 d\n");
+    outline(&mut ops, &Rect::new(left.pt() as f32, bottom.pt() as f32, right.pt() as f32, top.pt() as f32));
+    ops.push_str("[] 0 d\n");
+
+    ops.push_str(&format!("{}\n", Color::rgb(1.0, 0.5, 0.0).stroke_operator()));
+    for (_, rect) in &layout.columns {
+        outline(&mut ops, rect);
+    }
+    ops.push_str(&format!("{}\n", Color::rgb(1.0, 0.0, 0.0).stroke_operator()));
+    for (_, rect) in &layout.masks {
+        outline(&mut ops, rect);
+    }
+    ops.push_str(&format!("{}\n", Color::rgb(0.0, 0.7, 0.9).stroke_operator()));
+    for (x0, x1, y) in &layout.baselines {
+        ops.push_str(&format!("{} {} m {} {} l S\n", x0, y, x1, y));
+    }
+
+    format!("/Artifact <</Type /Layout>> BDC\nq\n{}Q\nEMC\n", ops)
+}
+
 /// Generate PDF content stream operators for headers/footers
+///
+/// `page_size` is the displayed width and height of the page; landscape pages
+/// use the landscape footer template if one is set.
 fn generate_header_footer_content(
     page_num: usize,
     total_pages: usize,
     is_first_page: bool,
     options: &HeaderFooterOptions,
-) -> String {
-    let mut content = String::new();
-
-    // Page dimensions (US Letter: 612pt × 792pt)
-    let page_width = 612.0;
-    let page_height = 792.0;
+    page_size: (f32, f32),
+    resources: &mut ContentResources,
+) -> Result<PlacedContent> {
+    // Header and footer content are kept apart so each can be marked as a
+    // pagination artifact for tagged PDFs; masks are kept apart from text so
+    // each element can be placed over or under the page content
+    let mut header_mask = String::new();
+    let mut header = String::new();
+    let mut footer_mask = String::new();
+    let mut footer = String::new();
+
+    // Page dimensions as displayed (US Letter: 612pt × 792pt)
+    let (page_width, page_height) = page_size;
+    let template = options.footer_template(page_width > page_height);
 
     // Points per inch for converting mask heights
     const POINTS_PER_INCH: f32 = 72.0;
@@ -570,75 +1249,109 @@ fn generate_header_footer_content(
     // Header mask (at top of page)
     if let Some(header_height_inches) = options.mask.effective_header_height(is_first_page) {
         let height_pt = header_height_inches * POINTS_PER_INCH;
-        let (r, g, b) = options.mask.color;
-        // Set fill color
-        content.push_str(&format!("{:.3} {:.3} {:.3} rg\n", r, g, b));
+        // Scope the mask color (and its opacity) to the rectangle
+        header_mask.push_str("q\n");
+        resources.set_color(&mut header_mask, &options.mask.color);
         // Draw rectangle: x y width height re (rectangle) f (fill)
         // Header is at top of page, so y = page_height - height
-        content.push_str(&format!("0 {} {} {} re f\n",
+        header_mask.push_str(&format!("0 {} {} {} re f\n",
             page_height - height_pt,
             page_width,
             height_pt
         ));
+        header_mask.push_str("Q\n");
+        resources.record_mask(Zone::Header, Rect::new(0.0, page_height - height_pt, page_width, page_height));
+    }
+
+    // Get effective font sizes from options (respects FontSpec if set)
+    let header_font_size = options.effective_header_font_size();
+    let footer_font_size = template.footer_font
+        .as_ref()
+        .and_then(|f| f.size)
+        .unwrap_or(options.footer_font_size);
+
+    // Add title and subtitle on first page
+    if is_first_page && (options.title.is_some() || options.subtitle.is_some()) {
+        // First baseline 50pt from top of page (PDF coordinates: bottom-left origin);
+        // further title and subtitle lines stack downward
+        let mut baseline = page_height - 50.0;
+
+        // Set header color
+        resources.set_color(&mut header, &options.header_color());
+
+        let title_layout = BlockLayout {
+            align: options.title_align,
+            direction: options.directions.title,
+            page_width,
+        };
+
+        if let Some(ref title) = options.title {
+            let title_style = options.header_font.clone().unwrap_or_default();
+            let expanded = expand_placeholders(title, page_num, total_pages, options.date.as_ref());
+            header.push_str(&generate_text_block(
+                &expanded,
+                &mut baseline,
+                header_font_size,
+                &title_style,
+                title_layout,
+                resources,
+            )?);
+        }
+
+        if let Some(ref subtitle) = options.subtitle {
+            // The subtitle has its own font but keeps the header color unless it sets one
+            let subtitle_style = options.subtitle_font.clone().unwrap_or_default();
+            if let Some(ref color) = subtitle_style.color {
+                resources.set_color(&mut header, color);
+            }
+
+            let expanded = expand_placeholders(subtitle, page_num, total_pages, options.date.as_ref());
+            header.push_str(&generate_text_block(
+                &expanded,
+                &mut baseline,
+                options.effective_subtitle_font_size(),
+                &subtitle_style,
+                title_layout,
+                resources,
+            )?);
+        }
     }
 
     // Footer mask (at bottom of page)
     if let Some(footer_height_inches) = options.mask.effective_footer_height(is_first_page) {
         let height_pt = footer_height_inches * POINTS_PER_INCH;
-        let (r, g, b) = options.mask.color;
-        // Set fill color
-        content.push_str(&format!("{:.3} {:.3} {:.3} rg\n", r, g, b));
+        // Scope the mask color (and its opacity) to the rectangle
+        footer_mask.push_str("q\n");
+        resources.set_color(&mut footer_mask, &options.mask.color);
         // Draw rectangle at bottom of page (y = 0)
-        content.push_str(&format!("0 0 {} {} re f\n",
+        footer_mask.push_str(&format!("0 0 {} {} re f\n",
             page_width,
             height_pt
         ));
+        footer_mask.push_str("Q\n");
+        resources.record_mask(Zone::Footer, Rect::new(0.0, 0.0, page_width, height_pt));
     }
 
-    // Get effective font sizes from options (respects FontSpec if set)
-    let header_font_size = options.effective_header_font_size();
-    let footer_font_size = options.effective_footer_font_size();
-
-    // Add title on first page
-    if is_first_page {
-        if let Some(ref title) = options.title {
-            // Expand placeholders in title
-            let expanded_title = expand_placeholders(title, page_num, total_pages, options.date.as_ref());
-
-            // Position title 50pt from top of page (PDF coordinates: bottom-left origin)
-            let title_y = page_height - 50.0;
-            let title_width = estimate_text_width(&expanded_title, header_font_size);
-            let title_x = (page_width - title_width) / 2.0; // Center
-
-            // Set header color (RGB)
-            let header_color = options.header_color_pdf();
-            content.push_str(&format!("{} rg\n", header_color)); // Fill color
-            content.push_str(&format!("{} RG\n", header_color)); // Stroke color
-
-            content.push_str("BT\n");
-            content.push_str("0 Tr\n"); // Fill text
-            content.push_str(&format!("/F1 {} Tf\n", header_font_size));
-            content.push_str(&format!("1 0 0 1 {} {} Tm\n", title_x, title_y));
-            content.push_str(&format!("({}) Tj\n", escape_pdf_string(&expanded_title)));
-            content.push_str("ET\n");
-        }
-    }
-
-    // Set footer color (RGB)
-    let footer_color = options.footer_color_pdf();
-    content.push_str(&format!("{} rg\n", footer_color)); // Fill color
-    content.push_str(&format!("{} RG\n", footer_color)); // Stroke color
+    // Set footer color
+    let footer_color = template.footer_font
+        .as_ref()
+        .and_then(|f| f.color)
+        .unwrap_or(Color::BLACK);
+    resources.set_color(&mut footer, &footer_color);
 
     // Add footers
     // We position footer lines starting from the bottom of the page, with the
     // first line at the top of the footer area and subsequent lines below it.
     let line_height = footer_font_size * 1.2;
 
+    // Inline [font] tags build on the footer font specification
+    let footer_style = template.footer_font.clone().unwrap_or_default();
+
     // Footer left
-    if let Some(ref left_text) = options.footer_left {
+    if let Some(ref left_text) = template.footer_left {
         // Expand placeholders first, then parse lines
         let expanded = expand_placeholders(left_text, page_num, total_pages, options.date.as_ref());
-        let lines = parse_multiline_text(&expanded);
+        let lines = template.arrange_lines(parse_multiline_text(&expanded));
         let num_lines = lines.len();
         // Calculate top of footer area: start high enough to fit all lines above the margin
         let footer_top = 30.0 + ((num_lines - 1) as f32 * line_height);
@@ -646,45 +1359,189 @@ fn generate_header_footer_content(
             // First line at top, subsequent lines below (Y decreases)
             let y = footer_top - (i as f32 * line_height);
             // Use font tag rendering for styled text
-            content.push_str(&generate_line_with_font_tags(line, 50.0, y, footer_font_size));
+            footer.push_str(&generate_line_with_font_tags(
+                line, SIDE_MARGIN, y, footer_font_size, &footer_style, options.directions.left, resources,
+            )?);
         }
+        let baselines: Vec<f32> = (0..num_lines).map(|i| footer_top - i as f32 * line_height).collect();
+        resources.record_block(Zone::Footer, footer_column_budget(page_width, 0), &baselines, footer_font_size);
     }
 
     // Footer center
-    if let Some(ref center_text) = options.footer_center {
+    if let Some(ref center_text) = template.footer_center {
         // Expand placeholders first, then parse lines
         let expanded = expand_placeholders(center_text, page_num, total_pages, options.date.as_ref());
-        let lines = parse_multiline_text(&expanded);
+        let lines = template.arrange_lines(parse_multiline_text(&expanded));
         let num_lines = lines.len();
         let footer_top = 30.0 + ((num_lines - 1) as f32 * line_height);
         for (i, line) in lines.iter().enumerate() {
             let y = footer_top - (i as f32 * line_height);
             // Use width calculation that excludes font tags
-            let text_width = estimate_text_width_with_tags(line, footer_font_size);
+            let text_width = estimate_text_width_with_tags(line, footer_font_size, &footer_style, &resources.font)?;
             let x = (page_width - text_width) / 2.0;
             // Use font tag rendering for styled text
-            content.push_str(&generate_line_with_font_tags(line, x, y, footer_font_size));
+            footer.push_str(&generate_line_with_font_tags(
+                line, x, y, footer_font_size, &footer_style, options.directions.center, resources,
+            )?);
         }
+        let baselines: Vec<f32> = (0..num_lines).map(|i| footer_top - i as f32 * line_height).collect();
+        resources.record_block(Zone::Footer, footer_column_budget(page_width, 1), &baselines, footer_font_size);
     }
 
     // Footer right - now uses placeholder-based content like other footers
-    if let Some(ref right_text) = options.footer_right {
+    if let Some(ref right_text) = template.footer_right {
         // Expand placeholders first, then parse lines
         let expanded = expand_placeholders(right_text, page_num, total_pages, options.date.as_ref());
-        let lines = parse_multiline_text(&expanded);
+        let lines = template.arrange_lines(parse_multiline_text(&expanded));
         let num_lines = lines.len();
         let footer_top = 30.0 + ((num_lines.saturating_sub(1)) as f32 * line_height);
         for (i, line) in lines.iter().enumerate() {
             let y = footer_top - (i as f32 * line_height);
             // Use width calculation that excludes font tags
-            let text_width = estimate_text_width_with_tags(line, footer_font_size);
-            let x = page_width - 50.0 - text_width; // Right-aligned with margin
+            let text_width = estimate_text_width_with_tags(line, footer_font_size, &footer_style, &resources.font)?;
+            let x = page_width - SIDE_MARGIN - text_width; // Right-aligned with margin
             // Use font tag rendering for styled text
-            content.push_str(&generate_line_with_font_tags(line, x, y, footer_font_size));
+            footer.push_str(&generate_line_with_font_tags(
+                line, x, y, footer_font_size, &footer_style, options.directions.right, resources,
+            )?);
         }
+        let baselines: Vec<f32> = (0..num_lines).map(|i| footer_top - i as f32 * line_height).collect();
+        resources.record_block(Zone::Footer, footer_column_budget(page_width, 2), &baselines, footer_font_size);
     }
 
-    content
+    // Masks come first within each layer so they stay behind the text
+    let placement = options.placement;
+    let mut content = PlacedContent::default();
+    for (mask, text, text_placement, subtype) in [
+        (header_mask, header, placement.header, "Header"),
+        (footer_mask, footer, placement.footer, "Footer"),
+    ] {
+        let (mut over, mut under) = (String::new(), String::new());
+        match placement.masks {
+            Placement::Over => over.push_str(&mask),
+            Placement::Under => under.push_str(&mask),
+        }
+        match text_placement {
+            Placement::Over => over.push_str(&text),
+            Placement::Under => under.push_str(&text),
+        }
+        content.over.push_str(&wrap_pagination_artifact(&over, subtype));
+        content.under.push_str(&wrap_pagination_artifact(&under, subtype));
+    }
+
+    // Stamps on this page, in the order given
+    let (mut over, mut under) = (String::new(), String::new());
+    for stamp in options.stamps.iter().filter(|s| s.pages.contains(page_num, total_pages)) {
+        let ops = generate_stamp(stamp, page_num, total_pages, options, page_size, resources)?;
+        match stamp.placement {
+            Placement::Over => over.push_str(&ops),
+            Placement::Under => under.push_str(&ops),
+        }
+    }
+    content.over.push_str(&wrap_pagination_artifact(&over, "Watermark"));
+    content.under.push_str(&wrap_pagination_artifact(&under, "Watermark"));
+    Ok(content)
+}
+
+/// Content for a blank page inserted by a duplex merge: only the optional
+/// notice, centered on the page in the footer font
+fn generate_blank_page_notice(
+    page_num: usize,
+    total_pages: usize,
+    options: &HeaderFooterOptions,
+    page_size: (f32, f32),
+    resources: &mut ContentResources,
+) -> Result<PlacedContent> {
+    let mut content = PlacedContent::default();
+    if let Some(ref text) = options.blank_page_text {
+        let notice = Stamp { anchor: Anchor::Center, ..Stamp::text(text.clone()) };
+        let ops = generate_stamp(&notice, page_num, total_pages, options, page_size, resources)?;
+        content.over = wrap_pagination_artifact(&ops, "Watermark");
+    }
+    Ok(content)
+}
+
+/// Generate content stream operators for one stamp
+///
+/// Text stamps are laid out like a footer column: the box runs from the last
+/// line's baseline to one font size above the first line's, and lines are
+/// aligned within it following the anchor's horizontal position.
+fn generate_stamp(
+    stamp: &Stamp,
+    page_num: usize,
+    total_pages: usize,
+    options: &HeaderFooterOptions,
+    page_size: (f32, f32),
+    resources: &mut ContentResources,
+) -> Result<String> {
+    let (x, y) = (stamp.x.pt() as f32, stamp.y.pt() as f32);
+
+    match stamp.content {
+        StampContent::Text(ref text) => {
+            let style = stamp.font.clone()
+                .or_else(|| options.footer_font.clone())
+                .unwrap_or_default();
+            let font_size = style.size.unwrap_or(options.footer_font_size);
+            let line_height = font_size * 1.2;
+
+            let expanded = expand_placeholders(text, page_num, total_pages, options.date.as_ref());
+            let lines = parse_multiline_text(&expanded);
+            let widths = lines.iter()
+                .map(|line| estimate_text_width_with_tags(line, font_size, &style, &resources.font))
+                .collect::<Result<Vec<f32>>>()?;
+            let width = widths.iter().copied().fold(0.0, f32::max);
+            let height = font_size + lines.len().saturating_sub(1) as f32 * line_height;
+            let (left, bottom) = stamp.anchor.position(x, y, (width, height), page_size);
+            let (align, _) = stamp.anchor.fractions();
+
+            // Scope the stamp's color to the stamp
+            let mut ops = String::from("q\n");
+            resources.set_color(&mut ops, &style.color.unwrap_or(Color::BLACK));
+            for (i, (line, line_width)) in lines.iter().zip(&widths).enumerate() {
+                let line_x = left + (width - line_width) * align;
+                let baseline = bottom + (lines.len() - 1 - i) as f32 * line_height;
+                ops.push_str(&generate_line_with_font_tags(
+                    line, line_x, baseline, font_size, &style, stamp.direction, resources,
+                )?);
+            }
+            ops.push_str("Q\n");
+            Ok(ops)
+        }
+        StampContent::Image(ref path) => {
+            let image = *resources.images.get(path)
+                .ok_or_else(|| Error::General(format!("Stamp image not loaded: {}", path.display())))?;
+            let (width, height) = image.size(stamp.width, stamp.height);
+            let (left, bottom) = stamp.anchor.position(x, y, (width, height), page_size);
+
+            let name = format!("Im{}", image.id.0);
+            let ops = format!("q {} 0 0 {} {} {} cm /{} Do Q\n", width, height, left, bottom, name);
+            resources.used_images.insert(name, image.id);
+            Ok(ops)
+        }
+    }
+}
+
+/// Header/footer content streams drawn over and under the page content
+#[derive(Debug, Default)]
+struct PlacedContent {
+    over: String,
+    under: String,
+}
+
+/// Mark generated content as a pagination artifact
+///
+/// Screen readers skip artifacts, so our stamped text doesn't break the reading
+/// order of tagged PDFs. Empty content produces no marked-content section.
+/// The content is also wrapped in `q`/`Q` so colors and opacity set for the
+/// header don't carry over into the footer.
+fn wrap_pagination_artifact(content: &str, subtype: &str) -> String {
+    if content.is_empty() {
+        return String::new();
+    }
+    format!(
+        "/Artifact <</Type /Pagination /Subtype /{}>> BDC\nq\n{}Q\nEMC\n",
+        subtype, content
+    )
 }
 
 /// Expand placeholders in text
@@ -718,12 +1575,14 @@ fn expand_placeholders(text: &str, page_num: usize, total_pages: usize, date: Op
 
 /// Parsed font specification from CLI-style string
 ///
-/// Format: `[weight] [style] [size] [family] [color]`
+/// Format: `[weight] [style] [decoration] [size] [family] [color]`
 /// Examples:
 /// - `14pt` (size only)
 /// - `bold 14pt` (weight and size)
 /// - `italic 12pt Liberation_Serif` (style, size, family)
 /// - `bold italic 16pt Times_New_Roman #333333` (all components)
+/// - `underline #0000ff` (decoration and color)
+/// - `10pt cmyk(0,0,0,1)` (pure CMYK black)
 ///
 /// All components are optional. Underscores in family names are converted to spaces.
 #[derive(Debug, Clone)]
@@ -736,8 +1595,12 @@ pub struct FontSpec {
     pub size: Option<f32>,
     /// Font family name (None = use default)
     pub family: Option<String>,
-    /// Text color as RGB tuple (0.0-1.0 for each component)
-    pub color: Option<(f32, f32, f32)>,
+    /// Text color (None = use default)
+    pub color: Option<Color>,
+    /// Draw a line under the text
+    pub underline: bool,
+    /// Draw a line through the text
+    pub strike: bool,
 }
 
 impl Default for FontSpec {
@@ -748,6 +1611,8 @@ impl Default for FontSpec {
             size: None,
             family: None,
             color: None,
+            underline: false,
+            strike: false,
         }
     }
 }
@@ -755,14 +1620,18 @@ impl Default for FontSpec {
 impl FontSpec {
     /// Parse a font specification string
     ///
-    /// Format: `[bold] [italic] [size[pt]] [family_name] [#rrggbb]`
+    /// Format: `[bold] [italic] [underline] [strike] [size[pt]] [family_name] [color]`
     ///
     /// Examples:
     /// - `"14pt"` -> size 14
     /// - `"bold 14pt"` -> bold, size 14
     /// - `"italic 12pt Liberation_Serif"` -> italic, size 12, Liberation Serif
     /// - `"bold italic 16pt #ff0000"` -> bold italic, size 16, red color
-    pub fn parse(spec: &str) -> Self {
+    /// - `"underline strike"` -> underlined and struck through
+    /// - `"gray(0.4)"`, `"navy"`, `"rgba(0,0,0,0.5)"` -> see [`Color::parse`]
+    ///
+    /// Returns an error if a color token (hex or functional form) is malformed.
+    pub fn parse(spec: &str) -> Result<Self> {
         let mut result = Self::default();
         let tokens: Vec<&str> = spec.split_whitespace().collect();
 
@@ -773,18 +1642,21 @@ impl FontSpec {
                 result.bold = true;
             } else if lower == "italic" {
                 result.italic = true;
-            } else if lower.starts_with('#') && (token.len() == 7 || token.len() == 4) {
-                // Hex color
-                result.color = parse_hex_color(token);
+            } else if lower == "underline" {
+                result.underline = true;
+            } else if lower == "strike" || lower == "strikethrough" {
+                result.strike = true;
             } else if let Some(size) = parse_size_token(&lower) {
                 result.size = Some(size);
+            } else if Color::is_color_token(token) {
+                result.color = Some(Color::parse(token)?);
             } else if !lower.is_empty() {
                 // Assume it's a font family name (convert underscores to spaces)
                 result.family = Some(token.replace('_', " "));
             }
         }
 
-        result
+        Ok(result)
     }
 
     /// Create a FontSpec with just a size
@@ -794,6 +1666,22 @@ impl FontSpec {
             ..Default::default()
         }
     }
+
+    /// Apply a nested specification on top of this one
+    ///
+    /// Flags (bold, italic, decorations) accumulate, while size, family and
+    /// color from `inner` replace the outer values when set.
+    pub fn overlay(&self, inner: &FontSpec) -> FontSpec {
+        FontSpec {
+            bold: self.bold || inner.bold,
+            italic: self.italic || inner.italic,
+            size: inner.size.or(self.size),
+            family: inner.family.clone().or_else(|| self.family.clone()),
+            color: inner.color.or(self.color),
+            underline: self.underline || inner.underline,
+            strike: self.strike || inner.strike,
+        }
+    }
 }
 
 /// Parse a size token like "14pt", "14", "14.5pt"
@@ -802,194 +1690,468 @@ fn parse_size_token(token: &str) -> Option<f32> {
     cleaned.parse::<f32>().ok()
 }
 
-/// Parse a hex color like "#ff0000" or "#f00" to RGB tuple (0.0-1.0)
-fn parse_hex_color(hex: &str) -> Option<(f32, f32, f32)> {
-    let hex = hex.trim_start_matches('#');
-
-    if hex.len() == 6 {
-        // Full hex: #rrggbb
-        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-        Some((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0))
-    } else if hex.len() == 3 {
-        // Short hex: #rgb -> #rrggbb
-        let r = u8::from_str_radix(&hex[0..1].repeat(2), 16).ok()?;
-        let g = u8::from_str_radix(&hex[1..2].repeat(2), 16).ok()?;
-        let b = u8::from_str_radix(&hex[2..3].repeat(2), 16).ok()?;
-        Some((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0))
-    } else {
-        None
+/// Font faces available to generated header/footer content
+///
+/// Liberation Serif is embedded; the sans and monospace faces use the standard
+/// PDF fonts, which every viewer provides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FontFace {
+    Serif,
+    Sans,
+    Mono,
+}
+
+impl FontFace {
+    /// Pick the face for a family name, falling back to Liberation Serif
+    fn from_family(family: Option<&str>) -> Self {
+        let family = match family {
+            Some(f) => f.to_lowercase(),
+            None => return FontFace::Serif,
+        };
+        match family.as_str() {
+            "helvetica" | "arial" | "sans" | "sans-serif" | "sans serif" | "liberation sans" => FontFace::Sans,
+            "courier" | "courier new" | "mono" | "monospace" | "liberation mono" => FontFace::Mono,
+            _ => FontFace::Serif,
+        }
+    }
+
+    /// Font resource name used in the Form XObject
+    fn resource_name(self) -> &'static str {
+        match self {
+            FontFace::Serif => "F1",
+            FontFace::Sans => "F2",
+            FontFace::Mono => "F3",
+        }
+    }
+
+    /// Average glyph width in ems, used for width estimates
+    fn average_width(self) -> f32 {
+        match self {
+            FontFace::Serif => 0.48,
+            FontFace::Sans => 0.52,
+            FontFace::Mono => 0.6,
+        }
     }
 }
 
-/// Font style for inline text formatting
-#[derive(Debug, Clone, Default)]
-struct FontStyle {
-    italic: bool,
-    bold: bool,
+/// Font objects shared by every header/footer XObject in a document
+#[derive(Debug, Clone, Copy)]
+struct FontResources {
+    serif: ObjectId,
+    sans: ObjectId,
+    mono: ObjectId,
+    /// Liberation Serif font program, shared with the shaped-text font
+    serif_file: ObjectId,
+    /// Reserved ID of the Type0 font for shaped text, written at the end
+    shaped: ObjectId,
+}
+
+impl FontResources {
+    /// Embed/register all faces in the document
+    fn add_to_document(doc: &mut Document) -> Result<Self> {
+        let (serif, serif_file) = embed_liberation_serif(doc)?;
+        Ok(Self {
+            serif,
+            sans: add_standard_font(doc, "Helvetica"),
+            mono: add_standard_font(doc, "Courier"),
+            serif_file,
+            shaped: doc.new_object_id(),
+        })
+    }
+
+    /// Build the /Font resource subdictionary
+    fn to_dictionary(self, include_shaped: bool) -> Dictionary {
+        let mut fonts = Dictionary::new();
+        fonts.set(FontFace::Serif.resource_name(), Object::Reference(self.serif));
+        fonts.set(FontFace::Sans.resource_name(), Object::Reference(self.sans));
+        fonts.set(FontFace::Mono.resource_name(), Object::Reference(self.mono));
+        if include_shaped {
+            fonts.set(SHAPED_FONT_RESOURCE, Object::Reference(self.shaped));
+        }
+        fonts
+    }
 }
 
-/// A segment of text with optional font styling
+/// A segment of text with its fully resolved font styling
 #[derive(Debug, Clone)]
 struct TextSegment {
     text: String,
-    style: FontStyle,
+    style: FontSpec,
 }
 
+impl TextSegment {
+    /// Font size for this segment, falling back to the column's size
+    fn font_size(&self, default_size: f32) -> f32 {
+        self.style.size.unwrap_or(default_size)
+    }
+
+    /// Font face for this segment
+    fn face(&self) -> FontFace {
+        FontFace::from_family(self.style.family.as_deref())
+    }
+
+    /// Width of this segment in points (shaped width for non-ASCII text)
+    fn width(&self, default_size: f32, font: &ShapingFont) -> f32 {
+        text_width(&self.text, self.font_size(default_size), self.face(), font)
+    }
+}
+
+/// Characters that can be escaped with a backslash in header/footer text
+const ESCAPABLE_CHARS: [char; 4] = ['[', ']', '|', '\\'];
+
 /// Parse text containing [font]...[/font] tags into segments
 ///
 /// Syntax: `[font italic]text[/font]` or `[font bold]text[/font]`
-/// Multiple styles: `[font bold italic]text[/font]`
-/// Underscores replace spaces in font names: `[font 12pt Liberation_Serif]`
-fn parse_font_tags(text: &str) -> Vec<TextSegment> {
+/// Tags accept the full font specification grammar:
+/// `[font bold 12pt Liberation_Serif #ff0000]text[/font]`, plus the
+/// `underline` and `strike` decorations. Tags nest, and inner tags inherit the
+/// styling of the outer ones: `[font bold]a [font #ff0000]b[/font][/font]`.
+///
+/// `\[`, `\]`, `\|` and `\\` produce literal characters.
+/// A malformed color inside a tag is an error.
+fn parse_font_tags(text: &str, base: &FontSpec) -> Result<Vec<TextSegment>> {
     let mut segments = Vec::new();
+    let mut styles = vec![base.clone()];
+    let mut current = String::new();
     let mut remaining = text;
 
-    while !remaining.is_empty() {
-        // Look for [font ...] tag
-        if let Some(font_start) = remaining.find("[font ") {
-            // Add text before the tag as plain segment
-            if font_start > 0 {
-                segments.push(TextSegment {
-                    text: remaining[..font_start].to_string(),
-                    style: FontStyle::default(),
-                });
-            }
+    // Emit the text collected so far using the innermost style
+    fn flush(segments: &mut Vec<TextSegment>, current: &mut String, style: &FontSpec) {
+        if !current.is_empty() {
+            segments.push(TextSegment {
+                text: std::mem::take(current),
+                style: style.clone(),
+            });
+        }
+    }
 
-            // Find the closing ] of the opening tag
-            if let Some(tag_end) = remaining[font_start..].find(']') {
-                let tag_end = font_start + tag_end;
-                let tag_content = &remaining[font_start + 6..tag_end]; // Skip "[font "
-
-                // Parse style from tag content
-                let style = parse_font_style(tag_content);
-
-                // Find the closing [/font] tag
-                let after_tag = &remaining[tag_end + 1..];
-                if let Some(close_pos) = after_tag.find("[/font]") {
-                    // Extract styled text
-                    let styled_text = &after_tag[..close_pos];
-                    segments.push(TextSegment {
-                        text: styled_text.to_string(),
-                        style,
-                    });
-
-                    // Continue after [/font]
-                    remaining = &after_tag[close_pos + 7..];
-                } else {
-                    // No closing tag found, treat rest as styled
-                    segments.push(TextSegment {
-                        text: after_tag.to_string(),
-                        style,
-                    });
-                    break;
-                }
+    while let Some(c) = remaining.chars().next() {
+        if c == '\\' {
+            if let Some(escaped) = remaining[1..].chars().next().filter(|e| ESCAPABLE_CHARS.contains(e)) {
+                current.push(escaped);
+                remaining = &remaining[1 + escaped.len_utf8()..];
+                continue;
+            }
+        } else if remaining.starts_with("[font ") {
+            if let Some(tag_end) = remaining.find(']') {
+                let tag_content = &remaining[6..tag_end]; // Skip "[font "
+                flush(&mut segments, &mut current, styles.last().unwrap());
+                let style = styles.last().unwrap().overlay(&FontSpec::parse(tag_content)?);
+                styles.push(style);
+                remaining = &remaining[tag_end + 1..];
+                continue;
             } else {
                 // Malformed tag, add rest as plain text
-                segments.push(TextSegment {
-                    text: remaining.to_string(),
-                    style: FontStyle::default(),
-                });
+                current.push_str(remaining);
                 break;
             }
-        } else {
-            // No more font tags, add rest as plain text
-            segments.push(TextSegment {
-                text: remaining.to_string(),
-                style: FontStyle::default(),
-            });
-            break;
+        } else if remaining.starts_with("[/font]") {
+            flush(&mut segments, &mut current, styles.last().unwrap());
+            // Stray closing tags never pop the base style
+            if styles.len() > 1 {
+                styles.pop();
+            }
+            remaining = &remaining[7..];
+            continue;
         }
+
+        current.push(c);
+        remaining = &remaining[c.len_utf8()..];
     }
 
-    segments
+    // Unclosed tags simply style the rest of the text
+    flush(&mut segments, &mut current, styles.last().unwrap());
+
+    Ok(segments)
+}
+
+/// How a block of text is placed across the page
+#[derive(Debug, Clone, Copy)]
+struct BlockLayout {
+    align: TextAlign,
+    direction: TextDirection,
+    page_width: f32,
 }
 
-/// Parse font style from tag content like "italic", "bold", "bold italic"
-fn parse_font_style(tag_content: &str) -> FontStyle {
-    let lower = tag_content.to_lowercase();
-    FontStyle {
-        italic: lower.contains("italic"),
-        bold: lower.contains("bold"),
+/// Generate a block of lines stacking downward from `baseline`
+///
+/// Lines are split on the usual line break markers, then word-wrapped to the
+/// page width between the side margins and aligned within it. `baseline` is
+/// advanced past the block so a following block starts below it.
+fn generate_text_block(
+    text: &str,
+    baseline: &mut f32,
+    font_size: f32,
+    base: &FontSpec,
+    layout: BlockLayout,
+    resources: &mut ContentResources,
+) -> Result<String> {
+    let mut content = String::new();
+    let max_width = layout.page_width - 2.0 * SIDE_MARGIN;
+    let mut baselines = Vec::new();
+
+    for line in parse_multiline_text(text) {
+        let segments = parse_font_tags(&line, base)?;
+        for wrapped in wrap_segments(segments, max_width, font_size, &resources.font) {
+            let width: f32 = wrapped.iter().map(|s| s.width(font_size, &resources.font)).sum();
+            let x = match layout.align {
+                TextAlign::Left => SIDE_MARGIN,
+                TextAlign::Center => (layout.page_width - width) / 2.0,
+                TextAlign::Right => layout.page_width - SIDE_MARGIN - width,
+            };
+
+            // Each line is as tall as its largest segment
+            let line_size = wrapped.iter()
+                .map(|s| s.font_size(font_size))
+                .fold(font_size, f32::max);
+
+            content.push_str(&generate_segments(
+                wrapped, x, *baseline, font_size, base, layout.direction, resources,
+            ));
+            baselines.push(*baseline);
+            *baseline -= line_size * 1.2;
+        }
     }
+
+    resources.record_block(Zone::Header, (SIDE_MARGIN, layout.page_width - SIDE_MARGIN), &baselines, font_size);
+    Ok(content)
+}
+
+/// Break styled segments into lines no wider than `max_width`
+///
+/// Breaks happen at spaces; a single word wider than the limit gets a line of
+/// its own rather than being split. Pieces of the same original segment are
+/// kept together so each line uses as few text objects as possible. Wrapping
+/// works on logical order, so bidi reordering happens per wrapped line.
+fn wrap_segments(
+    segments: Vec<TextSegment>,
+    max_width: f32,
+    default_size: f32,
+    font: &ShapingFont,
+) -> Vec<Vec<TextSegment>> {
+    let mut lines: Vec<Vec<TextSegment>> = Vec::new();
+    let mut line: Vec<(usize, TextSegment)> = Vec::new();
+    let mut line_width = 0.0;
+
+    fn finish(lines: &mut Vec<Vec<TextSegment>>, line: &mut Vec<(usize, TextSegment)>) {
+        // Trailing spaces don't count at the end of a wrapped line
+        if let Some((_, last)) = line.last_mut() {
+            let trimmed_len = last.text.trim_end().len();
+            last.text.truncate(trimmed_len);
+        }
+        let segments: Vec<TextSegment> = line.drain(..)
+            .map(|(_, segment)| segment)
+            .filter(|segment| !segment.text.is_empty())
+            .collect();
+        lines.push(segments);
+    }
+
+    for (index, segment) in segments.into_iter().enumerate() {
+        for word in segment.text.split_inclusive(' ') {
+            let piece = TextSegment { text: word.to_string(), style: segment.style.clone() };
+            let size = piece.font_size(default_size);
+            let visible_width = text_width(word.trim_end(), size, piece.face(), font);
+
+            if !line.is_empty() && line_width + visible_width > max_width {
+                finish(&mut lines, &mut line);
+                line_width = 0.0;
+            }
+
+            // Spaces that end up at the start of a line are dropped
+            let text = if line.is_empty() { word.trim_start() } else { word };
+            if text.is_empty() {
+                continue;
+            }
+            line_width += text_width(text, size, piece.face(), font);
+
+            match line.last_mut() {
+                Some((last_index, last)) if *last_index == index => last.text.push_str(text),
+                _ => line.push((index, TextSegment { text: text.to_string(), ..piece })),
+            }
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        finish(&mut lines, &mut line);
+    }
+
+    lines
 }
 
 /// Generate PDF content for a single line with font tag support
+///
+/// `base` is the column's font specification, which inline tags build upon.
 fn generate_line_with_font_tags(
     line: &str,
     x: f32,
     y: f32,
     font_size: f32,
+    base: &FontSpec,
+    direction: TextDirection,
+    resources: &mut ContentResources,
+) -> Result<String> {
+    let segments = parse_font_tags(line, base)?;
+    Ok(generate_segments(segments, x, y, font_size, base, direction, resources))
+}
+
+/// Generate PDF content for already parsed segments starting at (`x`, `y`)
+///
+/// Segments are laid out in display order using the Unicode bidi algorithm
+/// with `direction` as the base direction. Right-to-left and non-ASCII runs
+/// are shaped and drawn with the Type0 font; plain ASCII keeps the simple
+/// WinAnsi fonts.
+fn generate_segments(
+    segments: Vec<TextSegment>,
+    x: f32,
+    y: f32,
+    font_size: f32,
+    base: &FontSpec,
+    direction: TextDirection,
+    resources: &mut ContentResources,
 ) -> String {
-    let segments = parse_font_tags(line);
     let mut content = String::new();
     let mut current_x = x;
 
-    for segment in segments {
-        if segment.text.is_empty() {
-            continue;
+    // Byte ranges of the segments within the whole line, for the bidi algorithm
+    let mut line = String::new();
+    let mut ranges = Vec::with_capacity(segments.len());
+    for segment in &segments {
+        let start = line.len();
+        line.push_str(&segment.text);
+        ranges.push(start..line.len());
+    }
+
+    for (index, range, rtl) in shaping::visual_runs(&line, &ranges, direction) {
+        let segment = &segments[index];
+        let text = &line[range];
+
+        let size = segment.font_size(font_size);
+        let glyphs = (rtl || shaping::needs_shaping(text)).then(|| resources.shape(text, rtl));
+        let width = match glyphs {
+            Some(ref glyphs) => resources.font.width(glyphs, size),
+            None => estimate_text_width(text, size, segment.face()),
+        };
+
+        // Inline colors are scoped to the segment so later segments keep the column color
+        let colored = segment.style.color.filter(|c| Some(*c) != base.color);
+        if let Some(color) = colored {
+            content.push_str("q\n");
+            resources.set_color(&mut content, &color);
         }
 
         content.push_str("BT\n");
-        content.push_str(&format!("/F1 {} Tf\n", font_size));
-
-        // Apply transformations for style
-        if segment.style.italic && segment.style.bold {
-            // Bold italic: shear + thicker stroke
-            // Matrix: [1 0 tan(12°) 1 x y] for italic shear
-            let shear = 0.21; // tan(12°) ≈ 0.21
-            content.push_str(&format!("1 0 {} 1 {} {} Tm\n", shear, current_x, y));
-            content.push_str("2 Tr\n"); // Stroke + fill for bold effect
-            content.push_str(&format!("{} w\n", font_size * 0.03)); // Stroke width
-        } else if segment.style.italic {
-            // Italic: apply shear transformation
-            let shear = 0.21; // tan(12°) ≈ 0.21
-            content.push_str(&format!("1 0 {} 1 {} {} Tm\n", shear, current_x, y));
-            content.push_str("0 Tr\n"); // Fill only
-        } else if segment.style.bold {
-            // Bold: use stroke + fill rendering mode
-            content.push_str(&format!("1 0 0 1 {} {} Tm\n", current_x, y));
-            content.push_str("2 Tr\n"); // Stroke + fill for bold effect
-            content.push_str(&format!("{} w\n", font_size * 0.03)); // Stroke width
+
+        if let Some(ref glyphs) = glyphs {
+            content.push_str(&format!("/{} {} Tf\n", SHAPED_FONT_RESOURCE, size));
+            if segment.style.bold {
+                content.push_str("2 Tr\n"); // Stroke + fill for bold effect
+                content.push_str(&format!("{} w\n", size * 0.03)); // Stroke width
+            } else {
+                content.push_str("0 Tr\n"); // Fill only
+            }
+            let shear = if segment.style.italic { 0.21 } else { 0.0 }; // tan(12°) ≈ 0.21
+            content.push_str(&resources.font.glyph_operators(glyphs, current_x, y, size, shear));
         } else {
-            // Normal text
-            content.push_str(&format!("1 0 0 1 {} {} Tm\n", current_x, y));
-            content.push_str("0 Tr\n"); // Fill only
-        }
+            content.push_str(&format!("/{} {} Tf\n", segment.face().resource_name(), size));
+
+            // Apply transformations for style
+            if segment.style.italic && segment.style.bold {
+                // Bold italic: shear + thicker stroke
+                // Matrix: [1 0 tan(12°) 1 x y] for italic shear
+                let shear = 0.21; // tan(12°) ≈ 0.21
+                content.push_str(&format!("1 0 {} 1 {} {} Tm\n", shear, current_x, y));
+                content.push_str("2 Tr\n"); // Stroke + fill for bold effect
+                content.push_str(&format!("{} w\n", size * 0.03)); // Stroke width
+            } else if segment.style.italic {
+                // Italic: apply shear transformation
+                let shear = 0.21; // tan(12°) ≈ 0.21
+                content.push_str(&format!("1 0 {} 1 {} {} Tm\n", shear, current_x, y));
+                content.push_str("0 Tr\n"); // Fill only
+            } else if segment.style.bold {
+                // Bold: use stroke + fill rendering mode
+                content.push_str(&format!("1 0 0 1 {} {} Tm\n", current_x, y));
+                content.push_str("2 Tr\n"); // Stroke + fill for bold effect
+                content.push_str(&format!("{} w\n", size * 0.03)); // Stroke width
+            } else {
+                // Normal text
+                content.push_str(&format!("1 0 0 1 {} {} Tm\n", current_x, y));
+                content.push_str("0 Tr\n"); // Fill only
+            }
 
-        content.push_str(&format!("({}) Tj\n", escape_pdf_string(&segment.text)));
+            content.push_str(&format!("({}) Tj\n", escape_pdf_string(text)));
+        }
         content.push_str("ET\n");
 
+        // Decorations are thin filled rectangles in the current fill color
+        let thickness = size * 0.05;
+        if segment.style.underline {
+            content.push_str(&format!("{} {} {} {} re f\n", current_x, y - size * 0.12, width, thickness));
+        }
+        if segment.style.strike {
+            content.push_str(&format!("{} {} {} {} re f\n", current_x, y + size * 0.25, width, thickness));
+        }
+
+        if colored.is_some() {
+            content.push_str("Q\n");
+        }
+
         // Advance x position for next segment
-        current_x += estimate_text_width(&segment.text, font_size);
+        current_x += width;
     }
 
     content
 }
 
 /// Estimate text width excluding font tags
-fn estimate_text_width_with_tags(text: &str, font_size: f32) -> f32 {
-    let segments = parse_font_tags(text);
-    segments.iter()
-        .map(|s| estimate_text_width(&s.text, font_size))
-        .sum()
+fn estimate_text_width_with_tags(text: &str, font_size: f32, base: &FontSpec, font: &ShapingFont) -> Result<f32> {
+    let segments = parse_font_tags(text, base)?;
+    Ok(segments.iter()
+        .map(|s| s.width(font_size, font))
+        .sum())
 }
 
+/// Line break markers recognized by `parse_multiline_text` (besides `\n` and `|`)
+const LINE_BREAK_TAGS: [&str; 8] = [
+    "[br]", "[BR]", "<br>", "<BR>", "<br/>", "<BR/>", "<br />", "<BR />",
+];
+
 /// Parse text with line break markers
+///
+/// Escaped characters (`\|`, `\[` ...) never break lines; the escapes are kept
+/// so `parse_font_tags` can turn them into literal characters.
 fn parse_multiline_text(text: &str) -> Vec<String> {
-    text.split('\n')
-        .flat_map(|line| line.split('|'))
-        .flat_map(|part| part.split("[br]"))
-        .flat_map(|part| part.split("[BR]"))
-        .flat_map(|part| part.split("<br>"))
-        .flat_map(|part| part.split("<BR>"))
-        .flat_map(|part| part.split("<br/>"))
-        .flat_map(|part| part.split("<BR/>"))
-        .flat_map(|part| part.split("<br />"))
-        .flat_map(|part| part.split("<BR />"))
-        .map(|s| s.to_string())
-        .collect()
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut remaining = text;
+
+    'scan: while let Some(c) = remaining.chars().next() {
+        if c == '\\' {
+            if let Some(escaped) = remaining[1..].chars().next().filter(|e| ESCAPABLE_CHARS.contains(e)) {
+                current.push(c);
+                current.push(escaped);
+                remaining = &remaining[1 + escaped.len_utf8()..];
+                continue;
+            }
+        } else if c == '\n' || c == '|' {
+            lines.push(std::mem::take(&mut current));
+            remaining = &remaining[1..];
+            continue;
+        } else {
+            for tag in LINE_BREAK_TAGS {
+                if remaining.starts_with(tag) {
+                    lines.push(std::mem::take(&mut current));
+                    remaining = &remaining[tag.len()..];
+                    continue 'scan;
+                }
+            }
+        }
+
+        current.push(c);
+        remaining = &remaining[c.len_utf8()..];
+    }
+
+    lines.push(current);
+    lines
 }
 
 /// Escape special characters in PDF strings
@@ -1001,11 +2163,20 @@ fn escape_pdf_string(s: &str) -> String {
         .replace('\n', "\\n")
 }
 
-/// Estimate text width for Liberation Serif
-fn estimate_text_width(text: &str, font_size: f32) -> f32 {
+/// Width of text in points: shaped text is measured, plain text estimated
+fn text_width(text: &str, font_size: f32, face: FontFace, font: &ShapingFont) -> f32 {
+    if shaping::needs_shaping(text) {
+        font.measure(text, font_size)
+    } else {
+        estimate_text_width(text, font_size, face)
+    }
+}
+
+/// Estimate text width for one of our font faces
+fn estimate_text_width(text: &str, font_size: f32, face: FontFace) -> f32 {
     // Use average character width from the widths table
-    // Average width is approximately 480/1000 = 0.48 em
-    text.len() as f32 * font_size * 0.48
+    // (Liberation Serif averages approximately 480/1000 = 0.48 em)
+    text.len() as f32 * font_size * face.average_width()
 }
 
 /// Create a Form XObject for headers/footers
@@ -1016,13 +2187,19 @@ fn estimate_text_width(text: &str, font_size: f32) -> f32 {
 fn create_form_xobject(
     doc: &mut Document,
     content: String,
-    font_id: ObjectId,
+    fonts: FontResources,
+    content_resources: &ContentResources,
+    page_size: (f32, f32),
 ) -> Result<ObjectId> {
     // Create Resources dictionary for the Form XObject
     let mut resources = Dictionary::new();
-    let mut fonts = Dictionary::new();
-    fonts.set("F1", Object::Reference(font_id));
-    resources.set("Font", Object::Dictionary(fonts));
+    resources.set("Font", Object::Dictionary(fonts.to_dictionary(content_resources.uses_shaped_font())));
+    if let Some(states) = content_resources.ext_g_state_dictionary() {
+        resources.set("ExtGState", Object::Dictionary(states));
+    }
+    if let Some(images) = content_resources.image_dictionary() {
+        resources.set("XObject", Object::Dictionary(images));
+    }
 
     // Create the Form XObject dictionary
     let mut xobject_dict = Dictionary::new();
@@ -1030,12 +2207,13 @@ fn create_form_xobject(
     xobject_dict.set("Subtype", Object::Name(b"Form".to_vec()));
     xobject_dict.set("FormType", Object::Integer(1));
 
-    // BBox defines the Form's coordinate system - use standard Letter size
+    // BBox covers the page as displayed; the page maps it onto its own box with `cm`
+    let (width, height) = page_size;
     xobject_dict.set("BBox", Object::Array(vec![
         Object::Integer(0),
         Object::Integer(0),
-        Object::Integer(612),
-        Object::Integer(792),
+        Object::Real(width),
+        Object::Real(height),
     ]));
 
     // Identity matrix - our XObject uses standard page coordinates
@@ -1064,7 +2242,7 @@ fn create_form_xobject(
 ///
 /// Returns a positive number if there are more q than Q operators,
 /// meaning there are unclosed graphics states.
-fn count_graphics_state_imbalance(content: &[u8]) -> i32 {
+pub(super) fn count_graphics_state_imbalance(content: &[u8]) -> i32 {
     let content_str = String::from_utf8_lossy(content);
     let mut depth: i32 = 0;
 
@@ -1105,12 +2283,24 @@ fn count_graphics_state_imbalance(content: &[u8]) -> i32 {
 ///
 /// The structure becomes:
 /// ```text
-/// Stream 1: q
+/// Stream 1: [underlay invocation, if any]
+///           q [content transform, if any]
 /// Stream 2: [original content]
 /// Stream 3: Q Q Q... (enough to balance)
 ///           q 1 0 0 1 0 0 cm /HeaderFooter Do Q
 /// ```
-fn wrap_content_and_append_xobject(doc: &mut Document, page_id: ObjectId) -> Result<()> {
+///
+/// `underlay` and `overlay` are XObject invocations from [`xobject_invocation`];
+/// the underlay is drawn before the original content so it ends up beneath it.
+/// `content_transform` is concatenated inside the q/Q wrapper, so it moves the
+/// original content only.
+fn wrap_content_and_append_xobject(
+    doc: &mut Document,
+    page_id: ObjectId,
+    underlay: &str,
+    overlay: &str,
+    content_transform: Option<Matrix>,
+) -> Result<()> {
     // First, read existing content to count q/Q imbalance
     let imbalance = {
         let page_obj = doc.get_object(page_id)?;
@@ -1139,10 +2329,14 @@ fn wrap_content_and_append_xobject(doc: &mut Document, page_id: ObjectId) -> Res
         }
     };
 
-    // Create stream for "q\n" (save graphics state)
+    // Create stream for "q\n" (save graphics state), preceded by the underlay
+    let mut q_content = format!("{}q\n", underlay);
+    if let Some(transform) = content_transform {
+        q_content.push_str(&format!("{} cm\n", transform.to_operands()));
+    }
     let q_stream_id = doc.add_object(Stream::new(
         Dictionary::new(),
-        b"q\n".to_vec(),
+        q_content.into_bytes(),
     ));
 
     // Build the closing stream:
@@ -1161,7 +2355,7 @@ fn wrap_content_and_append_xobject(doc: &mut Document, page_id: ObjectId) -> Res
     qx_content.push_str(" Q\n");
 
     // Draw our XObject in clean coordinate space
-    qx_content.push_str("q 1 0 0 1 0 0 cm /HeaderFooter Do Q\n");
+    qx_content.push_str(overlay);
 
     let qx_stream_id = doc.add_object(Stream::new(
         Dictionary::new(),
@@ -1205,8 +2399,31 @@ fn wrap_content_and_append_xobject(doc: &mut Document, page_id: ObjectId) -> Res
     Ok(())
 }
 
+/// Content stream operators drawing a header/footer XObject
+///
+/// The `cm` matrix maps the XObject's display coordinates onto the page; it is
+/// the identity for unrotated pages whose box starts at the origin. When
+/// `in_layer` is set, the invocation is wrapped in `/OC /HandoutLayer BDC ... EMC`
+/// so it belongs to the optional content group registered in the page's `/Properties`.
+fn xobject_invocation(name: &str, matrix: Matrix, in_layer: bool) -> String {
+    let mut ops = String::new();
+    if in_layer {
+        ops.push_str(&format!("/OC /{} BDC\n", LAYER_PROPERTY_NAME));
+    }
+    ops.push_str(&format!("q {} cm /{} Do Q\n", matrix.to_operands(), name));
+    if in_layer {
+        ops.push_str("EMC\n");
+    }
+    ops
+}
+
 /// Add XObject reference to page's Resources dictionary
-fn add_xobject_to_page_resources(doc: &mut Document, page_id: ObjectId, xobject_id: ObjectId) -> Result<()> {
+fn add_xobject_to_page_resources(
+    doc: &mut Document,
+    page_id: ObjectId,
+    name: &str,
+    xobject_id: ObjectId,
+) -> Result<()> {
     // First, get the resources dictionary and XObject subdictionary
     // We need to dereference both if they are references
     let (resources_dict, xobjects_dict) = {
@@ -1271,7 +2488,7 @@ fn add_xobject_to_page_resources(doc: &mut Document, page_id: ObjectId, xobject_
 
         // Use the dereferenced XObject subdictionary and add our header/footer
         let mut xobjects = xobjects_dict;
-        xobjects.set("HeaderFooter", Object::Reference(xobject_id));
+        xobjects.set(name, Object::Reference(xobject_id));
 
         new_resources.set("XObject", Object::Dictionary(xobjects));
 
@@ -1283,6 +2500,159 @@ fn add_xobject_to_page_resources(doc: &mut Document, page_id: ObjectId, xobject_
     Ok(())
 }
 
+/// Add a marked-content property (e.g. an optional content group) to the page's Resources
+///
+/// Expects the page to already carry its own Resources dictionary, which
+/// `add_xobject_to_page_resources` guarantees.
+fn add_property_to_page_resources(
+    doc: &mut Document,
+    page_id: ObjectId,
+    name: &str,
+    property_id: ObjectId,
+) -> Result<()> {
+    // Dereference an existing /Properties subdictionary so we don't clobber it
+    let properties = {
+        let page_dict = doc.get_dictionary(page_id)?;
+        match page_dict.get(b"Resources").and_then(|res| res.as_dict()) {
+            Ok(resources) => match resources.get(b"Properties") {
+                Ok(props) => resolve_dictionary(doc, props),
+                Err(_) => Dictionary::new(),
+            },
+            Err(_) => Dictionary::new(),
+        }
+    };
+
+    let mut properties = properties;
+    properties.set(name, Object::Reference(property_id));
+
+    let page_dict = doc.get_dictionary_mut(page_id)?;
+    let mut resources = match page_dict.get(b"Resources") {
+        Ok(Object::Dictionary(dict)) => dict.clone(),
+        _ => Dictionary::new(),
+    };
+    resources.set("Properties", Object::Dictionary(properties));
+    page_dict.set("Resources", Object::Dictionary(resources));
+
+    Ok(())
+}
+
+/// Create an Optional Content Group for the header/footer layer and register it
+/// in the catalog's `/OCProperties`
+///
+/// Existing layers in the source document are kept; our group is appended to
+/// `/OCGs` and the default configuration's `/Order`. The group's `/Usage`
+/// dictionary records the view and print states, and matching `/AS` entries make
+/// viewers apply them automatically.
+fn add_optional_content_group(doc: &mut Document, layer: &LayerOptions) -> Result<ObjectId> {
+    let on_off = |on: bool| Object::Name(if on { b"ON".to_vec() } else { b"OFF".to_vec() });
+
+    let mut view_usage = Dictionary::new();
+    view_usage.set("ViewState", on_off(layer.visible));
+    let mut print_usage = Dictionary::new();
+    print_usage.set("PrintState", on_off(layer.printable));
+    let mut usage = Dictionary::new();
+    usage.set("View", Object::Dictionary(view_usage));
+    usage.set("Print", Object::Dictionary(print_usage));
+
+    let mut ocg = Dictionary::new();
+    ocg.set("Type", Object::Name(b"OCG".to_vec()));
+    ocg.set("Name", text_string_object(&layer.name));
+    ocg.set("Intent", Object::Name(b"View".to_vec()));
+    ocg.set("Usage", Object::Dictionary(usage));
+    let ocg_id = doc.add_object(Object::Dictionary(ocg));
+
+    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+
+    // Start from the existing /OCProperties (if any) so source layers survive
+    let mut oc_properties = match doc.get_dictionary(catalog_id)?.get(b"OCProperties") {
+        Ok(existing) => resolve_dictionary(doc, existing),
+        Err(_) => Dictionary::new(),
+    };
+    let mut config = match oc_properties.get(b"D") {
+        Ok(existing) => resolve_dictionary(doc, existing),
+        Err(_) => Dictionary::new(),
+    };
+
+    let mut ocgs = match oc_properties.get(b"OCGs") {
+        Ok(existing) => resolve_array(doc, existing),
+        Err(_) => Vec::new(),
+    };
+    ocgs.push(Object::Reference(ocg_id));
+    oc_properties.set("OCGs", Object::Array(ocgs));
+
+    let mut order = match config.get(b"Order") {
+        Ok(existing) => resolve_array(doc, existing),
+        Err(_) => Vec::new(),
+    };
+    order.push(Object::Reference(ocg_id));
+    config.set("Order", Object::Array(order));
+
+    // The default BaseState is ON, so only a hidden layer needs an /OFF entry
+    if !layer.visible {
+        let mut off = match config.get(b"OFF") {
+            Ok(existing) => resolve_array(doc, existing),
+            Err(_) => Vec::new(),
+        };
+        off.push(Object::Reference(ocg_id));
+        config.set("OFF", Object::Array(off));
+    }
+
+    let mut auto_states = match config.get(b"AS") {
+        Ok(existing) => resolve_array(doc, existing),
+        Err(_) => Vec::new(),
+    };
+    for event in [&b"View"[..], &b"Print"[..]] {
+        let mut auto_state = Dictionary::new();
+        auto_state.set("Event", Object::Name(event.to_vec()));
+        auto_state.set("OCGs", Object::Array(vec![Object::Reference(ocg_id)]));
+        auto_state.set("Category", Object::Array(vec![Object::Name(event.to_vec())]));
+        auto_states.push(Object::Dictionary(auto_state));
+    }
+    config.set("AS", Object::Array(auto_states));
+
+    oc_properties.set("D", Object::Dictionary(config));
+
+    let catalog = doc.get_dictionary_mut(catalog_id)?;
+    catalog.set("OCProperties", Object::Dictionary(oc_properties));
+
+    // Optional content requires PDF 1.5
+    raise_version(doc, "1.5");
+
+    Ok(ocg_id)
+}
+
+/// Clone a dictionary, following a reference if needed
+fn resolve_dictionary(doc: &Document, object: &Object) -> Dictionary {
+    match doc.dereference(object) {
+        Ok((_, Object::Dictionary(dict))) => dict.clone(),
+        _ => Dictionary::new(),
+    }
+}
+
+/// Clone an array, following a reference if needed
+fn resolve_array(doc: &Document, object: &Object) -> Vec<Object> {
+    match doc.dereference(object) {
+        Ok((_, Object::Array(array))) => array.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Encode text as a PDF text string
+///
+/// ASCII text is written as a plain literal string; anything else is written as
+/// UTF-16BE with a byte order mark, as required for non-PDFDocEncoding text.
+pub(crate) fn text_string_object(text: &str) -> Object {
+    if text.is_ascii() {
+        Object::String(text.as_bytes().to_vec(), lopdf::StringFormat::Literal)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        Object::String(bytes, lopdf::StringFormat::Hexadecimal)
+    }
+}
+
 /// Get Resources from page tree parent (handles inheritance)
 fn get_inherited_resources(doc: &Document, parent_id: ObjectId) -> Dictionary {
     if let Ok(Object::Dictionary(parent_dict)) = doc.get_object(parent_id) {
@@ -1316,7 +2686,7 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_size_only() {
-        let spec = FontSpec::parse("14pt");
+        let spec = FontSpec::parse("14pt").unwrap();
         assert!(!spec.bold);
         assert!(!spec.italic);
         assert_eq!(spec.size, Some(14.0));
@@ -1326,13 +2696,13 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_size_no_unit() {
-        let spec = FontSpec::parse("12");
+        let spec = FontSpec::parse("12").unwrap();
         assert_eq!(spec.size, Some(12.0));
     }
 
     #[test]
     fn test_font_spec_parse_bold() {
-        let spec = FontSpec::parse("bold 16pt");
+        let spec = FontSpec::parse("bold 16pt").unwrap();
         assert!(spec.bold);
         assert!(!spec.italic);
         assert_eq!(spec.size, Some(16.0));
@@ -1340,7 +2710,7 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_italic() {
-        let spec = FontSpec::parse("italic 14pt");
+        let spec = FontSpec::parse("italic 14pt").unwrap();
         assert!(!spec.bold);
         assert!(spec.italic);
         assert_eq!(spec.size, Some(14.0));
@@ -1348,7 +2718,7 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_bold_italic() {
-        let spec = FontSpec::parse("bold italic 18pt");
+        let spec = FontSpec::parse("bold italic 18pt").unwrap();
         assert!(spec.bold);
         assert!(spec.italic);
         assert_eq!(spec.size, Some(18.0));
@@ -1356,16 +2726,16 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_family_with_underscores() {
-        let spec = FontSpec::parse("14pt Liberation_Serif");
+        let spec = FontSpec::parse("14pt Liberation_Serif").unwrap();
         assert_eq!(spec.size, Some(14.0));
         assert_eq!(spec.family, Some("Liberation Serif".to_string()));
     }
 
     #[test]
     fn test_font_spec_parse_hex_color_full() {
-        let spec = FontSpec::parse("14pt #ff0000");
+        let spec = FontSpec::parse("14pt #ff0000").unwrap();
         assert_eq!(spec.size, Some(14.0));
-        let color = spec.color.unwrap();
+        let color = spec.color.unwrap().to_rgb();
         assert!((color.0 - 1.0).abs() < 0.01); // Red
         assert!(color.1.abs() < 0.01); // Green
         assert!(color.2.abs() < 0.01); // Blue
@@ -1373,8 +2743,8 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_hex_color_short() {
-        let spec = FontSpec::parse("14pt #f00");
-        let color = spec.color.unwrap();
+        let spec = FontSpec::parse("14pt #f00").unwrap();
+        let color = spec.color.unwrap().to_rgb();
         assert!((color.0 - 1.0).abs() < 0.01); // Red
         assert!(color.1.abs() < 0.01); // Green
         assert!(color.2.abs() < 0.01); // Blue
@@ -1382,8 +2752,8 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_hex_color_gray() {
-        let spec = FontSpec::parse("#333333");
-        let color = spec.color.unwrap();
+        let spec = FontSpec::parse("#333333").unwrap();
+        let color = spec.color.unwrap().to_rgb();
         let expected = 0x33 as f32 / 255.0;
         assert!((color.0 - expected).abs() < 0.01);
         assert!((color.1 - expected).abs() < 0.01);
@@ -1392,12 +2762,12 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_full_spec() {
-        let spec = FontSpec::parse("bold italic 24pt Times_New_Roman #0000ff");
+        let spec = FontSpec::parse("bold italic 24pt Times_New_Roman #0000ff").unwrap();
         assert!(spec.bold);
         assert!(spec.italic);
         assert_eq!(spec.size, Some(24.0));
         assert_eq!(spec.family, Some("Times New Roman".to_string()));
-        let color = spec.color.unwrap();
+        let color = spec.color.unwrap().to_rgb();
         assert!(color.0.abs() < 0.01); // Red
         assert!(color.1.abs() < 0.01); // Green
         assert!((color.2 - 1.0).abs() < 0.01); // Blue
@@ -1405,7 +2775,7 @@ mod tests {
 
     #[test]
     fn test_font_spec_parse_empty() {
-        let spec = FontSpec::parse("");
+        let spec = FontSpec::parse("").unwrap();
         assert!(!spec.bold);
         assert!(!spec.italic);
         assert!(spec.size.is_none());
@@ -1415,29 +2785,370 @@ mod tests {
 
     #[test]
     fn test_font_spec_case_insensitive() {
-        let spec = FontSpec::parse("BOLD ITALIC 14PT");
+        let spec = FontSpec::parse("BOLD ITALIC 14PT").unwrap();
         assert!(spec.bold);
         assert!(spec.italic);
         assert_eq!(spec.size, Some(14.0));
     }
 
     #[test]
-    fn test_parse_hex_color() {
-        // Full hex
-        let color = parse_hex_color("#ff8800").unwrap();
-        assert!((color.0 - 1.0).abs() < 0.01);
-        assert!((color.1 - 0.533).abs() < 0.01);
-        assert!(color.2.abs() < 0.01);
-
-        // Short hex
-        let color = parse_hex_color("#f80").unwrap();
-        assert!((color.0 - 1.0).abs() < 0.01);
-        assert!((color.1 - 0.533).abs() < 0.01);
-        assert!(color.2.abs() < 0.01);
-
-        // Invalid lengths
-        assert!(parse_hex_color("#12345").is_none()); // Wrong length
-        assert!(parse_hex_color("#1234567").is_none()); // Too long
-        assert!(parse_hex_color("#12").is_none()); // Too short
+    fn test_font_spec_parse_decorations() {
+        let spec = FontSpec::parse("underline strike 10pt").unwrap();
+        assert!(spec.underline);
+        assert!(spec.strike);
+        assert_eq!(spec.size, Some(10.0));
+        assert!(spec.family.is_none());
+    }
+
+    #[test]
+    fn test_parse_font_tags_full_spec() {
+        let segments = parse_font_tags("a [font bold 12pt Courier #ff0000]b[/font] c", &FontSpec::default()).unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].text, "a ");
+        assert!(!segments[0].style.bold);
+
+        let styled = &segments[1];
+        assert_eq!(styled.text, "b");
+        assert!(styled.style.bold);
+        assert_eq!(styled.style.size, Some(12.0));
+        assert_eq!(styled.face(), FontFace::Mono);
+        assert_eq!(styled.style.color, Some(Color::rgb(1.0, 0.0, 0.0)));
+
+        assert_eq!(segments[2].text, " c");
+        assert!(segments[2].style.color.is_none());
+    }
+
+    #[test]
+    fn test_parse_font_tags_nested() {
+        let base = FontSpec::parse("14pt").unwrap();
+        let segments = parse_font_tags("[font bold]a [font underline 8pt]b[/font] c[/font]", &base).unwrap();
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["a ", "b", " c"]);
+
+        // Inner tag inherits bold and overrides the size
+        assert!(segments[1].style.bold);
+        assert!(segments[1].style.underline);
+        assert_eq!(segments[1].style.size, Some(8.0));
+
+        // Closing the inner tag restores the outer style
+        assert!(segments[2].style.bold);
+        assert!(!segments[2].style.underline);
+        assert_eq!(segments[2].style.size, Some(14.0));
+    }
+
+    #[test]
+    fn test_parse_font_tags_escapes() {
+        let segments = parse_font_tags(r"\[font bold\] a\|b \\", &FontSpec::default()).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, r"[font bold] a|b \");
+        assert!(!segments[0].style.bold);
+    }
+
+    #[test]
+    fn test_parse_multiline_text_escaped_separators() {
+        assert_eq!(parse_multiline_text("a|b[br]c"), vec!["a", "b", "c"]);
+        assert_eq!(parse_multiline_text(r"a\|b|c"), vec![r"a\|b", "c"]);
+        assert_eq!(parse_multiline_text(r"\[br]"), vec![r"\[br]"]);
+    }
+
+    #[test]
+    fn test_generate_line_decorations_and_color() {
+        let content = generate_line_with_font_tags(
+            "[font underline #0000ff]link[/font]",
+            50.0,
+            30.0,
+            10.0,
+            &FontSpec::default(),
+            TextDirection::Auto,
+            &mut ContentResources::default(),
+        ).unwrap();
+        // Colored segment is isolated in q/Q and underlined with a filled rectangle
+        assert!(content.starts_with("q\n0.000 0.000 1.000 rg\n"));
+        assert!(content.contains(" re f\n"));
+        assert!(content.trim_end().ends_with('Q'));
+    }
+
+    #[test]
+    fn test_font_spec_parse_color_forms() {
+        let spec = FontSpec::parse("10pt cmyk(0,0,0,1)").unwrap();
+        assert_eq!(spec.color, Some(Color::cmyk(0.0, 0.0, 0.0, 1.0)));
+        assert!(spec.family.is_none());
+
+        let spec = FontSpec::parse("Helvetica navy").unwrap();
+        assert_eq!(spec.family, Some("Helvetica".to_string()));
+        assert_eq!(spec.color, Some(Color::parse("navy").unwrap()));
+
+        // Malformed colors are reported rather than read as a family name
+        assert!(FontSpec::parse("12pt #12345").is_err());
+        assert!(FontSpec::parse("gray(2)").is_err());
+        assert!(parse_font_tags("[font cmyk(1,0)]x[/font]", &FontSpec::default()).is_err());
+    }
+
+    #[test]
+    fn test_translucent_colors_use_ext_g_state() {
+        let mut resources = ContentResources::default();
+        let content = generate_line_with_font_tags(
+            "[font gray(0.5,0.25)]faint[/font] plain",
+            50.0,
+            30.0,
+            10.0,
+            &FontSpec::default(),
+            TextDirection::Auto,
+            &mut resources,
+        ).unwrap();
+        assert!(content.starts_with("q\n0.500 g\n0.500 G\n/GSa250 gs\n"));
+
+        let states = resources.ext_g_state_dictionary().unwrap();
+        let state = states.get(b"GSa250").unwrap().as_dict().unwrap();
+        assert_eq!(state.get(b"ca").unwrap().as_float().unwrap(), 0.25);
+        assert_eq!(state.get(b"CA").unwrap().as_float().unwrap(), 0.25);
+
+        // Opaque colors need no graphics state
+        assert!(ContentResources::default().ext_g_state_dictionary().is_none());
+    }
+
+    #[test]
+    fn test_text_align_parse() {
+        assert_eq!(TextAlign::parse("left").unwrap(), TextAlign::Left);
+        assert_eq!(TextAlign::parse("Centre").unwrap(), TextAlign::Center);
+        assert_eq!(TextAlign::parse("RIGHT").unwrap(), TextAlign::Right);
+        assert!(TextAlign::parse("justify").is_err());
+    }
+
+    #[test]
+    fn test_wrap_segments() {
+        let base = FontSpec::default();
+        let segments = parse_font_tags("one two [font bold]three four[/font] five", &base).unwrap();
+        let max_width = estimate_text_width("three four", 10.0, FontFace::Serif) + 1.0;
+        let font = ShapingFont::liberation_serif();
+        let lines = wrap_segments(segments, max_width, 10.0, &font);
+
+        let texts: Vec<Vec<&str>> = lines.iter()
+            .map(|line| line.iter().map(|s| s.text.as_str()).collect())
+            .collect();
+        assert_eq!(texts, vec![vec!["one two"], vec!["three four"], vec!["five"]]);
+        assert!(lines[1][0].style.bold);
+
+        // Styled runs split across lines keep their style on both sides
+        let lines = wrap_segments(
+            parse_font_tags("[font italic]aaa bbb[/font] ccc", &base).unwrap(),
+            estimate_text_width("aaa ", 10.0, FontFace::Serif),
+            10.0,
+            &font,
+        );
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0][0].style.italic && lines[1][0].style.italic);
+        assert!(!lines[2][0].style.italic);
+
+        // Overlong words are not split
+        let lines = wrap_segments(parse_font_tags("unbreakable", &base).unwrap(), 5.0, 10.0, &font);
+        assert_eq!(lines.len(), 1);
+    }
+
+    #[test]
+    fn test_title_block_alignment_and_subtitle() {
+        let options = HeaderFooterOptions {
+            title: Some("First|Second".to_string()),
+            subtitle: Some("Sub".to_string()),
+            title_align: TextAlign::Left,
+            header_font: Some(FontSpec::parse("20pt").unwrap()),
+            ..Default::default()
+        };
+        let content = generate_header_footer_content(
+            1, 1, true, &options, (612.0, 792.0), &mut ContentResources::default(),
+        ).unwrap().over;
+
+        // Lines stack downward from the top at the left margin
+        assert!(content.contains("1 0 0 1 50 742 Tm\n"));
+        assert!(content.contains("1 0 0 1 50 718 Tm\n"));
+        // Subtitle defaults to two thirds of the title size
+        assert!(content.contains("1 0 0 1 50 694 Tm\n"));
+        assert!(content.contains(&format!("/F1 {} Tf\n", 20.0_f32 * 2.0 / 3.0)));
+
+        // Right alignment ends at the right margin
+        let options = HeaderFooterOptions { title_align: TextAlign::Right, ..options };
+        let content = generate_header_footer_content(
+            1, 1, true, &options, (612.0, 792.0), &mut ContentResources::default(),
+        ).unwrap().over;
+        let x = 612.0 - SIDE_MARGIN - estimate_text_width("First", 20.0, FontFace::Serif);
+        assert!(content.contains(&format!("1 0 0 1 {} 742 Tm\n", x)));
+    }
+
+    #[test]
+    fn test_column_directions_parse() {
+        assert_eq!(
+            ColumnDirections::parse("rtl").unwrap(),
+            ColumnDirections::all(TextDirection::RightToLeft)
+        );
+
+        let directions = ColumnDirections::parse("title=rtl, right=ltr").unwrap();
+        assert_eq!(directions.title, TextDirection::RightToLeft);
+        assert_eq!(directions.right, TextDirection::LeftToRight);
+        assert_eq!(directions.left, TextDirection::Auto);
+
+        assert!(ColumnDirections::parse("middle=rtl").is_err());
+        assert!(ColumnDirections::parse("title=sideways").is_err());
+    }
+
+    #[test]
+    fn test_generate_line_right_to_left() {
+        let mut resources = ContentResources::default();
+        // Hebrew followed by Latin in a right-to-left column: the Latin word is drawn first (leftmost)
+        let content = generate_line_with_font_tags(
+            "\u{05D0}\u{05D1} abc",
+            50.0,
+            30.0,
+            10.0,
+            &FontSpec::default(),
+            TextDirection::RightToLeft,
+            &mut resources,
+        ).unwrap();
+
+        let latin = content.find("(abc) Tj").expect("Latin run uses the WinAnsi font");
+        let hebrew = content.find("/F4 10 Tf").expect("Hebrew run uses the shaped font");
+        assert!(latin < hebrew);
+        assert!(content.contains("1 0 0 1 50 30 Tm\n"));
+        assert!(resources.uses_shaped_font());
+    }
+
+    #[test]
+    fn test_landscape_footer_template() {
+        let options = HeaderFooterOptions {
+            footer_left: Some("Club|Room 4".to_string()),
+            footer_font: Some(FontSpec { size: Some(10.0), ..Default::default() }),
+            landscape: Some(PageTemplate {
+                footer_left: Some("Club|Room 4".to_string()),
+                single_line: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let portrait = generate_header_footer_content(
+            1, 1, false, &options, (612.0, 792.0), &mut ContentResources::default(),
+        ).unwrap().over;
+        assert!(portrait.contains("(Club) Tj"));
+        assert!(portrait.contains("(Room 4) Tj"));
+
+        let landscape = generate_header_footer_content(
+            1, 1, false, &options, (792.0, 612.0), &mut ContentResources::default(),
+        ).unwrap().over;
+        assert!(landscape.contains("(Club | Room 4) Tj"));
+        // The landscape template inherits the footer font
+        assert!(landscape.contains(" 10 Tf"));
+        assert!(landscape.contains("1 0 0 1 50 30 Tm\n"));
+    }
+
+    #[test]
+    fn test_element_placement_parse() {
+        assert_eq!(ElementPlacement::parse("under").unwrap(), ElementPlacement::all(Placement::Under));
+
+        let placement = ElementPlacement::parse("masks=under").unwrap();
+        assert_eq!(placement.masks, Placement::Under);
+        assert_eq!(placement.header, Placement::Over);
+        assert_eq!(placement.footer, Placement::Over);
+
+        assert!(ElementPlacement::parse("sideways").is_err());
+        assert!(ElementPlacement::parse("watermark=under").is_err());
+    }
+
+    #[test]
+    fn test_masks_placed_under_text() {
+        let options = HeaderFooterOptions {
+            footer_left: Some("Club".to_string()),
+            mask: MaskOptions { footer_all_height: Some(0.5), ..MaskOptions::new() },
+            placement: ElementPlacement::parse("masks=under").unwrap(),
+            ..Default::default()
+        };
+        let content = generate_header_footer_content(
+            1, 1, false, &options, (612.0, 792.0), &mut ContentResources::default(),
+        ).unwrap();
+
+        assert!(content.under.contains("0 0 612 36 re f\n"));
+        assert!(!content.under.contains("(Club) Tj"));
+        assert!(content.over.contains("(Club) Tj"));
+        assert!(!content.over.contains(" re f\n"));
+        // Both halves of the footer are still marked as artifacts
+        assert!(content.under.starts_with("/Artifact <</Type /Pagination /Subtype /Footer>> BDC\n"));
+        assert!(content.over.starts_with("/Artifact <</Type /Pagination /Subtype /Footer>> BDC\n"));
+    }
+
+    #[test]
+    fn test_text_stamp_on_selected_page() {
+        let options = HeaderFooterOptions {
+            stamps: vec![Stamp::parse("page=2 x=1in y=1in anchor=top-right text=Hand [page]").unwrap()],
+            ..Default::default()
+        };
+
+        let first = generate_header_footer_content(
+            1, 3, true, &options, (612.0, 792.0), &mut ContentResources::default(),
+        ).unwrap();
+        assert!(!first.over.contains("Hand"));
+
+        let second = generate_header_footer_content(
+            2, 3, false, &options, (612.0, 792.0), &mut ContentResources::default(),
+        ).unwrap();
+        assert!(second.over.contains("/Artifact <</Type /Pagination /Subtype /Watermark>> BDC\n"));
+        assert!(second.over.contains("(Hand 2) Tj"));
+        // Flush against the point 1in from the top-right corner, baseline one font size below it
+        let x = 612.0 - 72.0 - estimate_text_width("Hand 2", 14.0, FontFace::Serif);
+        assert!(second.over.contains(&format!("1 0 0 1 {} 706 Tm\n", x)));
+    }
+
+    #[test]
+    fn test_fit_matrix_centers_in_safe_area() {
+        let fit = FitOptions {
+            header_reserve: Length::from_pt(72.0),
+            footer_reserve: Length::from_pt(72.0),
+        };
+        let Matrix([scale, b, c, d, x, y]) = fit.matrix((612.0, 792.0));
+        // Height is the limit: 648 of 792 points
+        assert!((scale - 648.0 / 792.0).abs() < 1e-4);
+        assert_eq!((b, c), (0.0, 0.0));
+        assert_eq!(d, scale);
+        assert!((x - (612.0 - 612.0 * scale) / 2.0).abs() < 1e-3);
+        assert!((y - 72.0).abs() < 1e-3);
+
+        // Content is never enlarged
+        let none = FitOptions { header_reserve: Length::from_mm(0.0), footer_reserve: Length::from_mm(0.0) };
+        assert_eq!(none.matrix((612.0, 792.0)).0[0], 1.0);
+    }
+
+    #[test]
+    fn test_layout_debug_records_blocks_and_masks() {
+        let options = HeaderFooterOptions {
+            title: Some("Title".to_string()),
+            footer_left: Some("One|Two".to_string()),
+            mask: MaskOptions { footer_all_height: Some(0.5), ..MaskOptions::new() },
+            debug_layout: true,
+            ..Default::default()
+        };
+        let mut resources = ContentResources {
+            layout: Some(LayoutDebug::default()),
+            ..Default::default()
+        };
+        generate_header_footer_content(1, 1, true, &options, (612.0, 792.0), &mut resources).unwrap();
+        let layout = resources.layout.unwrap();
+
+        // Title block: 24pt baseline 50pt below the top
+        assert_eq!(layout.header_bottom(), Some(742.0 - 24.0 * DESCENT));
+        // Two 14pt footer lines, the first 16.8pt above the bottom baseline at 30
+        assert!((layout.footer_top().unwrap() - (30.0 + 16.8 + 14.0)).abs() < 0.001);
+        assert_eq!(layout.masks, vec![(Zone::Footer, Rect::new(0.0, 0.0, 612.0, 36.0))]);
+        assert_eq!(layout.baselines.len(), 3);
+        let (x0, x1) = footer_column_budget(612.0, 0);
+        assert_eq!(layout.columns[1].1.x0, x0);
+        assert_eq!(layout.columns[1].1.x1, x1);
+
+        let geometry = PageGeometry {
+            media_box: Rect::new(0.0, 0.0, 612.0, 792.0),
+            crop_box: Rect::new(0.0, 0.0, 612.0, 792.0),
+            rotation: 0,
+        };
+        let ops = generate_layout_debug(&geometry, &layout);
+        assert!(ops.starts_with("/Artifact <</Type /Layout>> BDC\n"));
+        assert!(ops.contains("1.000 RG\n0 0 612 792 re S\n"));
+        assert!(ops.contains("1.000 0.000 0.000 RG\n0 0 612 36 re S\n"));
+        assert!(ops.contains("[4 2] 0 d\n"));
     }
 }
@@ -1,17 +1,100 @@
 //! PDF merging functionality using lopdf
 
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
 use lopdf::{Document, Object, ObjectId, Dictionary};
 use crate::error::{Error, Result};
+use crate::layout::PageDimensions;
+use crate::pages::PageSelection;
+use super::canvas::{apply_canvas, CanvasOptions, PaperScaling};
+use super::outlines::{read_outline, write_outline, InputBookmarks, OutlineItem};
+use super::names::{retarget_links, MergedNames};
+use super::forms::MergedForm;
+use super::dedup::deduplicate;
+use super::blanks::blank_page_like;
+use super::encryption::{encrypt_document, load_document, password_for, InputPassword, OutputEncryption};
 
 /// Options for merging PDFs
 #[derive(Debug, Clone)]
 pub struct MergeOptions {
     /// Input PDF file paths in the order they should be merged
     pub input_paths: Vec<PathBuf>,
+    /// Pages to take from each input, by position in `input_paths`
+    /// (inputs without an entry contribute all their pages)
+    pub page_selections: Vec<PageSelection>,
     /// Output PDF file path
     pub output_path: PathBuf,
+    /// Put every page on this paper size (None = keep each page's size)
+    pub paper: Option<PageDimensions>,
+    /// How pages are placed on `paper`: scaled to fit, or centered and padded
+    pub paper_scaling: PaperScaling,
+    /// Add a top-level bookmark for each input (source bookmarks go beneath it)
+    pub bookmarks: InputBookmarks,
+    /// Store identical fonts, images and other shared objects only once
+    pub deduplicate: bool,
+    /// Passwords for encrypted inputs (files with an empty password open without one)
+    pub passwords: Vec<InputPassword>,
+    /// Encrypt the merged PDF (None = not encrypted)
+    pub encryption: Option<OutputEncryption>,
+    /// Insert a blank page after each input with an odd page count (except the
+    /// last), so every input starts on a right-hand page when printed double-sided
+    pub duplex: bool,
+    /// Add blank pages at the end so the page count is a multiple of this
+    /// (e.g. 2 for double-sided printing, 4 for folded booklets)
+    pub pad_to_multiple: Option<usize>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            input_paths: Vec::new(),
+            page_selections: Vec::new(),
+            output_path: PathBuf::new(),
+            paper: None,
+            paper_scaling: PaperScaling::default(),
+            bookmarks: InputBookmarks::default(),
+            deduplicate: true,
+            passwords: Vec::new(),
+            encryption: None,
+            duplex: false,
+            pad_to_multiple: None,
+        }
+    }
+}
+
+/// What a merge produced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Pages in the merged document
+    pub pages: usize,
+    /// Objects in the merged document
+    pub objects: usize,
+    /// Source objects left out because nothing in the merged document uses them
+    /// (old catalogs and page trees, unselected pages, unused resources)
+    pub objects_removed: usize,
+    /// Approximate size of the removed objects as stored, in bytes
+    pub bytes_removed: usize,
+    /// Copies of identical fonts, images and other shared objects replaced by one object
+    pub duplicates_removed: usize,
+    /// Stream data saved by deduplication, in bytes (before compression)
+    pub bytes_deduplicated: usize,
+    /// Blank pages inserted for `duplex` and `pad_to_multiple` (included in `pages`)
+    pub blank_pages: usize,
+    /// Where each input landed in the merged document, in order
+    pub sections: Vec<MergeSection>,
+}
+
+/// One input's place in the merged document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeSection {
+    /// The input file
+    pub path: PathBuf,
+    /// The input's Info Title, or its file name without extension
+    pub title: String,
+    /// First page of the input in the merged document (from 1)
+    pub first_page: usize,
+    /// Pages taken from the input
+    pub pages: usize,
 }
 
 /// Merge multiple PDF files into a single PDF
@@ -31,11 +114,13 @@ pub struct MergeOptions {
 ///         PathBuf::from("2. second.pdf"),
 ///     ],
 ///     output_path: PathBuf::from("merged.pdf"),
+///     ..Default::default()
 /// };
 ///
-/// merge_pdfs(&options).expect("Failed to merge");
+/// let report = merge_pdfs(&options).expect("Failed to merge");
+/// println!("{} pages, {} unused objects removed", report.pages, report.objects_removed);
 /// ```
-pub fn merge_pdfs(options: &MergeOptions) -> Result<()> {
+pub fn merge_pdfs(options: &MergeOptions) -> Result<MergeReport> {
     if options.input_paths.is_empty() {
         return Err(Error::General("No input files provided".to_string()));
     }
@@ -47,10 +132,16 @@ pub fn merge_pdfs(options: &MergeOptions) -> Result<()> {
         }
     }
 
+    let canvas = options.paper.map(|paper| CanvasOptions {
+        paper: Some(paper),
+        scaling: options.paper_scaling,
+        ..Default::default()
+    });
+
     // Load all documents
     let mut documents: Vec<Document> = Vec::new();
     for path in &options.input_paths {
-        let doc = Document::load(path)?;
+        let doc = load_document(path, password_for(&options.passwords, path))?;
 
         // Validate document has pages
         if doc.get_pages().is_empty() {
@@ -64,26 +155,96 @@ pub fn merge_pdfs(options: &MergeOptions) -> Result<()> {
     let mut max_id = 1;
     let mut page_ids: Vec<(u32, u16)> = Vec::new();
     let mut objects: BTreeMap<ObjectId, Object> = BTreeMap::new();
-
-    for mut doc in documents {
+    let mut outline: Vec<OutlineItem> = Vec::new();
+    let mut names = MergedNames::default();
+    let mut form = MergedForm::default();
+    let mut report = MergeReport::default();
+    let all_pages = PageSelection::all();
+    let input_count = documents.len();
+
+    for (index, mut doc) in documents.into_iter().enumerate() {
         // Renumber objects in this document to avoid conflicts
         doc.renumber_objects_with(max_id);
 
-        // Update max_id for next document
+        let selection = options.page_selections.get(index).unwrap_or(&all_pages);
+        let selected = select_pages(&mut doc, selection);
+        if selected.is_empty() {
+            return Err(Error::InvalidPageSelection(format!(
+                "no pages of {} selected",
+                options.input_paths[index].display()
+            )));
+        }
+
+        for &page_id in &selected {
+            // Ensure each page has its own Resources, boxes and rotation
+            // (copy inherited attributes from the page tree if needed)
+            copy_inherited_attributes_to_page(&mut doc, page_id);
+
+            // Mixed page sizes are normalized before the documents are combined
+            if let Some(ref canvas) = canvas {
+                apply_canvas(&mut doc, page_id, canvas)?;
+            }
+        }
+
+        // Update max_id for next document (pages above may have added objects)
         max_id = doc.max_id + 1;
 
-        // Before collecting pages, ensure each page has its own Resources
-        // (copy inherited Resources from parent if needed)
-        let pages = doc.get_pages();
-        for (_page_num, page_id) in &pages {
-            copy_inherited_resources_to_page(&mut doc, *page_id);
+        // Named destinations join the merged name trees (renamed on collision);
+        // links and bookmarks are pointed at them, or dropped if their page was left out
+        let destinations = names.add_source(&doc, &selected.iter().copied().collect());
+        for &page_id in &selected {
+            retarget_links(&mut doc, page_id, &destinations)?;
+        }
+        let source_outline = read_outline(&doc, &destinations);
+        let path = &options.input_paths[index];
+        report.sections.push(MergeSection {
+            path: path.clone(),
+            title: info_title(&doc).unwrap_or_else(|| file_stem(path)),
+            first_page: page_ids.len() + 1,
+            pages: selected.len(),
+        });
+        let title = match options.bookmarks {
+            InputBookmarks::None => None,
+            InputBookmarks::FileName => Some(file_stem(path)),
+            InputBookmarks::Title => Some(info_title(&doc).unwrap_or_else(|| file_stem(path))),
+        };
+
+        // Form fields with a widget on a selected page join the merged form
+        let form_objects = form.add_source(&mut doc, index + 1, &selected);
+
+        // Collect only the objects the selected pages, bookmarks, names and fields use
+        let mut roots = OutlineItem::references(&source_outline);
+        roots.extend(destinations.references);
+        roots.extend(form_objects);
+        let used = collect_page_objects(&doc, &selected, roots);
+        for (id, object) in doc.objects {
+            if used.contains(&id) {
+                objects.insert(id, object);
+            } else {
+                report.objects_removed += 1;
+                report.bytes_removed += stored_size(&object);
+            }
+        }
+
+        match title {
+            Some(title) => outline.push(OutlineItem::bookmark(&title, selected[0], source_outline)),
+            None => outline.extend(source_outline),
         }
 
-        // Collect page IDs from this document
-        page_ids.extend(pages.into_iter().map(|(_, id)| id));
+        page_ids.extend(selected);
 
-        // Collect all objects from this document
-        objects.extend(doc.objects);
+        // The next input starts on a right-hand page
+        if options.duplex && page_ids.len() % 2 == 1 && index + 1 < input_count {
+            add_blank_page(&mut objects, &mut page_ids, &mut max_id);
+            report.blank_pages += 1;
+        }
+    }
+
+    if let Some(multiple) = options.pad_to_multiple.filter(|&multiple| multiple > 1) {
+        while !page_ids.len().is_multiple_of(multiple) {
+            add_blank_page(&mut objects, &mut page_ids, &mut max_id);
+            report.blank_pages += 1;
+        }
     }
 
     // Create new document with merged content
@@ -118,6 +279,15 @@ pub fn merge_pdfs(options: &MergeOptions) -> Result<()> {
     let mut catalog = Dictionary::new();
     catalog.set("Type", Object::Name(b"Catalog".to_vec()));
     catalog.set("Pages", Object::Reference(pages_id));
+    if let Some(outlines_id) = write_outline(&mut merged_doc, &outline) {
+        catalog.set("Outlines", Object::Reference(outlines_id));
+    }
+    if let Some(names) = names.to_dictionary() {
+        catalog.set("Names", Object::Dictionary(names));
+    }
+    if let Some(form) = form.to_dictionary() {
+        catalog.set("AcroForm", Object::Dictionary(form));
+    }
 
     // Insert catalog and pages into merged document
     merged_doc.objects.insert(catalog_id, Object::Dictionary(catalog));
@@ -135,11 +305,101 @@ pub fn merge_pdfs(options: &MergeOptions) -> Result<()> {
         }
     }
 
+    // Sweep anything the new catalog does not reach
+    let (removed, bytes) = prune_unreachable(&mut merged_doc);
+    report.objects_removed += removed;
+    report.bytes_removed += bytes;
+    if options.deduplicate {
+        let (duplicates, bytes) = deduplicate(&mut merged_doc);
+        report.duplicates_removed = duplicates;
+        report.bytes_deduplicated = bytes;
+    }
+    report.pages = page_ids.len();
+    report.objects = merged_doc.objects.len();
+
     // Compress and save
     merged_doc.compress();
+    if let Some(ref encryption) = options.encryption {
+        encrypt_document(&mut merged_doc, encryption)?;
+    }
     merged_doc.save(&options.output_path)?;
 
-    Ok(())
+    Ok(report)
+}
+
+/// Insert all pages of `source` into `doc` so the first lands at position `index` (from 0)
+///
+/// The pages join the page tree node of the page they are inserted before
+/// (or after the last page when `index` is the page count), and only the
+/// objects they use are copied. Attributes the pages do not set themselves
+/// are set to their defaults rather than inherited from their new parents,
+/// so they keep the size and orientation they had in `source`. Returns the
+/// IDs of the inserted pages.
+pub(crate) fn insert_pages(doc: &mut Document, mut source: Document, index: usize) -> Result<Vec<ObjectId>> {
+    let existing: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let (neighbour, after) = match existing.get(index) {
+        Some(&id) => (id, false),
+        None => (*existing.last().ok_or_else(|| Error::General("Document has no pages".to_string()))?, true),
+    };
+    let parent_id = doc.get_dictionary(neighbour)?.get(b"Parent")?.as_reference()?;
+
+    source.renumber_objects_with(doc.max_id + 1);
+    let pages: Vec<ObjectId> = source.get_pages().into_values().collect();
+    for &page_id in &pages {
+        copy_inherited_attributes_to_page(&mut source, page_id);
+    }
+    let used = collect_page_objects(&source, &pages, Vec::new());
+    for (id, object) in source.objects {
+        if used.contains(&id) {
+            doc.objects.insert(id, object);
+        }
+    }
+    doc.max_id = doc.max_id.max(source.max_id);
+    for &page_id in &pages {
+        let page = doc.get_dictionary_mut(page_id)?;
+        page.set("Parent", parent_id);
+        if !page.has(b"Rotate") {
+            page.set("Rotate", 0);
+        }
+        if !page.has(b"CropBox") {
+            if let Ok(media_box) = page.get(b"MediaBox").cloned() {
+                page.set("CropBox", media_box);
+            }
+        }
+        if !page.has(b"Resources") {
+            page.set("Resources", Dictionary::new());
+        }
+    }
+
+    // Splice into the parent's kids, then fix the counts up to the root
+    let parent = doc.get_dictionary_mut(parent_id)?;
+    let mut kids = parent.get(b"Kids")?.as_array()?.clone();
+    let position = kids
+        .iter()
+        .position(|kid| kid.as_reference().ok() == Some(neighbour))
+        .map(|position| if after { position + 1 } else { position })
+        .unwrap_or(kids.len());
+    kids.splice(position..position, pages.iter().map(|&id| Object::Reference(id)));
+    parent.set("Kids", kids);
+
+    let mut node = Some(parent_id);
+    while let Some(node_id) = node {
+        let dict = doc.get_dictionary_mut(node_id)?;
+        let count = dict.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+        dict.set("Count", count + pages.len() as i64);
+        node = dict.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    Ok(pages)
+}
+
+/// Append a blank page the size of the current last page
+fn add_blank_page(objects: &mut BTreeMap<ObjectId, Object>, page_ids: &mut Vec<ObjectId>, max_id: &mut u32) {
+    let last = page_ids.last().and_then(|id| objects.get(id)).and_then(|page| page.as_dict().ok());
+    let blank = blank_page_like(last.unwrap_or(&Dictionary::new()));
+    let blank_id = (*max_id, 0);
+    *max_id += 1;
+    objects.insert(blank_id, Object::Dictionary(blank));
+    page_ids.push(blank_id);
 }
 
 /// Overlay a watermark PDF onto a source PDF
@@ -383,72 +643,228 @@ fn merge_resources(page_dict: &mut Dictionary, watermark_resources: &Object) ->
     Ok(())
 }
 
-/// Copy inherited Resources from page tree parent to page directly
+/// Resolve a page selection to page object IDs in selection order
 ///
-/// This ensures pages are self-contained and don't lose their Resources
-/// when re-parented during merge operations.
-fn copy_inherited_resources_to_page(doc: &mut Document, page_id: ObjectId) {
-    // First, check if page already has Resources
-    let has_resources = {
-        if let Ok(Object::Dictionary(page_dict)) = doc.get_object(page_id) {
-            page_dict.get(b"Resources").is_ok()
-        } else {
-            false
+/// A page selected more than once is copied, so every entry in the
+/// page tree is its own page object.
+fn select_pages(doc: &mut Document, selection: &PageSelection) -> Vec<ObjectId> {
+    let pages = doc.get_pages();
+    let mut seen = HashSet::new();
+    let mut selected = Vec::new();
+    for number in selection.pages(pages.len()) {
+        let Some(&page_id) = pages.get(&(number as u32)) else { continue };
+        if seen.insert(page_id) {
+            selected.push(page_id);
+        } else if let Ok(page) = doc.get_dictionary(page_id) {
+            let mut copy = page.clone();
+            let copy_id = doc.new_object_id();
+            // Annotations belong to one page; the copy gets its own, so page
+            // transforms (such as `--paper`) do not move the shared ones twice
+            if let Some(annots) = copy_annotations(doc, &copy, copy_id) {
+                copy.set("Annots", annots);
+            }
+            doc.objects.insert(copy_id, Object::Dictionary(copy));
+            selected.push(copy_id);
         }
+    }
+    selected
+}
+
+/// Copies of a page's annotations for a duplicate of the page at `copy_id`
+fn copy_annotations(doc: &mut Document, page: &Dictionary, copy_id: ObjectId) -> Option<Vec<Object>> {
+    let annots = match page.get(b"Annots").ok()? {
+        Object::Array(items) => items.clone(),
+        Object::Reference(id) => doc.get_object(*id).ok()?.as_array().ok()?.clone(),
+        _ => return None,
     };
+    let copies = annots
+        .into_iter()
+        .map(|annot| {
+            let mut copy = match annot {
+                Object::Reference(id) => match doc.get_dictionary(id) {
+                    Ok(dict) => dict.clone(),
+                    Err(_) => return annot,
+                },
+                Object::Dictionary(dict) => dict,
+                other => return other,
+            };
+            if copy.has(b"P") {
+                copy.set("P", copy_id);
+            }
+            Object::Reference(doc.add_object(copy))
+        })
+        .collect();
+    Some(copies)
+}
 
-    if has_resources {
-        return; // Page already has its own Resources
+/// Collect the objects reachable from the given pages (and other roots)
+///
+/// Page tree `/Parent` links are not followed, and neither are references to
+/// pages outside the set, so unselected pages and their content stay behind.
+fn collect_page_objects(doc: &Document, page_ids: &[ObjectId], roots: Vec<ObjectId>) -> BTreeSet<ObjectId> {
+    let pages: BTreeSet<ObjectId> = page_ids.iter().copied().collect();
+    let mut used = BTreeSet::new();
+    let mut pending: Vec<ObjectId> = page_ids.to_vec();
+    pending.extend(roots);
+
+    while let Some(id) = pending.pop() {
+        let Ok(object) = doc.get_object(id) else { continue };
+        if !pages.contains(&id) && is_page(object) {
+            continue;
+        }
+        if !used.insert(id) {
+            continue;
+        }
+        collect_references(object, &mut pending);
     }
+    used
+}
 
-    // Get inherited Resources from parent
-    let inherited_resources = {
-        if let Ok(Object::Dictionary(page_dict)) = doc.get_object(page_id) {
-            if let Ok(parent) = page_dict.get(b"Parent") {
-                if let Object::Reference(parent_id) = parent {
-                    get_inherited_resources_from_tree(doc, *parent_id)
-                } else {
-                    None
-                }
-            } else {
-                None
+fn is_page(object: &Object) -> bool {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        _ => return false,
+    };
+    matches!(dict.get(b"Type"), Ok(Object::Name(name)) if name == b"Page")
+}
+
+/// Push every object `object` refers to (directly or in nested arrays,
+/// dictionaries and stream dictionaries) onto `pending`
+pub(crate) fn collect_references(object: &Object, pending: &mut Vec<ObjectId>) {
+    match object {
+        Object::Reference(id) => pending.push(*id),
+        Object::Array(items) => items.iter().for_each(|item| collect_references(item, pending)),
+        Object::Dictionary(dict) => collect_dictionary_references(dict, pending),
+        Object::Stream(stream) => collect_dictionary_references(&stream.dict, pending),
+        _ => {}
+    }
+}
+
+fn collect_dictionary_references(dict: &Dictionary, pending: &mut Vec<ObjectId>) {
+    // Only the page tree's /Parent is skipped; form fields and popups keep theirs
+    let is_tree_node = matches!(dict.get(b"Type"), Ok(Object::Name(name)) if name == b"Page" || name == b"Pages");
+    for (key, value) in dict.iter() {
+        if !(is_tree_node && key == b"Parent") {
+            collect_references(value, pending);
+        }
+    }
+}
+
+/// Remove objects not reachable from the trailer (`/Root`, `/Info`)
+///
+/// Returns how many objects were removed and their approximate size.
+fn prune_unreachable(doc: &mut Document) -> (usize, usize) {
+    let mut reachable = BTreeSet::new();
+    let mut pending = Vec::new();
+    collect_references(&Object::Dictionary(doc.trailer.clone()), &mut pending);
+    while let Some(id) = pending.pop() {
+        if reachable.insert(id) {
+            if let Some(object) = doc.objects.get(&id) {
+                collect_references(object, &mut pending);
             }
-        } else {
-            None
         }
+    }
+
+    let unreachable: Vec<ObjectId> = doc.objects.keys().filter(|id| !reachable.contains(id)).copied().collect();
+    let mut bytes = 0;
+    for id in &unreachable {
+        if let Some(object) = doc.objects.remove(id) {
+            bytes += stored_size(&object);
+        }
+    }
+    (unreachable.len(), bytes)
+}
+
+/// Approximate size of an indirect object as written to a file: the object
+/// syntax, stream data as stored, and its `obj`/`endobj` framing and xref entry
+fn stored_size(object: &Object) -> usize {
+    const FRAMING: usize = "1 0 obj\n\nendobj\n".len() + 20;
+    FRAMING + syntax_size(object)
+}
+
+fn syntax_size(object: &Object) -> usize {
+    match object {
+        Object::Null => 4,
+        Object::Boolean(value) => if *value { 4 } else { 5 },
+        Object::Integer(value) => value.to_string().len(),
+        Object::Real(value) => value.to_string().len(),
+        Object::Name(name) => 1 + name.len(),
+        Object::String(bytes, lopdf::StringFormat::Hexadecimal) => 2 + 2 * bytes.len(),
+        Object::String(bytes, _) => 2 + bytes.len(),
+        Object::Reference((id, generation)) => format!("{} {} R", id, generation).len(),
+        Object::Array(items) => 2 + items.iter().map(|item| syntax_size(item) + 1).sum::<usize>(),
+        Object::Dictionary(dict) => dictionary_size(dict),
+        Object::Stream(stream) => {
+            dictionary_size(&stream.dict) + "\nstream\n\nendstream".len() + stream.content.len()
+        }
+    }
+}
+
+fn dictionary_size(dict: &Dictionary) -> usize {
+    4 + dict.iter().map(|(key, value)| 2 + key.len() + syntax_size(value)).sum::<usize>()
+}
+
+/// File name without its extension, for bookmarks and sections
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// The document's Info Title, if it has a non-empty one
+fn info_title(doc: &Document) -> Option<String> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+    let title = lopdf::decode_text_string(info.as_dict().ok()?.get(b"Title").ok()?).ok()?;
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Page attributes a page can inherit from its ancestors in the page tree
+const INHERITABLE_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Copy inherited page attributes from the page tree to the page directly
+///
+/// This ensures pages are self-contained and don't lose their Resources,
+/// boxes or rotation when re-parented during merge operations.
+fn copy_inherited_attributes_to_page(doc: &mut Document, page_id: ObjectId) {
+    let parent_id = match doc.get_object(page_id) {
+        Ok(Object::Dictionary(page_dict)) => match page_dict.get(b"Parent") {
+            Ok(Object::Reference(parent_id)) => *parent_id,
+            _ => return,
+        },
+        _ => return,
     };
 
-    // Set the inherited Resources on the page
-    if let Some(resources) = inherited_resources {
-        if let Ok(page_obj) = doc.get_object_mut(page_id) {
-            if let Object::Dictionary(ref mut page_dict) = page_obj {
-                page_dict.set("Resources", Object::Dictionary(resources));
+    for key in INHERITABLE_ATTRIBUTES {
+        // Skip attributes the page already has
+        let has_attribute = match doc.get_object(page_id) {
+            Ok(Object::Dictionary(page_dict)) => page_dict.get(key).is_ok(),
+            _ => false,
+        };
+        if has_attribute {
+            continue;
+        }
+
+        if let Some(value) = get_inherited_attribute_from_tree(doc, parent_id, key) {
+            if let Ok(Object::Dictionary(ref mut page_dict)) = doc.get_object_mut(page_id) {
+                page_dict.set(key, value);
             }
         }
     }
 }
 
-/// Recursively get Resources from page tree ancestors
-fn get_inherited_resources_from_tree(doc: &Document, node_id: ObjectId) -> Option<Dictionary> {
+/// Recursively get an attribute from page tree ancestors
+fn get_inherited_attribute_from_tree(doc: &Document, node_id: ObjectId, key: &[u8]) -> Option<Object> {
     if let Ok(Object::Dictionary(node_dict)) = doc.get_object(node_id) {
-        // Check for Resources on this node
-        if let Ok(res) = node_dict.get(b"Resources") {
-            match res {
-                Object::Dictionary(dict) => return Some(dict.clone()),
-                Object::Reference(res_id) => {
-                    if let Ok(Object::Dictionary(dict)) = doc.get_object(*res_id) {
-                        return Some(dict.clone());
-                    }
-                }
-                _ => {}
-            }
+        // Check for the attribute on this node
+        if let Ok(value) = node_dict.get(key) {
+            return Some(value.clone());
         }
 
         // Not found here, check parent
-        if let Ok(parent) = node_dict.get(b"Parent") {
-            if let Object::Reference(parent_id) = parent {
-                return get_inherited_resources_from_tree(doc, *parent_id);
-            }
+        if let Ok(Object::Reference(parent_id)) = node_dict.get(b"Parent") {
+            return get_inherited_attribute_from_tree(doc, *parent_id, key);
         }
     }
 
@@ -468,11 +884,69 @@ mod tests {
                 PathBuf::from("test2.pdf"),
             ],
             output_path: PathBuf::from("merged.pdf"),
+            ..Default::default()
         };
 
         assert_eq!(options.input_paths.len(), 2);
         assert_eq!(options.output_path, Path::new("merged.pdf"));
     }
 
+    #[test]
+    fn test_prune_unreachable() {
+        use lopdf::{dictionary, Stream};
+
+        let mut doc = Document::with_version("1.7");
+        let font_id = doc.add_object(dictionary! { "Type" => "Font" });
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Font" => font_id });
+        let orphan_id = doc.add_object(Stream::new(dictionary! {}, vec![0; 1000]));
+        doc.trailer.set("Root", catalog_id);
+
+        let (removed, bytes) = prune_unreachable(&mut doc);
+        assert_eq!(removed, 1);
+        assert!(bytes > 1000);
+        assert!(doc.objects.contains_key(&font_id));
+        assert!(!doc.objects.contains_key(&orphan_id));
+    }
+
+    #[test]
+    fn test_insert_pages_ignores_parent_attributes() {
+        use lopdf::dictionary;
+        use super::super::blanks::blank_document_like;
+        use super::super::geometry::PageGeometry;
+
+        // The target's page tree rotates and crops its pages
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! { "Type" => "Font" });
+        let page_id = doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "CropBox" => vec![100.into(), 100.into(), 300.into(), 300.into()],
+            "Rotate" => 90,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+
+        let source = blank_document_like(&dictionary! {
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        let inserted = insert_pages(&mut doc, source, 0).unwrap();
+
+        let page = doc.get_dictionary(inserted[0]).unwrap();
+        assert_eq!(page.get(b"Rotate").unwrap().as_i64().unwrap(), 0);
+        assert_eq!(page.get(b"CropBox").unwrap(), page.get(b"MediaBox").unwrap());
+        assert!(page.get(b"Resources").unwrap().as_dict().unwrap().is_empty());
+        let geometry = PageGeometry::from_page(&doc, inserted[0]).unwrap();
+        assert_eq!((geometry.display_width(), geometry.display_height()), (612.0, 792.0));
+
+        // The existing page still inherits from the tree
+        let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+        assert!(geometry.is_rotated_sideways());
+    }
+
     // Note: Integration tests with actual PDFs will be in tests/ directory
 }
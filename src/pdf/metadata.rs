@@ -3,6 +3,7 @@
 use std::path::Path;
 use lopdf::{Document, Object};
 use crate::error::{Error, Result};
+use super::encryption::load_document;
 
 /// Count pages by reading the Count field from the Pages dictionary
 /// This is more reliable than get_pages() which doesn't handle nested page trees
@@ -64,11 +65,18 @@ pub struct PdfMetadata {
 
 /// Extract metadata from a PDF file
 pub fn extract_metadata(path: &Path) -> Result<PdfMetadata> {
+    extract_metadata_with_password(path, None)
+}
+
+/// Extract metadata from a PDF file that may need a password
+///
+/// Files encrypted with an empty user password open without one.
+pub fn extract_metadata_with_password(path: &Path, password: Option<&str>) -> Result<PdfMetadata> {
     if !path.exists() {
         return Err(Error::FileNotFound(path.to_path_buf()));
     }
 
-    let doc = Document::load(path)?;
+    let doc = load_document(path, password)?;
 
     // Use catalog-based counting for accuracy
     let page_count = count_pages_from_catalog(&doc)?;
@@ -122,7 +130,7 @@ pub fn count_pages(path: &Path) -> Result<usize> {
         return Err(Error::FileNotFound(path.to_path_buf()));
     }
 
-    let doc = Document::load(path)?;
+    let doc = load_document(path, None)?;
     let page_count = count_pages_from_catalog(&doc)?;
 
     if page_count == 0 {
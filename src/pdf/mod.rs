@@ -4,9 +4,30 @@ pub mod merge;
 pub mod metadata;
 pub mod create;
 pub mod headers;
+pub mod color;
+pub mod shaping;
+pub mod geometry;
+pub mod stamps;
+pub mod canvas;
+pub mod outlines;
+pub mod encryption;
+pub mod cover;
+pub mod toc;
+mod names;
+mod forms;
+mod dedup;
+mod blanks;
 
 // Re-export commonly used items
-pub use merge::{merge_pdfs, overlay_watermark, MergeOptions};
-pub use metadata::{count_pages, extract_metadata, PdfMetadata};
+pub use merge::{merge_pdfs, overlay_watermark, MergeOptions, MergeReport, MergeSection};
+pub use metadata::{count_pages, extract_metadata, extract_metadata_with_password, PdfMetadata};
 pub use create::{create_watermark_pdf, WatermarkOptions};
-pub use headers::{add_headers_footers, HeaderFooterOptions, FontSpec, MaskOptions};
+pub use cover::{create_cover_pdf, CoverOptions};
+pub use toc::{TocEntry, TocOptions};
+pub use headers::{add_headers_footers, HeaderFooterOptions, FontSpec, MaskOptions, LayerOptions, TextAlign, ColumnDirections, PageTemplate, Placement, ElementPlacement, FitOptions};
+pub use shaping::TextDirection;
+pub use color::{Color, ColorValue};
+pub use stamps::{Anchor, Stamp, StampContent};
+pub use canvas::{apply_canvas, CanvasOptions, PaperScaling};
+pub use outlines::InputBookmarks;
+pub use encryption::{encrypt_document, load_document, password_for, EncryptionAlgorithm, InputPassword, OutputEncryption, OutputPermissions};
@@ -0,0 +1,303 @@
+//! Named destinations, name trees and internal links across merges
+//!
+//! Each source's name trees (`/Names` in the catalog, plus the older
+//! `/Dests` dictionary) are combined into one set of trees. Names that are
+//! already taken by an earlier source get a numeric suffix, and the source's
+//! links and bookmarks are pointed at the new names. Destinations on pages
+//! that were left out of the merge are dropped, along with the links to them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, StringFormat};
+use crate::error::Result;
+use super::merge::collect_references;
+
+/// How one source's links and bookmarks map into the merged document
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceDestinations {
+    /// Pages of the source that are in the merge
+    pages: BTreeSet<ObjectId>,
+    /// Named destinations that land on a kept page: source name → merged name
+    names: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Objects the source's kept name tree entries refer to
+    pub references: Vec<ObjectId>,
+}
+
+impl SourceDestinations {
+    /// Destinations for a source without named destinations
+    pub fn new(pages: BTreeSet<ObjectId>) -> Self {
+        Self { pages, ..Default::default() }
+    }
+
+    /// Point a link annotation or bookmark at its merged target
+    ///
+    /// Named targets are renamed to their merged names (a `/GoTo` action
+    /// given by reference is copied into the entries first). Returns None when
+    /// there is no internal target, and `Some(false)` when the target page
+    /// or name is not in the merge.
+    pub fn retarget(&self, doc: &Document, entries: &mut Dictionary) -> Option<bool> {
+        if let Ok(dest) = entries.get(b"Dest") {
+            let (kept, name) = self.resolve(doc, dest)?;
+            if let Some(name) = name {
+                entries.set("Dest", Object::String(name, StringFormat::Literal));
+            }
+            return Some(kept);
+        }
+
+        let (_, action) = doc.dereference(entries.get(b"A").ok()?).ok()?;
+        let mut action = action.as_dict().ok()?.clone();
+        if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+            return None;
+        }
+        let (kept, name) = self.resolve(doc, action.get(b"D").ok()?)?;
+        if let Some(name) = name {
+            action.set("D", Object::String(name, StringFormat::Literal));
+            entries.set("A", action);
+        }
+        Some(kept)
+    }
+
+    /// Whether a destination lands on a kept page, and its merged name if it is a named one
+    fn resolve(&self, doc: &Document, dest: &Object) -> Option<(bool, Option<Vec<u8>>)> {
+        match dest {
+            Object::Name(name) | Object::String(name, _) => match self.names.get(name) {
+                Some(merged) => Some((true, Some(merged.clone()))),
+                None => Some((false, None)),
+            },
+            _ => {
+                let page = match doc.dereference(dest).ok()?.1 {
+                    Object::Array(items) => items.first()?.as_reference().ok()?,
+                    _ => return None,
+                };
+                Some((self.pages.contains(&page), None))
+            }
+        }
+    }
+}
+
+/// Name trees gathered from every source, by tree (`Dests`, `EmbeddedFiles`, ...)
+#[derive(Debug, Default)]
+pub(crate) struct MergedNames {
+    trees: BTreeMap<Vec<u8>, BTreeMap<Vec<u8>, Object>>,
+}
+
+impl MergedNames {
+    /// Add one source's name trees, keeping destinations only if they land on `pages`
+    pub fn add_source(&mut self, doc: &Document, pages: &BTreeSet<ObjectId>) -> SourceDestinations {
+        let mut destinations = SourceDestinations::new(pages.clone());
+        let Ok(catalog) = doc.catalog() else { return destinations };
+
+        // The PDF 1.1 /Dests dictionary and the /Dests name tree hold the same kind of entries
+        let mut trees: BTreeMap<Vec<u8>, Vec<(Vec<u8>, Object)>> = BTreeMap::new();
+        if let Ok((_, Object::Dictionary(dests))) = catalog.get(b"Dests").and_then(|dests| doc.dereference(dests)) {
+            let entries = dests.iter().map(|(name, value)| (name.clone(), value.clone()));
+            trees.entry(b"Dests".to_vec()).or_default().extend(entries);
+        }
+        if let Ok((_, Object::Dictionary(names))) = catalog.get(b"Names").and_then(|names| doc.dereference(names)) {
+            for (tree, root) in names.iter() {
+                let entries = trees.entry(tree.clone()).or_default();
+                read_name_tree(doc, root, entries, &mut BTreeSet::new());
+            }
+        }
+
+        for (tree, entries) in trees {
+            let is_dests = tree == b"Dests";
+            let merged = self.trees.entry(tree).or_default();
+            for (name, value) in entries {
+                let value = if is_dests {
+                    if destinations.names.contains_key(&name) {
+                        continue;
+                    }
+                    match destination_array(doc, &value) {
+                        Some(array) if lands_on(&array, pages) => Object::Array(array),
+                        _ => continue,
+                    }
+                } else {
+                    value
+                };
+
+                let merged_name = unique_name(merged, &name);
+                collect_references(&value, &mut destinations.references);
+                if is_dests {
+                    destinations.names.insert(name, merged_name.clone());
+                }
+                merged.insert(merged_name, value);
+            }
+        }
+        destinations
+    }
+
+    /// The catalog's `/Names` dictionary, if any tree has entries
+    pub fn to_dictionary(&self) -> Option<Dictionary> {
+        let mut names = Dictionary::new();
+        for (tree, entries) in self.trees.iter().filter(|(_, entries)| !entries.is_empty()) {
+            // BTreeMap order is byte order, which is what name tree lookups expect
+            let mut flat = Vec::with_capacity(entries.len() * 2);
+            for (name, value) in entries {
+                flat.push(Object::String(name.clone(), StringFormat::Literal));
+                flat.push(value.clone());
+            }
+            names.set(tree.clone(), dictionary! { "Names" => flat });
+        }
+        (!names.is_empty()).then_some(names)
+    }
+}
+
+/// Point the page's link annotations at merged targets, dropping links into
+/// pages or names that are not in the merge
+pub(crate) fn retarget_links(doc: &mut Document, page_id: ObjectId, destinations: &SourceDestinations) -> Result<()> {
+    let annots = match doc.get_dictionary(page_id)?.get(b"Annots") {
+        Ok(annots) => match doc.dereference(annots) {
+            Ok((_, Object::Array(annots))) => annots.clone(),
+            _ => return Ok(()),
+        },
+        Err(_) => return Ok(()),
+    };
+
+    let mut kept = Vec::with_capacity(annots.len());
+    for annot in annots {
+        let (id, mut dict) = match &annot {
+            Object::Reference(id) => match doc.get_dictionary(*id) {
+                Ok(dict) => (Some(*id), dict.clone()),
+                Err(_) => continue,
+            },
+            Object::Dictionary(dict) => (None, dict.clone()),
+            _ => continue,
+        };
+        let is_link = matches!(dict.get(b"Subtype"), Ok(Object::Name(subtype)) if subtype == b"Link");
+        if !is_link {
+            kept.push(annot);
+            continue;
+        }
+        if destinations.retarget(doc, &mut dict) == Some(false) {
+            continue;
+        }
+        match id {
+            Some(id) => {
+                doc.objects.insert(id, Object::Dictionary(dict));
+                kept.push(annot);
+            }
+            None => kept.push(Object::Dictionary(dict)),
+        }
+    }
+
+    let page = doc.get_dictionary_mut(page_id)?;
+    if kept.is_empty() {
+        page.remove(b"Annots");
+    } else {
+        page.set("Annots", kept);
+    }
+    Ok(())
+}
+
+/// Collect the entries of a name tree (leaf `/Names` arrays under `/Kids`)
+fn read_name_tree(doc: &Document, node: &Object, entries: &mut Vec<(Vec<u8>, Object)>, visited: &mut BTreeSet<ObjectId>) {
+    if let Object::Reference(id) = node {
+        if !visited.insert(*id) {
+            return;
+        }
+    }
+    let Ok((_, Object::Dictionary(node))) = doc.dereference(node) else { return };
+
+    if let Ok((_, Object::Array(names))) = node.get(b"Names").and_then(|names| doc.dereference(names)) {
+        for pair in names.chunks_exact(2) {
+            if let Ok(name) = pair[0].as_str() {
+                entries.push((name.to_vec(), pair[1].clone()));
+            }
+        }
+    }
+    if let Ok((_, Object::Array(kids))) = node.get(b"Kids").and_then(|kids| doc.dereference(kids)) {
+        for kid in kids {
+            read_name_tree(doc, kid, entries, visited);
+        }
+    }
+}
+
+/// The explicit destination array behind a name tree value (`[page /Fit]` or `<< /D [...] >>`)
+fn destination_array(doc: &Document, value: &Object) -> Option<Vec<Object>> {
+    let (_, value) = doc.dereference(value).ok()?;
+    let value = match value {
+        Object::Dictionary(dict) => doc.dereference(dict.get(b"D").ok()?).ok()?.1,
+        other => other,
+    };
+    value.as_array().ok().cloned()
+}
+
+fn lands_on(destination: &[Object], pages: &BTreeSet<ObjectId>) -> bool {
+    matches!(destination.first(), Some(Object::Reference(page)) if pages.contains(page))
+}
+
+/// `name`, or `name-2`, `name-3`, ... if it is already taken
+fn unique_name(taken: &BTreeMap<Vec<u8>, Object>, name: &[u8]) -> Vec<u8> {
+    if !taken.contains_key(name) {
+        return name.to_vec();
+    }
+    (2..)
+        .map(|n| [name, format!("-{}", n).as_bytes()].concat())
+        .find(|candidate| !taken.contains_key(candidate))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two pages with named destinations "intro" (page 1) and "deal" (page 2),
+    /// one in the old /Dests dictionary and one in the /Names tree
+    fn named_doc() -> (Document, ObjectId, ObjectId) {
+        let mut doc = Document::with_version("1.7");
+        let first = doc.add_object(dictionary! { "Type" => "Page" });
+        let second = doc.add_object(dictionary! { "Type" => "Page" });
+        let deal = doc.add_object(dictionary! { "D" => vec![second.into(), "Fit".into()] });
+        let leaf = doc.add_object(dictionary! {
+            "Names" => vec![Object::string_literal("deal"), deal.into()],
+        });
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Dests" => dictionary! { "intro" => vec![first.into(), "Fit".into()] },
+            "Names" => dictionary! { "Dests" => dictionary! { "Kids" => vec![leaf.into()] } },
+        });
+        doc.trailer.set("Root", catalog_id);
+        (doc, first, second)
+    }
+
+    #[test]
+    fn test_colliding_names_are_renamed() {
+        let (doc, first, second) = named_doc();
+        let mut merged = MergedNames::default();
+        let pages: BTreeSet<ObjectId> = [first, second].into();
+        merged.add_source(&doc, &pages);
+        let destinations = merged.add_source(&doc, &pages);
+
+        let mut link = dictionary! { "Dest" => Object::Name(b"intro".to_vec()) };
+        assert_eq!(destinations.retarget(&doc, &mut link), Some(true));
+        assert_eq!(link.get(b"Dest").unwrap().as_str().unwrap(), b"intro-2");
+
+        let names = merged.to_dictionary().unwrap();
+        let dests = names.get(b"Dests").unwrap().as_dict().unwrap();
+        let keys: Vec<&[u8]> = dests.get(b"Names").unwrap().as_array().unwrap()
+            .iter().step_by(2).map(|name| name.as_str().unwrap()).collect();
+        assert_eq!(keys, vec![b"deal".as_slice(), b"deal-2", b"intro", b"intro-2"]);
+    }
+
+    #[test]
+    fn test_targets_on_dropped_pages_are_removed() {
+        let (doc, first, second) = named_doc();
+        let mut merged = MergedNames::default();
+        let destinations = merged.add_source(&doc, &[first].into());
+
+        let mut named = dictionary! {
+            "A" => dictionary! { "S" => "GoTo", "D" => Object::string_literal("deal") },
+        };
+        assert_eq!(destinations.retarget(&doc, &mut named), Some(false));
+        let mut explicit = dictionary! { "Dest" => vec![second.into(), "Fit".into()] };
+        assert_eq!(destinations.retarget(&doc, &mut explicit), Some(false));
+        let mut kept = dictionary! { "Dest" => vec![first.into(), "Fit".into()] };
+        assert_eq!(destinations.retarget(&doc, &mut kept), Some(true));
+        let mut web = dictionary! { "A" => dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com") } };
+        assert_eq!(destinations.retarget(&doc, &mut web), None);
+
+        let names = merged.to_dictionary().unwrap();
+        let dests = names.get(b"Dests").unwrap().as_dict().unwrap();
+        assert_eq!(dests.get(b"Names").unwrap().as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,261 @@
+//! Outline (bookmark) trees for merged documents
+//!
+//! Each source's outline is read into a plain tree, pruned to the pages that
+//! were kept, and written into the merged document with fresh links.
+
+use std::collections::BTreeSet;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use crate::error::{Error, Result};
+use super::headers::text_string_object;
+use super::merge::collect_references;
+use super::names::SourceDestinations;
+
+/// Bookmark added for each merged input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputBookmarks {
+    /// No extra bookmarks; source outlines are combined at the top level
+    #[default]
+    None,
+    /// One bookmark per input, named after its file name (without extension)
+    FileName,
+    /// One bookmark per input, named after its Info Title (file name if it has none)
+    Title,
+}
+
+impl InputBookmarks {
+    /// Parse a bookmark mode (`none`, `filename`, `title`)
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "none" => Ok(InputBookmarks::None),
+            "filename" | "file" => Ok(InputBookmarks::FileName),
+            "title" => Ok(InputBookmarks::Title),
+            _ => Err(Error::General(format!(
+                "Invalid bookmark mode: {} (expected none, filename or title)",
+                value
+            ))),
+        }
+    }
+}
+
+/// One outline item: its own entries (title, destination, style) and children
+#[derive(Debug, Clone)]
+pub(crate) struct OutlineItem {
+    pub entries: Dictionary,
+    pub open: bool,
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    /// An open bookmark to the top of a page
+    pub fn bookmark(title: &str, page_id: ObjectId, children: Vec<OutlineItem>) -> Self {
+        Self {
+            entries: dictionary! {
+                "Title" => text_string_object(title),
+                "Dest" => vec![page_id.into(), "Fit".into()],
+            },
+            open: true,
+            children,
+        }
+    }
+
+    /// Every object referenced from the items' own entries
+    pub fn references(items: &[OutlineItem]) -> Vec<ObjectId> {
+        let mut references = Vec::new();
+        for item in items {
+            for (_, value) in item.entries.iter() {
+                collect_references(value, &mut references);
+            }
+            references.extend(Self::references(&item.children));
+        }
+        references
+    }
+}
+
+/// Keys that link items into the tree; they are rebuilt when writing
+const LINK_KEYS: [&[u8]; 6] = [b"Parent", b"Prev", b"Next", b"First", b"Last", b"Count"];
+
+/// Read a document's outline, pointing its items at merged targets
+///
+/// Items that jump to a page outside the merge lose their destination, and
+/// are dropped entirely unless they still have children.
+pub(crate) fn read_outline(doc: &Document, destinations: &SourceDestinations) -> Vec<OutlineItem> {
+    let root = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Outlines"))
+        .and_then(|outlines| doc.dereference(outlines));
+    match root {
+        Ok((_, Object::Dictionary(root))) => {
+            read_items(doc, root.get(b"First").ok(), destinations, &mut BTreeSet::new())
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn read_items(
+    doc: &Document,
+    first: Option<&Object>,
+    destinations: &SourceDestinations,
+    visited: &mut BTreeSet<ObjectId>,
+) -> Vec<OutlineItem> {
+    let mut items = Vec::new();
+    let mut next = first.and_then(|object| object.as_reference().ok());
+
+    // `visited` guards against broken files whose links form a cycle
+    while let Some(id) = next.filter(|id| visited.insert(*id)) {
+        let Ok(dict) = doc.get_dictionary(id) else { break };
+        let children = read_items(doc, dict.get(b"First").ok(), destinations, visited);
+
+        let mut entries = Dictionary::new();
+        for (key, value) in dict.iter() {
+            if !LINK_KEYS.contains(&key.as_slice()) {
+                entries.set(key.clone(), value.clone());
+            }
+        }
+
+        let target_kept = destinations.retarget(doc, &mut entries);
+        if target_kept == Some(false) {
+            entries.remove(b"Dest");
+            entries.remove(b"A");
+        }
+        if target_kept != Some(false) || !children.is_empty() {
+            let open = !matches!(dict.get(b"Count"), Ok(Object::Integer(count)) if *count < 0);
+            items.push(OutlineItem { entries, open, children });
+        }
+
+        next = dict.get(b"Next").ok().and_then(|object| object.as_reference().ok());
+    }
+    items
+}
+
+/// Write items as a new outline root and return its ID
+pub(crate) fn write_outline(doc: &mut Document, items: &[OutlineItem]) -> Option<ObjectId> {
+    if items.is_empty() {
+        return None;
+    }
+    let root_id = doc.new_object_id();
+    let (first, last, count) = write_items(doc, items, root_id);
+    doc.objects.insert(root_id, Object::Dictionary(dictionary! {
+        "Type" => "Outlines",
+        "First" => first,
+        "Last" => last,
+        "Count" => count,
+    }));
+    Some(root_id)
+}
+
+/// Write sibling items under `parent`; returns the first and last IDs and
+/// how many items are visible with each item's open state
+fn write_items(doc: &mut Document, items: &[OutlineItem], parent: ObjectId) -> (ObjectId, ObjectId, i64) {
+    let ids: Vec<ObjectId> = items.iter().map(|_| doc.new_object_id()).collect();
+    let mut visible = items.len() as i64;
+
+    for (i, item) in items.iter().enumerate() {
+        let mut dict = item.entries.clone();
+        dict.set("Parent", parent);
+        if i > 0 {
+            dict.set("Prev", ids[i - 1]);
+        }
+        if i + 1 < ids.len() {
+            dict.set("Next", ids[i + 1]);
+        }
+        if !item.children.is_empty() {
+            let (first, last, count) = write_items(doc, &item.children, ids[i]);
+            dict.set("First", first);
+            dict.set("Last", last);
+            // Closed items store the negated count of what opening them reveals
+            dict.set("Count", if item.open { count } else { -count });
+            if item.open {
+                visible += count;
+            }
+        }
+        doc.objects.insert(ids[i], Object::Dictionary(dict));
+    }
+    (ids[0], ids[ids.len() - 1], visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline_doc() -> (Document, ObjectId, ObjectId) {
+        let mut doc = Document::with_version("1.7");
+        let kept = doc.add_object(dictionary! { "Type" => "Page" });
+        let dropped = doc.add_object(dictionary! { "Type" => "Page" });
+
+        let root_id = doc.new_object_id();
+        let chapter_id = doc.new_object_id();
+        let section_id = doc.add_object(dictionary! {
+            "Title" => text_string_object("Section"),
+            "Parent" => chapter_id,
+            "Dest" => vec![dropped.into(), "Fit".into()],
+        });
+        let intro_id = doc.add_object(dictionary! {
+            "Title" => text_string_object("Intro"),
+            "Parent" => root_id,
+            "A" => dictionary! { "S" => "GoTo", "D" => vec![kept.into(), "Fit".into()] },
+            "Next" => chapter_id,
+        });
+        doc.objects.insert(chapter_id, Object::Dictionary(dictionary! {
+            "Title" => text_string_object("Chapter"),
+            "Parent" => root_id,
+            "Prev" => intro_id,
+            "Dest" => vec![dropped.into(), "Fit".into()],
+            "First" => section_id,
+            "Last" => section_id,
+            "Count" => -1,
+        }));
+        doc.objects.insert(root_id, Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => intro_id,
+            "Last" => chapter_id,
+            "Count" => 2,
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Outlines" => root_id });
+        doc.trailer.set("Root", catalog_id);
+        (doc, kept, dropped)
+    }
+
+    #[test]
+    fn test_read_outline_prunes_dropped_pages() {
+        let (doc, kept, dropped) = outline_doc();
+
+        let items = read_outline(&doc, &SourceDestinations::new([kept, dropped].into()));
+        assert_eq!(items.len(), 2);
+        assert!(!items[1].open);
+        assert!(items[1].entries.get(b"Dest").is_ok());
+
+        // Without the second page, the section goes, and so does the chapter,
+        // which has neither a destination nor children left
+        let items = read_outline(&doc, &SourceDestinations::new([kept].into()));
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].entries.get(b"Title").unwrap().as_str().unwrap(), b"Intro");
+    }
+
+    #[test]
+    fn test_write_outline_links_and_counts() {
+        let (mut doc, kept, dropped) = outline_doc();
+        let items = read_outline(&doc, &SourceDestinations::new([kept, dropped].into()));
+        let wrapped = vec![OutlineItem::bookmark("handout", kept, items)];
+
+        let root_id = write_outline(&mut doc, &wrapped).unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        // The wrapper and its two children are visible; the closed chapter hides its section
+        assert_eq!(root.get(b"Count").unwrap().as_i64().unwrap(), 3);
+
+        let wrapper_id = root.get(b"First").unwrap().as_reference().unwrap();
+        let wrapper = doc.get_dictionary(wrapper_id).unwrap();
+        let chapter_id = wrapper.get(b"Last").unwrap().as_reference().unwrap();
+        let chapter = doc.get_dictionary(chapter_id).unwrap();
+        assert_eq!(chapter.get(b"Count").unwrap().as_i64().unwrap(), -1);
+        assert_eq!(chapter.get(b"Parent").unwrap().as_reference().unwrap(), wrapper_id);
+        assert!(chapter.get(b"Prev").is_ok());
+        assert!(chapter.get(b"Next").is_err());
+    }
+
+    #[test]
+    fn test_parse_input_bookmarks() {
+        assert_eq!(InputBookmarks::parse("Title").unwrap(), InputBookmarks::Title);
+        assert_eq!(InputBookmarks::parse("filename").unwrap(), InputBookmarks::FileName);
+        assert!(InputBookmarks::parse("chapters").is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! Text shaping for header/footer text outside plain ASCII
+//!
+//! Plain ASCII text is written with the simple WinAnsi fonts. Everything else
+//! (accented Latin, Hebrew, Devanagari, ...) and every right-to-left run is
+//! shaped with rustybuzz and written as positioned glyph IDs through a
+//! Type0/CIDFontType2 font with `Identity-H` encoding, so ligatures, mark
+//! positioning and contextual forms come out as the font intends.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use unicode_bidi::{BidiInfo, Level};
+
+use crate::error::{Error, Result};
+
+/// Liberation Serif, the bundled font used for shaping unless a font file is given
+const LIBERATION_SERIF: &[u8] = include_bytes!("../../assets/fonts/LiberationSerif-Regular.ttf");
+
+/// Base paragraph direction for a line of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDirection {
+    /// Taken from the first strong character (Unicode bidi rules P2/P3)
+    #[default]
+    Auto,
+    /// Left-to-right
+    LeftToRight,
+    /// Right-to-left
+    RightToLeft,
+}
+
+impl TextDirection {
+    /// Parse a direction name (`auto`, `ltr`, `rtl`)
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Ok(TextDirection::Auto),
+            "ltr" | "left-to-right" => Ok(TextDirection::LeftToRight),
+            "rtl" | "right-to-left" => Ok(TextDirection::RightToLeft),
+            _ => Err(Error::General(format!("Invalid text direction: {} (expected auto, ltr or rtl)", value))),
+        }
+    }
+
+    /// Paragraph embedding level for the bidi algorithm (None = detect)
+    fn paragraph_level(self) -> Option<Level> {
+        match self {
+            TextDirection::Auto => None,
+            TextDirection::LeftToRight => Some(Level::ltr()),
+            TextDirection::RightToLeft => Some(Level::rtl()),
+        }
+    }
+}
+
+/// Split a line into runs in visual (left-to-right display) order
+///
+/// `ranges` are the byte ranges of the line's styled pieces in logical order.
+/// Each returned entry is a piece index, the byte range of that piece covered
+/// by the run, and whether the run is right-to-left. Pieces crossing a
+/// direction change are split.
+pub(crate) fn visual_runs(
+    text: &str,
+    ranges: &[Range<usize>],
+    direction: TextDirection,
+) -> Vec<(usize, Range<usize>, bool)> {
+    let mut result = Vec::new();
+    if text.is_empty() {
+        return result;
+    }
+
+    let bidi = BidiInfo::new(text, direction.paragraph_level());
+    for paragraph in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let mut pieces: Vec<(usize, Range<usize>, bool)> = ranges
+                .iter()
+                .enumerate()
+                .filter_map(|(index, range)| {
+                    let start = range.start.max(run.start);
+                    let end = range.end.min(run.end);
+                    (start < end).then_some((index, start..end, rtl))
+                })
+                .collect();
+            // Within a right-to-left run, logically later pieces sit further left
+            if rtl {
+                pieces.reverse();
+            }
+            result.extend(pieces);
+        }
+    }
+
+    result
+}
+
+/// Whether text has to go through the shaper rather than a WinAnsi font
+pub(crate) fn needs_shaping(text: &str) -> bool {
+    !text.is_ascii()
+}
+
+/// A shaped glyph with its position relative to the pen
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ShapedGlyph {
+    /// Glyph ID (used directly as CID with Identity-H)
+    pub id: u16,
+    /// Advance after shaping, in font units
+    pub advance: i32,
+    /// Advance from the font's `hmtx` table, as written to the `/W` array
+    pub default_advance: i32,
+    /// Offset from the pen position, in font units
+    pub x_offset: i32,
+    pub y_offset: i32,
+    /// Text this glyph represents (empty for further glyphs of the same cluster)
+    pub text: String,
+}
+
+/// Glyphs used by a document, keyed by glyph ID, with their widths and text
+pub(crate) type GlyphSet = BTreeMap<u16, (i32, String)>;
+
+/// A TrueType/OpenType font used for shaped text
+#[derive(Debug)]
+pub(crate) struct ShapingFont {
+    data: Cow<'static, [u8]>,
+    units_per_em: f32,
+    /// Whether this is the bundled Liberation Serif (whose font file is already embedded)
+    bundled: bool,
+}
+
+impl ShapingFont {
+    /// The bundled Liberation Serif, which covers Latin, Greek, Cyrillic and Hebrew
+    pub fn liberation_serif() -> Self {
+        Self::from_data(Cow::Borrowed(LIBERATION_SERIF), true)
+            .expect("bundled Liberation Serif font is valid")
+    }
+
+    /// Load a TrueType font file (e.g. a Devanagari font)
+    ///
+    /// The file is embedded as is as `/FontFile2`, which must be plain
+    /// TrueType: CFF-based OpenType fonts (`.otf` starting with `OTTO`) and
+    /// font collections (`.ttc`) are rejected.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(Error::FileNotFound(path.to_path_buf()));
+        }
+        let data = std::fs::read(path)?;
+        match data.get(..4) {
+            Some(b"OTTO") => return Err(Error::Font(format!(
+                "CFF-based OpenType fonts are not supported, use a TrueType (.ttf) font: {}",
+                path.display()
+            ))),
+            Some(b"ttcf") => return Err(Error::Font(format!(
+                "Font collections are not supported, use a single TrueType (.ttf) font: {}",
+                path.display()
+            ))),
+            _ => {}
+        }
+        Self::from_data(Cow::Owned(data), false)
+            .ok_or_else(|| Error::Font(format!("Unsupported font file: {}", path.display())))
+    }
+
+    fn from_data(data: Cow<'static, [u8]>, bundled: bool) -> Option<Self> {
+        let units_per_em = Face::from_slice(&data, 0)?.units_per_em() as f32;
+        Some(Self { data, units_per_em, bundled })
+    }
+
+    /// Whether this is the bundled font
+    pub fn is_bundled(&self) -> bool {
+        self.bundled
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::from_slice(&self.data, 0).expect("font was validated when loaded")
+    }
+
+    /// Shape a run of text that has a single direction
+    ///
+    /// Glyphs come back in visual order, so right-to-left runs are already
+    /// reversed.
+    pub fn shape(&self, text: &str, rtl: bool) -> Vec<ShapedGlyph> {
+        let face = self.face();
+
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+
+        let output = rustybuzz::shape(&face, &[], buffer);
+
+        // Cluster values are byte offsets; each cluster's text runs up to the next one
+        let mut boundaries: Vec<usize> = output.glyph_infos().iter().map(|g| g.cluster as usize).collect();
+        boundaries.push(text.len());
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut seen_clusters = Vec::new();
+        output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| {
+                let id = info.glyph_id as u16;
+                let cluster = info.cluster as usize;
+                let glyph_text = if seen_clusters.contains(&cluster) {
+                    String::new()
+                } else {
+                    seen_clusters.push(cluster);
+                    let end = boundaries.iter().copied().find(|b| *b > cluster).unwrap_or(text.len());
+                    text.get(cluster..end).unwrap_or_default().to_string()
+                };
+                ShapedGlyph {
+                    id,
+                    advance: position.x_advance,
+                    default_advance: face
+                        .glyph_hor_advance(rustybuzz::ttf_parser::GlyphId(id))
+                        .unwrap_or(0) as i32,
+                    x_offset: position.x_offset,
+                    y_offset: position.y_offset,
+                    text: glyph_text,
+                }
+            })
+            .collect()
+    }
+
+    /// Width of shaped glyphs in points
+    pub fn width(&self, glyphs: &[ShapedGlyph], font_size: f32) -> f32 {
+        glyphs.iter().map(|g| g.advance).sum::<i32>() as f32 * font_size / self.units_per_em
+    }
+
+    /// Width of text in points after shaping
+    pub fn measure(&self, text: &str, font_size: f32) -> f32 {
+        self.width(&self.shape(text, false), font_size)
+    }
+
+    /// Emit text-object operators drawing `glyphs` with the pen at (`x`, `y`)
+    ///
+    /// Must be used between `BT`/`ET` with the Type0 font selected. Kerning
+    /// and other advance changes become `TJ` adjustments; glyphs with offsets
+    /// (combining marks) are placed individually with their own `Tm`.
+    pub fn glyph_operators(&self, glyphs: &[ShapedGlyph], x: f32, y: f32, font_size: f32, shear: f32) -> String {
+        let scale = font_size / self.units_per_em;
+        let mut content = String::new();
+        let mut pen = x;
+        let mut group = String::new();
+
+        fn flush(content: &mut String, group: &mut String) {
+            if !group.is_empty() {
+                content.push_str(&format!("[{}] TJ\n", group));
+                group.clear();
+            }
+        }
+
+        for glyph in glyphs {
+            if glyph.x_offset != 0 || glyph.y_offset != 0 {
+                flush(&mut content, &mut group);
+                content.push_str(&format!(
+                    "1 0 {} 1 {} {} Tm\n<{:04X}> Tj\n",
+                    shear,
+                    pen + glyph.x_offset as f32 * scale,
+                    y + glyph.y_offset as f32 * scale,
+                    glyph.id
+                ));
+            } else {
+                if group.is_empty() {
+                    content.push_str(&format!("1 0 {} 1 {} {} Tm\n", shear, pen, y));
+                }
+                group.push_str(&format!("<{:04X}>", glyph.id));
+                // TJ numbers are in thousandths of an em and move the pen backwards
+                let adjust = (glyph.default_advance - glyph.advance) as f32 * 1000.0 / self.units_per_em;
+                if adjust.abs() > 0.01 {
+                    group.push_str(&format!(" {} ", adjust));
+                }
+            }
+            // After a placed mark the next group starts with its own Tm at the pen
+            pen += glyph.advance as f32 * scale;
+        }
+        flush(&mut content, &mut group);
+
+        content
+    }
+
+    /// Write the Type0 font for all glyphs used in the document
+    ///
+    /// `font_id` is the (reserved) object ID XObjects already refer to.
+    /// `font_file` reuses an embedded copy of the same font program.
+    pub fn write_type0(
+        &self,
+        doc: &mut Document,
+        font_id: ObjectId,
+        glyphs: &GlyphSet,
+        font_file: Option<ObjectId>,
+    ) -> Result<()> {
+        let face = self.face();
+        let to_pdf_units = |v: f32| (v * 1000.0 / self.units_per_em).round() as i64;
+
+        let base_font = face
+            .names()
+            .into_iter()
+            .find(|name| name.name_id == rustybuzz::ttf_parser::name_id::POST_SCRIPT_NAME)
+            .and_then(|name| name.to_string())
+            .unwrap_or_else(|| "EmbeddedFont".to_string());
+
+        let font_file_id = match font_file {
+            Some(id) => id,
+            None => {
+                let mut dict = Dictionary::new();
+                dict.set("Length1", Object::Integer(self.data.len() as i64));
+                doc.add_object(Stream::new(dict, self.data.to_vec()))
+            }
+        };
+
+        let bbox = face.global_bounding_box();
+        let mut descriptor = Dictionary::new();
+        descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
+        descriptor.set("FontName", Object::Name(base_font.as_bytes().to_vec()));
+        descriptor.set("Flags", Object::Integer(4)); // Symbolic: glyphs are addressed by ID
+        descriptor.set("FontBBox", Object::Array(vec![
+            Object::Integer(to_pdf_units(bbox.x_min as f32)),
+            Object::Integer(to_pdf_units(bbox.y_min as f32)),
+            Object::Integer(to_pdf_units(bbox.x_max as f32)),
+            Object::Integer(to_pdf_units(bbox.y_max as f32)),
+        ]));
+        descriptor.set("ItalicAngle", Object::Integer(0));
+        descriptor.set("Ascent", Object::Integer(to_pdf_units(face.ascender() as f32)));
+        descriptor.set("Descent", Object::Integer(to_pdf_units(face.descender() as f32)));
+        descriptor.set(
+            "CapHeight",
+            Object::Integer(to_pdf_units(face.capital_height().unwrap_or(face.ascender()) as f32)),
+        );
+        descriptor.set("StemV", Object::Integer(80));
+        descriptor.set("FontFile2", Object::Reference(font_file_id));
+        let descriptor_id = doc.add_object(Object::Dictionary(descriptor));
+
+        // /W as individual entries: gid [width]
+        let mut widths = Vec::new();
+        for (id, (advance, _)) in glyphs {
+            widths.push(Object::Integer(*id as i64));
+            widths.push(Object::Array(vec![Object::Integer(to_pdf_units(*advance as f32))]));
+        }
+
+        let mut system_info = Dictionary::new();
+        system_info.set("Registry", Object::string_literal("Adobe"));
+        system_info.set("Ordering", Object::string_literal("Identity"));
+        system_info.set("Supplement", Object::Integer(0));
+
+        let mut cid_font = Dictionary::new();
+        cid_font.set("Type", Object::Name(b"Font".to_vec()));
+        cid_font.set("Subtype", Object::Name(b"CIDFontType2".to_vec()));
+        cid_font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+        cid_font.set("CIDSystemInfo", Object::Dictionary(system_info));
+        cid_font.set("FontDescriptor", Object::Reference(descriptor_id));
+        cid_font.set("W", Object::Array(widths));
+        cid_font.set("CIDToGIDMap", Object::Name(b"Identity".to_vec()));
+        let cid_font_id = doc.add_object(Object::Dictionary(cid_font));
+
+        let to_unicode_id = doc.add_object(Stream::new(Dictionary::new(), to_unicode_cmap(glyphs).into_bytes()));
+
+        let mut font = Dictionary::new();
+        font.set("Type", Object::Name(b"Font".to_vec()));
+        font.set("Subtype", Object::Name(b"Type0".to_vec()));
+        font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+        font.set("Encoding", Object::Name(b"Identity-H".to_vec()));
+        font.set("DescendantFonts", Object::Array(vec![Object::Reference(cid_font_id)]));
+        font.set("ToUnicode", Object::Reference(to_unicode_id));
+        doc.objects.insert(font_id, Object::Dictionary(font));
+
+        Ok(())
+    }
+}
+
+/// Record shaped glyphs in a document-wide glyph set
+pub(crate) fn record_glyphs(set: &mut GlyphSet, glyphs: &[ShapedGlyph]) {
+    for glyph in glyphs {
+        record_glyph(set, glyph.id, glyph.default_advance, &glyph.text);
+    }
+}
+
+/// Add the glyphs of another set (such as one page's) to `set`
+pub(crate) fn merge_glyphs(set: &mut GlyphSet, other: GlyphSet) {
+    for (id, (advance, text)) in other {
+        record_glyph(set, id, advance, &text);
+    }
+}
+
+fn record_glyph(set: &mut GlyphSet, id: u16, advance: i32, text: &str) {
+    let entry = set.entry(id).or_insert_with(|| (advance, String::new()));
+    // Keep the first non-empty text seen for a glyph
+    if entry.1.is_empty() {
+        entry.1 = text.to_string();
+    }
+}
+
+/// Build a ToUnicode CMap so shaped text can be searched and copied
+fn to_unicode_cmap(glyphs: &GlyphSet) -> String {
+    let mappings: Vec<(u16, &str)> = glyphs
+        .iter()
+        .filter(|(_, (_, text))| !text.is_empty())
+        .map(|(id, (_, text))| (*id, text.as_str()))
+        .collect();
+
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n\
+         12 dict begin\n\
+         begincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n\
+         /CMapType 2 def\n\
+         1 begincodespacerange\n\
+         <0000> <FFFF>\n\
+         endcodespacerange\n",
+    );
+
+    // bfchar sections hold at most 100 entries
+    for chunk in mappings.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (id, text) in chunk {
+            let utf16: String = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", id, utf16));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+
+    cmap.push_str(
+        "endcmap\n\
+         CMapName currentdict /CMap defineresource pop\n\
+         end\n\
+         end\n",
+    );
+    cmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_direction_parse() {
+        assert_eq!(TextDirection::parse("rtl").unwrap(), TextDirection::RightToLeft);
+        assert_eq!(TextDirection::parse("LTR").unwrap(), TextDirection::LeftToRight);
+        assert_eq!(TextDirection::parse("auto").unwrap(), TextDirection::Auto);
+        assert!(TextDirection::parse("up").is_err());
+    }
+
+    #[test]
+    fn test_visual_runs_mixed_direction() {
+        // "abc " + Hebrew "shalom" + " def"
+        let text = "abc \u{05E9}\u{05DC}\u{05D5}\u{05DD} def";
+        let runs = visual_runs(text, std::slice::from_ref(&(0..text.len())), TextDirection::Auto);
+        let texts: Vec<(&str, bool)> = runs.iter().map(|(_, r, rtl)| (&text[r.clone()], *rtl)).collect();
+        assert_eq!(
+            texts,
+            vec![("abc ", false), ("\u{05E9}\u{05DC}\u{05D5}\u{05DD}", true), (" def", false)]
+        );
+
+        // With a right-to-left base the Latin runs swap sides
+        let runs = visual_runs(text, std::slice::from_ref(&(0..text.len())), TextDirection::RightToLeft);
+        assert_eq!(&text[runs[0].1.clone()], "def");
+        assert!(runs.last().map(|(_, r, _)| &text[r.clone()]) == Some("abc"));
+    }
+
+    #[test]
+    fn test_visual_runs_split_pieces_in_rtl_run() {
+        // Two styled pieces inside one Hebrew run appear right-to-left
+        let text = "\u{05D0}\u{05D1}\u{05D2}\u{05D3}";
+        let runs = visual_runs(text, &[0..4, 4..8], TextDirection::Auto);
+        let order: Vec<usize> = runs.iter().map(|(index, _, _)| *index).collect();
+        assert_eq!(order, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_shape_hebrew_is_reversed() {
+        let font = ShapingFont::liberation_serif();
+        let alef = font.shape("\u{05D0}", false)[0].id;
+        let bet = font.shape("\u{05D1}", false)[0].id;
+        assert_ne!(alef, 0, "Liberation Serif covers Hebrew");
+
+        let glyphs = font.shape("\u{05D0}\u{05D1}", true);
+        let ids: Vec<u16> = glyphs.iter().map(|g| g.id).collect();
+        assert_eq!(ids, vec![bet, alef]);
+        // Text stays attached to the glyph in logical form
+        assert_eq!(glyphs[1].text, "\u{05D0}");
+    }
+
+    #[test]
+    fn test_glyph_operators_use_kerning_adjustments() {
+        let font = ShapingFont::liberation_serif();
+        let glyphs = vec![
+            ShapedGlyph { id: 1, advance: 1000, default_advance: 1000, x_offset: 0, y_offset: 0, text: "a".into() },
+            ShapedGlyph { id: 2, advance: 900, default_advance: 1000, x_offset: 0, y_offset: 0, text: "b".into() },
+            ShapedGlyph { id: 3, advance: 0, default_advance: 0, x_offset: 10, y_offset: 20, text: String::new() },
+        ];
+        let ops = font.glyph_operators(&glyphs, 10.0, 20.0, 12.0, 0.0);
+        let lines: Vec<&str> = ops.lines().collect();
+        assert_eq!(lines[0], "1 0 0 1 10 20 Tm");
+        assert!(lines[1].starts_with("[<0001><0002> "));
+        assert!(lines[1].ends_with("] TJ"));
+        // The mark is positioned on its own
+        assert!(lines[2].ends_with("Tm"));
+        assert_eq!(lines[3], "<0003> Tj");
+    }
+
+    #[test]
+    fn test_to_unicode_cmap() {
+        let mut set = GlyphSet::new();
+        record_glyphs(&mut set, &[ShapedGlyph {
+            id: 0x1234,
+            advance: 500,
+            default_advance: 500,
+            x_offset: 0,
+            y_offset: 0,
+            text: "\u{05D0}".into(),
+        }]);
+        let cmap = to_unicode_cmap(&set);
+        assert!(cmap.contains("1 beginbfchar\n<1234> <05D0>\n"));
+    }
+
+    #[test]
+    fn test_from_file_rejects_cff_and_collections() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for (name, header) in [("font.otf", b"OTTO"), ("fonts.ttc", b"ttcf")] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, [header.as_slice(), &[0; 64]].concat()).unwrap();
+            assert!(matches!(ShapingFont::from_file(&path), Err(Error::Font(_))));
+        }
+        let path = temp_dir.path().join("LiberationSerif.ttf");
+        std::fs::write(&path, LIBERATION_SERIF).unwrap();
+        assert!(ShapingFont::from_file(&path).is_ok());
+    }
+
+    #[test]
+    fn test_merge_glyphs_keeps_first_text() {
+        let mut set = GlyphSet::from([(7, (500, "fi".to_string())), (8, (300, String::new()))]);
+        // A later page sees glyph 7 as a ligature continuation and glyph 8 with its text
+        let page = GlyphSet::from([(7, (500, String::new())), (8, (300, "x".to_string())), (9, (250, "y".to_string()))]);
+        merge_glyphs(&mut set, page);
+        assert_eq!(set[&7].1, "fi");
+        assert_eq!(set[&8].1, "x");
+        assert_eq!(set[&9].1, "y");
+    }
+}
@@ -0,0 +1,438 @@
+//! Positioned text and image stamps
+//!
+//! A stamp places text or an image at a fixed spot on selected pages, e.g.
+//! "Hand 3" in the top-right corner of page 4. Text stamps share the footer's
+//! font handling, placeholders and inline markup; they are drawn into the same
+//! header/footer XObjects.
+
+use std::path::{Path, PathBuf};
+
+use lopdf::{dictionary, Document, ObjectId, Stream};
+
+use crate::error::{Error, Result};
+use crate::layout::Length;
+use crate::pages::PageSelection;
+
+use super::headers::{FontSpec, Placement};
+use super::shaping::TextDirection;
+
+/// Point of the page (and of the stamp) that a stamp's offsets are measured from
+///
+/// Offsets are measured inward from the anchored edges: `x` moves left from a
+/// right anchor and `y` moves down from a top anchor. For `center` anchors the
+/// offsets move right and up. The stamp is aligned so that its own anchor point
+/// lands on that spot, so `top-right` with zero offsets puts the stamp flush in
+/// the top-right corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    /// Absolute coordinates from the bottom-left corner of the page
+    #[default]
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Parse an anchor name such as `top-right`, `center` or `bottom`
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().replace('_', "-").as_str() {
+            "top-left" => Ok(Anchor::TopLeft),
+            "top" | "top-center" => Ok(Anchor::Top),
+            "top-right" => Ok(Anchor::TopRight),
+            "left" | "center-left" => Ok(Anchor::Left),
+            "center" | "centre" => Ok(Anchor::Center),
+            "right" | "center-right" => Ok(Anchor::Right),
+            "bottom-left" => Ok(Anchor::BottomLeft),
+            "bottom" | "bottom-center" => Ok(Anchor::Bottom),
+            "bottom-right" => Ok(Anchor::BottomRight),
+            _ => Err(Error::InvalidStamp(format!("unknown anchor: {}", value))),
+        }
+    }
+
+    /// Horizontal and vertical position as fractions of the width/height (0 = left/bottom)
+    pub(crate) fn fractions(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (0.0, 1.0),
+            Anchor::Top => (0.5, 1.0),
+            Anchor::TopRight => (1.0, 1.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 0.0),
+            Anchor::Bottom => (0.5, 0.0),
+            Anchor::BottomRight => (1.0, 0.0),
+        }
+    }
+
+    /// Bottom-left corner of a `width` × `height` stamp offset by `(x, y)` on a page
+    pub fn position(self, x: f32, y: f32, size: (f32, f32), page_size: (f32, f32)) -> (f32, f32) {
+        let (fx, fy) = self.fractions();
+        let (width, height) = size;
+        let (page_width, page_height) = page_size;
+        // Offsets point inward from right/top edges
+        let dx = if fx == 1.0 { -x } else { x };
+        let dy = if fy == 1.0 { -y } else { y };
+        (
+            page_width * fx + dx - width * fx,
+            page_height * fy + dy - height * fy,
+        )
+    }
+}
+
+/// What a stamp draws
+#[derive(Debug, Clone, PartialEq)]
+pub enum StampContent {
+    /// Text with placeholders, line breaks and `[font]` markup, like a footer column
+    Text(String),
+    /// A PNG or JPEG image
+    Image(PathBuf),
+}
+
+/// Text or image placed at a fixed position on selected pages
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    /// What to draw
+    pub content: StampContent,
+    /// Pages to stamp (default: all)
+    pub pages: PageSelection,
+    /// Reference point for the offsets
+    pub anchor: Anchor,
+    /// Horizontal offset from the anchor
+    pub x: Length,
+    /// Vertical offset from the anchor
+    pub y: Length,
+    /// Font for text stamps (None = the footer font)
+    pub font: Option<FontSpec>,
+    /// Base direction for text stamps
+    pub direction: TextDirection,
+    /// Image width (None = from height and aspect ratio, or 1pt per pixel)
+    pub width: Option<Length>,
+    /// Image height (None = from width and aspect ratio, or 1pt per pixel)
+    pub height: Option<Length>,
+    /// Draw over or under the page content
+    pub placement: Placement,
+}
+
+impl Stamp {
+    /// A text stamp on every page at the bottom-left corner
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::with_content(StampContent::Text(text.into()))
+    }
+
+    /// An image stamp on every page at the bottom-left corner
+    pub fn image(path: impl Into<PathBuf>) -> Self {
+        Self::with_content(StampContent::Image(path.into()))
+    }
+
+    fn with_content(content: StampContent) -> Self {
+        Self {
+            content,
+            pages: PageSelection::all(),
+            anchor: Anchor::default(),
+            x: Length::from_mm(0.0),
+            y: Length::from_mm(0.0),
+            font: None,
+            direction: TextDirection::default(),
+            width: None,
+            height: None,
+            placement: Placement::default(),
+        }
+    }
+
+    /// Parse a stamp specification
+    ///
+    /// The specification is a list of `key=value` settings, e.g.
+    /// `page=4 x=1in y=2in anchor=top-right text=Hand 3`. Values containing
+    /// spaces can be quoted (`font='bold 12pt red'`); an unquoted `text` value
+    /// runs to the end of the specification.
+    ///
+    /// Keys: `page`/`pages`, `x`, `y`, `anchor`, `text`, `image`, `font`,
+    /// `direction`, `width`, `height`, `placement`. Exactly one of `text` and
+    /// `image` is required.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut content = None;
+        let mut stamp = Self::text("");
+
+        for (key, value) in parse_settings(spec)? {
+            match key.to_lowercase().as_str() {
+                "page" | "pages" => stamp.pages = PageSelection::parse(&value)?,
+                "x" => stamp.x = Length::parse(&value)?,
+                "y" => stamp.y = Length::parse(&value)?,
+                "anchor" => stamp.anchor = Anchor::parse(&value)?,
+                "font" => stamp.font = Some(FontSpec::parse(&value)?),
+                "direction" => stamp.direction = TextDirection::parse(&value)?,
+                "width" => stamp.width = Some(Length::parse(&value)?),
+                "height" => stamp.height = Some(Length::parse(&value)?),
+                "placement" => stamp.placement = Placement::parse(&value)?,
+                "text" | "image" if content.is_some() => {
+                    return Err(Error::InvalidStamp(format!("only one of text and image allowed: {}", spec)))
+                }
+                "text" => content = Some(StampContent::Text(value)),
+                "image" => content = Some(StampContent::Image(PathBuf::from(value))),
+                other => return Err(Error::InvalidStamp(format!("unknown setting: {}", other))),
+            }
+        }
+
+        stamp.content = content
+            .ok_or_else(|| Error::InvalidStamp(format!("missing text or image: {}", spec)))?;
+        Ok(stamp)
+    }
+}
+
+/// Split a stamp specification into `key=value` pairs
+fn parse_settings(spec: &str) -> Result<Vec<(String, String)>> {
+    let mut settings = Vec::new();
+    let mut rest = spec.trim_start();
+
+    while !rest.is_empty() {
+        let (key, after) = rest
+            .split_once('=')
+            .ok_or_else(|| Error::InvalidStamp(format!("expected key=value: {}", rest)))?;
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(Error::InvalidStamp(format!("expected key=value: {}", rest)));
+        }
+
+        let (value, remaining) = match after.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let end = after[1..]
+                    .find(quote)
+                    .ok_or_else(|| Error::InvalidStamp(format!("unterminated quote: {}", after)))?;
+                (&after[1..end + 1], &after[end + 2..])
+            }
+            // Unquoted text takes the rest of the specification
+            _ if key.eq_ignore_ascii_case("text") => (after.trim_end(), ""),
+            _ => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+
+        settings.push((key.to_string(), value.to_string()));
+        rest = remaining.trim_start();
+    }
+
+    Ok(settings)
+}
+
+/// An image embedded in the document as an Image XObject
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StampImage {
+    pub id: ObjectId,
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+}
+
+impl StampImage {
+    /// Embed a PNG or JPEG file
+    pub fn embed(doc: &mut Document, path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(Error::FileNotFound(path.to_path_buf()));
+        }
+        let data = std::fs::read(path)?;
+        if data.starts_with(&[0xFF, 0xD8]) {
+            embed_jpeg(doc, data, path)
+        } else if data.starts_with(b"\x89PNG") {
+            embed_png(doc, &data, path)
+        } else {
+            Err(Error::InvalidStamp(format!(
+                "unsupported image format (expected PNG or JPEG): {}",
+                path.display()
+            )))
+        }
+    }
+
+    /// Displayed size in points, keeping the aspect ratio when only one side is given
+    pub fn size(&self, width: Option<Length>, height: Option<Length>) -> (f32, f32) {
+        let aspect = self.pixel_height as f32 / self.pixel_width.max(1) as f32;
+        match (width, height) {
+            (Some(w), Some(h)) => (w.pt() as f32, h.pt() as f32),
+            (Some(w), None) => (w.pt() as f32, w.pt() as f32 * aspect),
+            (None, Some(h)) => (h.pt() as f32 / aspect, h.pt() as f32),
+            (None, None) => (self.pixel_width as f32, self.pixel_height as f32),
+        }
+    }
+}
+
+/// Embed a JPEG as-is with DCTDecode
+fn embed_jpeg(doc: &mut Document, data: Vec<u8>, path: &Path) -> Result<StampImage> {
+    let invalid = || Error::InvalidStamp(format!("invalid JPEG image: {}", path.display()));
+
+    // Walk the marker segments to the start-of-frame header
+    let mut pos = 2;
+    let (width, height, components) = loop {
+        if pos + 4 > data.len() || data[pos] != 0xFF {
+            return Err(invalid());
+        }
+        let marker = data[pos + 1];
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        // SOF0..SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let header = data.get(pos + 4..pos + 10).ok_or_else(invalid)?;
+            let height = u16::from_be_bytes([header[1], header[2]]) as u32;
+            let width = u16::from_be_bytes([header[3], header[4]]) as u32;
+            break (width, height, header[5]);
+        }
+        pos += 2 + length;
+    };
+
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => width as i64,
+        "Height" => height as i64,
+        "BitsPerComponent" => 8,
+        "Filter" => "DCTDecode",
+    };
+    match components {
+        1 => dict.set("ColorSpace", "DeviceGray"),
+        3 => dict.set("ColorSpace", "DeviceRGB"),
+        4 => {
+            // Adobe CMYK JPEGs store inverted values
+            dict.set("ColorSpace", "DeviceCMYK");
+            dict.set("Decode", vec![1.into(), 0.into(), 1.into(), 0.into(), 1.into(), 0.into(), 1.into(), 0.into()]);
+        }
+        _ => return Err(invalid()),
+    }
+
+    let id = doc.add_object(Stream::new(dict, data).with_compression(false));
+    Ok(StampImage { id, pixel_width: width, pixel_height: height })
+}
+
+/// Decode a PNG and embed it as Flate-compressed samples, with an SMask for transparency
+fn embed_png(doc: &mut Document, data: &[u8], path: &Path) -> Result<StampImage> {
+    let invalid = |e: png::DecodingError| {
+        Error::InvalidStamp(format!("invalid PNG image {}: {}", path.display(), e))
+    };
+
+    let mut decoder = png::Decoder::new(data);
+    // Expand palettes and low bit depths, and reduce 16-bit samples to 8 bits
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(invalid)?;
+    let pixels = &buffer[..frame.buffer_size()];
+
+    let (color_space, channels, has_alpha) = match frame.color_type {
+        png::ColorType::Grayscale => ("DeviceGray", 1, false),
+        png::ColorType::GrayscaleAlpha => ("DeviceGray", 1, true),
+        png::ColorType::Rgb => ("DeviceRGB", 3, false),
+        png::ColorType::Rgba => ("DeviceRGB", 3, true),
+        png::ColorType::Indexed => {
+            return Err(Error::InvalidStamp(format!("unsupported PNG palette: {}", path.display())))
+        }
+    };
+
+    // Split interleaved alpha into its own soft mask
+    let stride = channels + usize::from(has_alpha);
+    let mut color = Vec::with_capacity(pixels.len() / stride * channels);
+    let mut alpha = Vec::new();
+    for pixel in pixels.chunks_exact(stride) {
+        color.extend_from_slice(&pixel[..channels]);
+        if has_alpha {
+            alpha.push(pixel[channels]);
+        }
+    }
+
+    let (width, height) = (frame.width, frame.height);
+    let image_dict = |color_space: &str| {
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => width as i64,
+            "Height" => height as i64,
+            "ColorSpace" => color_space,
+            "BitsPerComponent" => 8,
+        }
+    };
+
+    let mut dict = image_dict(color_space);
+    if has_alpha {
+        let mut mask = Stream::new(image_dict("DeviceGray"), alpha);
+        let _ = mask.compress();
+        dict.set("SMask", doc.add_object(mask));
+    }
+    let mut stream = Stream::new(dict, color);
+    let _ = stream.compress();
+    let id = doc.add_object(stream);
+
+    Ok(StampImage { id, pixel_width: width, pixel_height: height })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stamp_spec() {
+        let stamp = Stamp::parse("page=4 x=1in y=2in anchor=top-right text=Hand 3").unwrap();
+        assert_eq!(stamp.content, StampContent::Text("Hand 3".to_string()));
+        assert_eq!(stamp.pages, PageSelection::page(4));
+        assert_eq!(stamp.anchor, Anchor::TopRight);
+        assert!((stamp.x.pt() - 72.0).abs() < 0.001);
+        assert!((stamp.y.pt() - 144.0).abs() < 0.001);
+
+        let stamp = Stamp::parse("text='Initials: ____' font=\"bold 9pt gray\" pages=odd").unwrap();
+        assert_eq!(stamp.content, StampContent::Text("Initials: ____".to_string()));
+        assert_eq!(stamp.font.unwrap().size, Some(9.0));
+
+        let stamp = Stamp::parse("image=logo.png width=1in anchor=bottom-right placement=under").unwrap();
+        assert_eq!(stamp.content, StampContent::Image(PathBuf::from("logo.png")));
+        assert_eq!(stamp.placement, Placement::Under);
+    }
+
+    #[test]
+    fn test_parse_stamp_errors() {
+        assert!(Stamp::parse("x=1in").is_err()); // no content
+        assert!(Stamp::parse("image=a.png text=b").is_err());
+        assert!(Stamp::parse("anchor=middle text=a").is_err());
+        assert!(Stamp::parse("size=3 text=a").is_err());
+        assert!(Stamp::parse("text='unterminated").is_err());
+        assert!(Stamp::parse("just text").is_err());
+    }
+
+    #[test]
+    fn test_anchor_position() {
+        let page = (612.0, 792.0);
+        let size = (100.0, 20.0);
+        assert_eq!(Anchor::BottomLeft.position(72.0, 144.0, size, page), (72.0, 144.0));
+        // Measured inward from the top-right corner
+        assert_eq!(Anchor::TopRight.position(72.0, 144.0, size, page), (440.0, 628.0));
+        assert_eq!(Anchor::Center.position(0.0, 0.0, size, page), (256.0, 386.0));
+        assert_eq!(Anchor::Bottom.position(10.0, 0.0, size, page), (266.0, 0.0));
+    }
+
+    #[test]
+    fn test_embed_png_with_alpha() {
+        // 2×1 RGBA image
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 0, 255, 0, 0, 255, 128]).unwrap();
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("dot.png");
+        std::fs::write(&path, data).unwrap();
+
+        let mut doc = Document::with_version("1.7");
+        let image = StampImage::embed(&mut doc, &path).unwrap();
+        assert_eq!((image.pixel_width, image.pixel_height), (2, 1));
+        assert_eq!(image.size(Some(Length::from_pt(20.0)), None), (20.0, 10.0));
+
+        let dict = &doc.get_object(image.id).unwrap().as_stream().unwrap().dict;
+        assert_eq!(dict.get(b"ColorSpace").unwrap().as_name().unwrap(), b"DeviceRGB");
+        let mask_id = dict.get(b"SMask").unwrap().as_reference().unwrap();
+        let mut mask = doc.get_object(mask_id).unwrap().as_stream().unwrap().clone();
+        let _ = mask.decompress();
+        assert_eq!(mask.content, vec![255, 128]);
+    }
+}
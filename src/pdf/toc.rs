@@ -0,0 +1,328 @@
+//! Generated table of contents
+//!
+//! One line per entry: its title, dot leaders and the page it starts on,
+//! with a link annotation over the line that jumps to that page. The pages
+//! are drawn with krilla (like the cover) and inserted before headers and
+//! footers are added, so they are numbered with the rest of the document
+//! and the listed page numbers already count them.
+
+use lopdf::{dictionary, Document, Object, ObjectId};
+use crate::error::{Error, Result};
+use super::blanks::blank_document_like;
+use super::create::{load_default_font, measure_text_width};
+use super::geometry::PageGeometry;
+use super::merge::{insert_pages, MergeSection};
+
+/// Font sizes of the heading and the entries, in points
+const HEADING_SIZE: f32 = 24.0;
+const ENTRY_SIZE: f32 = 14.0;
+
+/// Distance between entry baselines
+const LINE_HEIGHT: f32 = ENTRY_SIZE * 1.8;
+
+/// Left and right margin
+const SIDE_MARGIN: f32 = 72.0;
+
+/// Room kept free for the footer below the last entry
+const BOTTOM_MARGIN: f32 = 108.0;
+
+/// Gap between the dot leaders and the text on either side
+const LEADER_GAP: f32 = 6.0;
+
+/// One line of the table of contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    /// Text of the line
+    pub title: String,
+    /// Page the entry points to, counted in the document without the table (from 1)
+    pub page: usize,
+}
+
+/// A table of contents put in front of the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocOptions {
+    /// Heading above the entries
+    pub heading: String,
+    /// Entries in order
+    pub entries: Vec<TocEntry>,
+    /// Add a blank page when the table has an odd page count, so the document
+    /// still starts on a right-hand page when printed double-sided
+    pub blank_back: bool,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        Self {
+            heading: "Contents".to_string(),
+            entries: Vec::new(),
+            blank_back: false,
+        }
+    }
+}
+
+impl TocOptions {
+    /// One entry per merged input, at its first page
+    pub fn from_sections(sections: &[MergeSection]) -> Self {
+        Self {
+            entries: sections
+                .iter()
+                .map(|section| TocEntry { title: section.title.clone(), page: section.first_page })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Put the table of contents in front of a document
+///
+/// The pages are sized like the first page as displayed. Returns how many
+/// pages were inserted (including a blank back).
+pub(crate) fn insert_toc(doc: &mut Document, options: &TocOptions) -> Result<usize> {
+    if options.entries.is_empty() {
+        return Ok(0);
+    }
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let targets = options
+        .entries
+        .iter()
+        .map(|entry| {
+            entry.page.checked_sub(1).and_then(|index| pages.get(index)).copied().ok_or_else(|| {
+                Error::General(format!(
+                    "Contents entry \"{}\" points to page {} of {}",
+                    entry.title,
+                    entry.page,
+                    pages.len()
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let geometry = PageGeometry::from_page(doc, pages[0])?;
+    let page_size = (geometry.display_width(), geometry.display_height());
+    let layout = paginate(options.entries.len(), page_size.1);
+    let blank_back = options.blank_back && layout.len() % 2 == 1;
+    let offset = layout.len() + usize::from(blank_back);
+
+    let toc = Document::load_mem(&render_toc(options, &layout, offset, page_size)?)?;
+    let toc_pages = insert_pages(doc, toc, 0)?;
+
+    // Links over each line, in PDF coordinates (y up from the bottom)
+    let (page_width, page_height) = page_size;
+    for (&page_id, lines) in toc_pages.iter().zip(&layout) {
+        let mut annots = Vec::new();
+        for &(entry, baseline) in lines {
+            let link = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                "Rect" => vec![
+                    SIDE_MARGIN.into(),
+                    (page_height - baseline - ENTRY_SIZE * 0.3).into(),
+                    (page_width - SIDE_MARGIN).into(),
+                    (page_height - baseline + ENTRY_SIZE).into(),
+                ],
+                "Border" => vec![0.into(), 0.into(), 0.into()],
+                "Dest" => vec![targets[entry].into(), "Fit".into()],
+            });
+            annots.push(Object::Reference(link));
+        }
+        doc.get_dictionary_mut(page_id)?.set("Annots", annots);
+    }
+
+    if blank_back {
+        let back = blank_document_like(doc.get_dictionary(toc_pages[0])?);
+        insert_pages(doc, back, toc_pages.len())?;
+    }
+    Ok(offset)
+}
+
+/// Split the entries over pages: for each page, the entry indexes and their
+/// baselines (from the top, as krilla measures)
+fn paginate(count: usize, page_height: f32) -> Vec<Vec<(usize, f32)>> {
+    let mut pages = Vec::new();
+    let mut entry = 0;
+    while entry < count {
+        let top = entries_top(pages.is_empty(), page_height);
+        let lines = (((page_height - BOTTOM_MARGIN - top) / LINE_HEIGHT).floor().max(0.0) as usize + 1).min(count - entry);
+        pages.push((0..lines).map(|line| (entry + line, top + line as f32 * LINE_HEIGHT)).collect());
+        entry += lines;
+    }
+    pages
+}
+
+/// Baseline of the first entry on a page; the first page starts below the heading
+fn entries_top(first_page: bool, page_height: f32) -> f32 {
+    // The top fifth stays free for the document title
+    let top = page_height * 0.2 + ENTRY_SIZE;
+    if first_page { top + HEADING_SIZE * 2.5 } else { top }
+}
+
+/// Draw the table with krilla and return the PDF bytes
+fn render_toc(
+    options: &TocOptions,
+    layout: &[Vec<(usize, f32)>],
+    offset: usize,
+    page_size: (f32, f32),
+) -> Result<Vec<u8>> {
+    use krilla::{Document, PageSettings};
+
+    let (page_width, page_height) = page_size;
+    let font = load_default_font()?;
+    let mut document = Document::new();
+
+    for (index, lines) in layout.iter().enumerate() {
+        let mut page = document.start_page_with(PageSettings::new(page_width, page_height));
+        let mut surface = page.surface();
+
+        if index == 0 {
+            let x = (page_width - measure_text_width(&options.heading, HEADING_SIZE)) / 2.0;
+            draw_text(&mut surface, &font, x, page_height * 0.2 + HEADING_SIZE, HEADING_SIZE, &options.heading);
+        }
+
+        for &(entry, baseline) in lines {
+            let entry = &options.entries[entry];
+            let number = (entry.page + offset).to_string();
+            let number_x = page_width - SIDE_MARGIN - measure_text_width(&number, ENTRY_SIZE);
+            let title_width = number_x - SIDE_MARGIN - 4.0 * LEADER_GAP;
+            let title = truncate_to_width(&entry.title, title_width);
+            let title_end = SIDE_MARGIN + measure_text_width(&title, ENTRY_SIZE);
+
+            draw_text(&mut surface, &font, SIDE_MARGIN, baseline, ENTRY_SIZE, &title);
+            let leaders = dot_leaders(number_x - title_end - 2.0 * LEADER_GAP);
+            let leaders_x = number_x - LEADER_GAP - measure_text_width(&leaders, ENTRY_SIZE);
+            draw_text(&mut surface, &font, leaders_x, baseline, ENTRY_SIZE, &leaders);
+            draw_text(&mut surface, &font, number_x, baseline, ENTRY_SIZE, &number);
+        }
+
+        surface.finish();
+        page.finish();
+    }
+
+    document.finish()
+        .map_err(|e| Error::General(format!("Failed to generate table of contents: {:?}", e)))
+}
+
+fn draw_text(surface: &mut krilla::surface::Surface<'_>, font: &krilla::font::Font, x: f32, y: f32, size: f32, text: &str) {
+    use krilla::color::rgb;
+    use krilla::path::Fill;
+    use krilla::surface::TextDirection;
+    use tiny_skia_path::Point;
+
+    if text.is_empty() {
+        return;
+    }
+    surface.fill_text(
+        Point::from_xy(x, y),
+        Fill {
+            paint: rgb::Color::new(0, 0, 0).into(),
+            ..Default::default()
+        },
+        font.clone(),
+        size,
+        &[],
+        text,
+        false,
+        TextDirection::Auto,
+    );
+}
+
+/// Spaced dots filling at most `width` points
+fn dot_leaders(width: f32) -> String {
+    let dot = measure_text_width(" .", ENTRY_SIZE);
+    if dot <= 0.0 || width < dot {
+        return String::new();
+    }
+    " .".repeat((width / dot).floor() as usize)
+}
+
+/// The title, cut short with an ellipsis if it is wider than `width` points
+fn truncate_to_width(title: &str, width: f32) -> String {
+    if measure_text_width(title, ENTRY_SIZE) <= width {
+        return title.to_string();
+    }
+    let mut chars: Vec<char> = title.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if measure_text_width(&candidate, ENTRY_SIZE) <= width {
+            return candidate;
+        }
+    }
+    "…".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::geometry::Rect;
+
+    #[test]
+    fn test_links_cover_lines_under_rotated_page_tree() {
+        // Portrait pages shown sideways by a /Rotate on the page tree
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..3).map(|_| doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+        }).into()).collect();
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids.clone(),
+            "Count" => 3,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Rotate" => 90,
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+
+        let options = TocOptions {
+            entries: vec![
+                TocEntry { title: "Stayman".to_string(), page: 1 },
+                TocEntry { title: "Transfers".to_string(), page: 3 },
+            ],
+            blank_back: true,
+            ..Default::default()
+        };
+        assert_eq!(insert_toc(&mut doc, &options).unwrap(), 2);
+
+        // The table and its back are landscape like the document, without a rotation
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+        for &page_id in &pages[..2] {
+            let geometry = PageGeometry::from_page(&doc, page_id).unwrap();
+            assert!(!geometry.is_rotated_sideways());
+            assert_eq!((geometry.display_width(), geometry.display_height()), (842.0, 595.0));
+        }
+
+        // Each link spans its line inside the page and points at its entry's page
+        let toc_page = doc.get_dictionary(pages[0]).unwrap();
+        let crop_box = Rect::from_object(&doc, toc_page.get(b"CropBox").unwrap()).unwrap();
+        let annots = toc_page.get(b"Annots").unwrap().as_array().unwrap();
+        assert_eq!(annots.len(), 2);
+        for (annot, target) in annots.iter().zip([&kids[0], &kids[2]]) {
+            let link = doc.get_dictionary(annot.as_reference().unwrap()).unwrap();
+            let rect = Rect::from_object(&doc, link.get(b"Rect").unwrap()).unwrap();
+            assert_eq!((rect.x0, rect.x1), (SIDE_MARGIN, 842.0 - SIDE_MARGIN));
+            assert!(rect.y0 >= crop_box.y0 && rect.y1 <= crop_box.y1, "{:?}", rect);
+            assert_eq!(&link.get(b"Dest").unwrap().as_array().unwrap()[0], target);
+        }
+    }
+
+    #[test]
+    fn test_paginate_continues_on_new_pages() {
+        let layout = paginate(40, 792.0);
+        assert!(layout.len() >= 2);
+        assert_eq!(layout.iter().map(Vec::len).sum::<usize>(), 40);
+        // Entries on the first page start below the heading
+        assert!(layout[0][0].1 > layout[1][0].1);
+        for page in &layout {
+            assert!(page.iter().all(|&(_, baseline)| baseline <= 792.0 - BOTTOM_MARGIN));
+        }
+    }
+
+    #[test]
+    fn test_truncate_long_titles() {
+        assert_eq!(truncate_to_width("Stayman", 200.0), "Stayman");
+        let short = truncate_to_width("A very long lesson title that will never fit on one line", 120.0);
+        assert!(short.ends_with('…'));
+        assert!(measure_text_width(&short, ENTRY_SIZE) <= 120.0);
+    }
+}
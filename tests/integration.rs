@@ -80,6 +80,7 @@ fn test_merge_real_pdfs_page_count() {
     let options = MergeOptions {
         input_paths,
         output_path: output_path.clone(),
+        ..Default::default()
     };
 
     merge_pdfs(&options).expect("Failed to merge PDFs");
@@ -128,6 +129,7 @@ fn test_merge_preserves_content_order() {
     let options = MergeOptions {
         input_paths,
         output_path: output_path.clone(),
+        ..Default::default()
     };
 
     merge_pdfs(&options).expect("Failed to merge PDFs");
@@ -151,6 +153,7 @@ fn test_merge_empty_input_list() {
     let options = MergeOptions {
         input_paths: vec![],
         output_path: output_path.clone(),
+        ..Default::default()
     };
 
     let result = merge_pdfs(&options);
@@ -172,6 +175,7 @@ fn test_merge_nonexistent_file() {
     let options = MergeOptions {
         input_paths: vec![PathBuf::from("nonexistent.pdf")],
         output_path: output_path.clone(),
+        ..Default::default()
     };
 
     let result = merge_pdfs(&options);
@@ -224,6 +228,7 @@ fn test_full_workflow_merge_watermark_overlay() {
     let merge_options = MergeOptions {
         input_paths,
         output_path: merged_path.clone(),
+        ..Default::default()
     };
 
     merge_pdfs(&merge_options).expect("Failed to merge PDFs");
@@ -292,3 +297,1394 @@ fn test_full_workflow_merge_watermark_overlay() {
     println!("✓ Overlaid watermark successfully");
     println!("✓ Final output: {} pages with headers/footers", final_page_count);
 }
+
+#[test]
+fn test_headers_in_optional_content_layer() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, HeaderFooterOptions, LayerOptions};
+
+    let input_path = fixture_path("2. NT Ladder Practice Sheet.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping layer test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("layered.pdf");
+
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        layer: Some(LayerOptions {
+            visible: false,
+            printable: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let catalog = doc.catalog().expect("No catalog");
+    let oc_properties = catalog.get(b"OCProperties").and_then(Object::as_dict)
+        .expect("Catalog should have /OCProperties");
+    let ocgs = oc_properties.get(b"OCGs").and_then(Object::as_array).expect("No /OCGs");
+    assert_eq!(ocgs.len(), 1);
+    let ocg_id = ocgs[0].as_reference().unwrap();
+
+    // Hidden on screen: the default configuration turns the group off
+    let config = oc_properties.get(b"D").and_then(Object::as_dict).expect("No /D");
+    let off = config.get(b"OFF").and_then(Object::as_array).expect("No /OFF");
+    assert!(off.contains(&Object::Reference(ocg_id)));
+
+    // Every page wraps the XObject invocation in an /OC marked-content section
+    for page_id in doc.get_pages().values() {
+        let content = String::from_utf8_lossy(&doc.get_page_content(*page_id).unwrap()).to_string();
+        assert!(content.contains("/OC /HandoutLayer BDC"), "Missing /OC section");
+
+        let (resources, _) = doc.get_page_resources(*page_id).unwrap();
+        let properties = resources.unwrap().get(b"Properties").and_then(Object::as_dict)
+            .expect("Page should have /Properties");
+        assert_eq!(properties.get(b"HandoutLayer").unwrap(), &Object::Reference(ocg_id));
+    }
+}
+
+#[test]
+fn test_headers_keep_tagged_structure() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, HeaderFooterOptions};
+
+    let input_path = fixture_path("1. NT Ladder - Google Docs.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping tagged PDF test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("tagged.pdf");
+
+    let options = HeaderFooterOptions {
+        title: Some("Bridge Class".to_string()),
+        footer_right: Some("Page [page]".to_string()),
+        lang: Some("en-US".to_string()),
+        ..Default::default()
+    };
+
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let catalog = doc.catalog().expect("No catalog");
+    assert!(catalog.get(b"StructTreeRoot").is_ok(), "/StructTreeRoot should be preserved");
+    assert!(catalog.get(b"MarkInfo").is_ok(), "/MarkInfo should be preserved");
+    assert_eq!(catalog.get(b"Lang").and_then(Object::as_str).unwrap(), b"en-US");
+
+    // The stamped XObject marks the title and footer as pagination artifacts
+    let page_id = *doc.get_pages().values().next().unwrap();
+    let (resources, _) = doc.get_page_resources(page_id).unwrap();
+    let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+    let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+    let mut stream = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap().clone();
+    let _ = stream.decompress();
+    let content = String::from_utf8_lossy(&stream.content).to_string();
+    assert!(content.contains("/Artifact <</Type /Pagination /Subtype /Header>> BDC"));
+    assert!(content.contains("/Artifact <</Type /Pagination /Subtype /Footer>> BDC"));
+}
+
+#[test]
+fn test_headers_shape_right_to_left_text() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, ColumnDirections, HeaderFooterOptions, TextDirection};
+
+    let input_path = fixture_path("2. NT Ladder Practice Sheet.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping shaping test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("hebrew.pdf");
+
+    let options = HeaderFooterOptions {
+        title: Some("Café Bridge".to_string()),
+        footer_left: Some("Page [page]".to_string()),
+        footer_right: Some("\u{05E9}\u{05DC}\u{05D5}\u{05DD} Bridge".to_string()),
+        directions: ColumnDirections {
+            right: TextDirection::RightToLeft,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let page_id = *doc.get_pages().values().next().unwrap();
+    let (resources, _) = doc.get_page_resources(page_id).unwrap();
+    let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+    let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+    let xobject = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap();
+
+    // Shaped runs use a Type0 font with Identity-H encoding and a ToUnicode map
+    let fonts = xobject.dict.get(b"Resources").and_then(Object::as_dict).unwrap()
+        .get(b"Font").and_then(Object::as_dict).unwrap();
+    let font_id = fonts.get(b"F4").and_then(Object::as_reference).expect("Type0 font resource");
+    let font = doc.get_dictionary(font_id).unwrap();
+    assert_eq!(font.get(b"Subtype").and_then(Object::as_name).unwrap(), b"Type0");
+    assert_eq!(font.get(b"Encoding").and_then(Object::as_name).unwrap(), b"Identity-H");
+    assert!(font.get(b"ToUnicode").is_ok());
+
+    let mut stream = xobject.clone();
+    let _ = stream.decompress();
+    let content = String::from_utf8_lossy(&stream.content).to_string();
+    assert!(content.contains("/F4 "));
+    assert!(content.contains("] TJ"));
+    // Plain ASCII text keeps the simple fonts
+    assert!(content.contains("(Page 1) Tj"));
+}
+
+#[test]
+fn test_headers_follow_page_rotation_and_orientation() {
+    use lopdf::{dictionary, Document, Object, Stream};
+    use pdf_handouts::pdf::{add_headers_footers, HeaderFooterOptions, PageTemplate};
+
+    // A portrait page rotated into landscape, and an unrotated portrait page
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids = Vec::new();
+    for rotation in [90, 0] {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 10 10 l S\n".to_vec()));
+        kids.push(Object::Reference(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Rotate" => rotation,
+            "Contents" => content_id,
+        })));
+    }
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids,
+        "Count" => 2,
+    }));
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("rotated.pdf");
+    let output_path = temp_dir.path().join("rotated-headers.pdf");
+    doc.save(&input_path).expect("Failed to save input");
+
+    let options = HeaderFooterOptions {
+        footer_left: Some("Bridge Club|Room 4".to_string()),
+        landscape: Some(PageTemplate {
+            footer_left: Some("Bridge Club|Room 4".to_string()),
+            single_line: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().values().copied().collect();
+    let expected = [
+        ("q 0 1 -1 0 612 0 cm /HeaderFooter Do Q", 792.0, "(Bridge Club | Room 4) Tj"),
+        ("q 1 0 0 1 0 0 cm /HeaderFooter Do Q", 612.0, "(Room 4) Tj"),
+    ];
+    for (page_id, (invocation, width, footer)) in pages.into_iter().zip(expected) {
+        let page_content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).to_string();
+        assert!(page_content.contains(invocation), "missing {:?} in {}", invocation, page_content);
+
+        let (resources, _) = doc.get_page_resources(page_id).unwrap();
+        let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+        let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+        let mut xobject = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap().clone();
+        let bbox = xobject.dict.get(b"BBox").and_then(Object::as_array).unwrap();
+        assert_eq!(bbox[2].as_float().unwrap(), width);
+
+        let _ = xobject.decompress();
+        let content = String::from_utf8_lossy(&xobject.content).to_string();
+        assert!(content.contains(footer), "missing {:?} in {}", footer, content);
+    }
+}
+
+#[test]
+fn test_headers_placed_under_page_content() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, ElementPlacement, HeaderFooterOptions, MaskOptions};
+
+    let input_path = fixture_path("2. NT Ladder Practice Sheet.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping placement test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("underlay.pdf");
+
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        mask: MaskOptions { header_all_height: Some(0.75), ..MaskOptions::new() },
+        placement: ElementPlacement::parse("masks=under").unwrap(),
+        ..Default::default()
+    };
+
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    for page_id in doc.get_pages().values() {
+        // The underlay is drawn first, the overlay after the original content
+        let content = String::from_utf8_lossy(&doc.get_page_content(*page_id).unwrap()).to_string();
+        assert!(content.starts_with("q 1 0 0 1 0 0 cm /HeaderFooterUnder Do Q\nq\n"), "{}", &content[..80.min(content.len())]);
+        assert!(content.trim_end().ends_with("q 1 0 0 1 0 0 cm /HeaderFooter Do Q"));
+
+        let (resources, _) = doc.get_page_resources(*page_id).unwrap();
+        let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+        assert!(xobjects.get(b"HeaderFooter").is_ok());
+        assert!(xobjects.get(b"HeaderFooterUnder").is_ok());
+    }
+}
+
+#[test]
+fn test_headers_with_text_and_image_stamps() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, HeaderFooterOptions, Stamp};
+
+    let input_path = fixture_path("2. NT Ladder Practice Sheet.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping stamp test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let image_path = temp_dir.path().join("logo.png");
+    let output_path = temp_dir.path().join("stamped.pdf");
+
+    // 1×1 gray PNG
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, 1, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[128]).unwrap();
+    }
+    std::fs::write(&image_path, png_data).unwrap();
+
+    let options = HeaderFooterOptions {
+        stamps: vec![
+            Stamp::parse("page=1 x=0.5in y=0.5in anchor=top-right text=Hand 3").unwrap(),
+            Stamp::parse(&format!("image='{}' width=1in anchor=bottom-right", image_path.display())).unwrap(),
+        ],
+        ..Default::default()
+    };
+
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let page_id = *doc.get_pages().values().next().unwrap();
+    let (resources, _) = doc.get_page_resources(page_id).unwrap();
+    let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+    let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+    let mut xobject = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap().clone();
+
+    // The image is referenced from the header/footer XObject's own resources
+    let images = xobject.dict.get(b"Resources").and_then(Object::as_dict).unwrap()
+        .get(b"XObject").and_then(Object::as_dict).expect("Stamp image resources");
+    let (name, image_ref) = images.iter().next().unwrap();
+    let name = String::from_utf8_lossy(name).to_string();
+    let image = doc.get_object(image_ref.as_reference().unwrap()).and_then(Object::as_stream).unwrap();
+    assert_eq!(image.dict.get(b"Subtype").and_then(Object::as_name).unwrap(), b"Image");
+
+    let _ = xobject.decompress();
+    let content = String::from_utf8_lossy(&xobject.content).to_string();
+    assert!(content.contains("(Hand 3) Tj"));
+    assert!(content.contains(&format!("q 72 0 0 72 540 0 cm /{} Do Q", name)));
+}
+
+#[test]
+fn test_headers_fit_shrinks_page_content() {
+    use lopdf::Document;
+    use pdf_handouts::pdf::{add_headers_footers, FitOptions, HeaderFooterOptions};
+
+    let input_path = fixture_path("2. NT Ladder Practice Sheet.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping fit test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("fit.pdf");
+
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        fit: Some(FitOptions::default()),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    for page_id in doc.get_pages().values() {
+        let content = String::from_utf8_lossy(&doc.get_page_content(*page_id).unwrap()).to_string();
+        // Letter page into the 1in header/footer reserves: height-limited, centered horizontally
+        let first_line = content.lines().nth(1).unwrap();
+        let operands: Vec<f32> = first_line.trim_end_matches(" cm").split(' ')
+            .map(|v| v.parse().unwrap())
+            .collect();
+        let scale = 648.0 / 792.0;
+        assert!((operands[0] - scale).abs() < 1e-4, "{}", first_line);
+        assert!((operands[4] - (612.0 - 612.0 * scale) / 2.0).abs() < 1e-2, "{}", first_line);
+        assert!((operands[5] - 72.0).abs() < 1e-2, "{}", first_line);
+        // The header/footer XObject itself is not scaled
+        assert!(content.contains("q 1 0 0 1 0 0 cm /HeaderFooter Do Q"));
+    }
+}
+
+#[test]
+fn test_headers_fit_moves_annotations() {
+    use lopdf::Document;
+    use pdf_handouts::pdf::{add_headers_footers, FitOptions, HeaderFooterOptions};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("linked.pdf");
+    let output_path = temp_dir.path().join("fit.pdf");
+
+    write_page_link_pdf(&input_path);
+
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        fit: Some(FitOptions::default()),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add headers/footers");
+
+    // The link covers the shrunken page, like the content does
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let page_id = *doc.get_pages().values().next().unwrap();
+    let annot = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap()[0].as_reference().unwrap();
+    let numbers = |key: &[u8]| -> Vec<f32> {
+        doc.get_dictionary(annot).unwrap().get(key).unwrap().as_array().unwrap()
+            .iter().map(|value| value.as_float().unwrap()).collect()
+    };
+    let scale = 648.0 / 792.0;
+    let x = (612.0 - 612.0 * scale) / 2.0;
+    let rect = numbers(b"Rect");
+    for (actual, expected) in rect.iter().zip([x, 72.0, 612.0 - x, 720.0]) {
+        assert!((actual - expected).abs() < 1e-2, "{:?}", rect);
+    }
+    // So does the click area the QuadPoints give
+    let quad = numbers(b"QuadPoints");
+    assert_eq!(quad.len(), 8);
+    for (actual, expected) in quad.iter().zip([x, 720.0, 612.0 - x, 720.0, x, 72.0, 612.0 - x, 72.0]) {
+        assert!((actual - expected).abs() < 1e-2, "{:?}", quad);
+    }
+}
+
+#[test]
+fn test_headers_extend_canvas_adds_bands() {
+    use lopdf::Document;
+    use pdf_handouts::layout::{Length, PageDimensions};
+    use pdf_handouts::pdf::{add_headers_footers, CanvasOptions, HeaderFooterOptions};
+
+    let input_path = fixture_path("2. NT Ladder Practice Sheet.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping canvas test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let extended_path = temp_dir.path().join("extended.pdf");
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        canvas: Some(CanvasOptions {
+            top: Length::from_inches(1.0),
+            bottom: Length::from_inches(0.5),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &extended_path, &options).expect("Failed to add headers/footers");
+
+    let original = Document::load(&input_path).expect("Failed to load input");
+    let doc = Document::load(&extended_path).expect("Failed to load output");
+    let media_box = |doc: &Document, page_id| -> Vec<f32> {
+        let page = doc.get_dictionary(page_id).unwrap();
+        let boxes = match page.get(b"MediaBox") {
+            Ok(obj) => obj.as_array().unwrap().clone(),
+            Err(_) => doc.catalog().and_then(|c| c.get(b"Pages")).and_then(|p| p.as_reference())
+                .and_then(|id| doc.get_dictionary(id)).unwrap()
+                .get(b"MediaBox").unwrap().as_array().unwrap().clone(),
+        };
+        boxes.iter().map(|v| v.as_float().unwrap()).collect()
+    };
+    for (before_id, after_id) in original.get_pages().values().zip(doc.get_pages().values()) {
+        let before = media_box(&original, *before_id);
+        let after = media_box(&doc, *after_id);
+        assert!((after[0] - before[0]).abs() < 1e-3);
+        assert!((after[3] - (before[3] + 72.0)).abs() < 1e-3, "{:?} -> {:?}", before, after);
+        assert!((after[1] - (before[1] - 36.0)).abs() < 1e-3, "{:?} -> {:?}", before, after);
+    }
+
+    // Normalizing to A4 sets every page to that size
+    let a4_path = temp_dir.path().join("a4.pdf");
+    let options = HeaderFooterOptions {
+        canvas: Some(CanvasOptions {
+            top: Length::from_inches(1.0),
+            bottom: Length::from_inches(1.0),
+            paper: Some(PageDimensions::a4()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &a4_path, &options).expect("Failed to add headers/footers");
+    let doc = Document::load(&a4_path).expect("Failed to load output");
+    for page_id in doc.get_pages().values() {
+        let media = media_box(&doc, *page_id);
+        assert!((media[2] - media[0] - 595.28).abs() < 0.1, "{:?}", media);
+        assert!((media[3] - media[1] - 841.89).abs() < 0.1, "{:?}", media);
+    }
+}
+
+#[test]
+fn test_merge_normalizes_paper_size() {
+    use lopdf::Document;
+    use pdf_handouts::layout::PageDimensions;
+    use pdf_handouts::pdf::PaperScaling;
+
+    let input_paths: Vec<PathBuf> = ["1. NT Ladder - Google Docs.pdf", "4. thinking-bridge-Responding to 1NT 1-6.pdf"]
+        .iter()
+        .map(|name| fixture_path(name))
+        .collect();
+    if input_paths.iter().any(|path| !path.exists()) {
+        eprintln!("Skipping paper test: fixtures not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    for scaling in [PaperScaling::Fit, PaperScaling::None] {
+        let output_path = temp_dir.path().join("a4.pdf");
+        let options = MergeOptions {
+            input_paths: input_paths.clone(),
+            output_path: output_path.clone(),
+            paper: Some(PageDimensions::a4()),
+            paper_scaling: scaling,
+            ..Default::default()
+        };
+        merge_pdfs(&options).expect("Failed to merge PDFs");
+
+        let doc = Document::load(&output_path).expect("Failed to load output");
+        for page_id in doc.get_pages().values() {
+            let page = doc.get_dictionary(*page_id).unwrap();
+            let media: Vec<f32> = page.get(b"MediaBox").unwrap().as_array().unwrap()
+                .iter().map(|v| v.as_float().unwrap()).collect();
+            let (width, height) = (media[2] - media[0], media[3] - media[1]);
+            assert!((width.min(height) - 595.28).abs() < 0.1, "{:?}", media);
+            assert!((width.max(height) - 841.89).abs() < 0.1, "{:?}", media);
+
+            let content = String::from_utf8_lossy(&doc.get_page_content(*page_id).unwrap()).to_string();
+            let placement = content.lines().next().unwrap();
+            let operands: Vec<f32> = placement.trim_start_matches("q ").trim_end_matches(" cm").split(' ')
+                .map(|v| v.parse().unwrap())
+                .collect();
+            match scaling {
+                PaperScaling::Fit => assert!(operands[0] < 1.0, "{}", placement),
+                PaperScaling::None => assert!((operands[0] - 1.0).abs() < 1e-6, "{}", placement),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_merge_with_page_selections() {
+    use lopdf::Document;
+    use pdf_handouts::pages::PageSelection;
+
+    let article = fixture_path("3. ABS4-2 Jacoby Transfers Handouts.pdf");
+    let intro = fixture_path("1. NT Ladder - Google Docs.pdf");
+    if !article.exists() || !intro.exists() {
+        eprintln!("Skipping page selection test: fixtures not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let whole_path = temp_dir.path().join("whole.pdf");
+    let selected_path = temp_dir.path().join("selected.pdf");
+
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![intro.clone(), article.clone()],
+        output_path: whole_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![intro, article],
+        page_selections: vec![PageSelection::all(), PageSelection::parse("-1,2,2").unwrap()],
+        output_path: selected_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    assert_eq!(count_pages(&selected_path).unwrap(), 4);
+
+    // Pages that were left out take their objects with them
+    let whole = Document::load(&whole_path).expect("Failed to load output");
+    let selected = Document::load(&selected_path).expect("Failed to load output");
+    assert!(selected.objects.len() < whole.objects.len());
+
+    // The repeated page is its own page object with the same content
+    let pages: Vec<_> = selected.get_pages().into_values().collect();
+    assert_ne!(pages[2], pages[3]);
+    assert_eq!(selected.get_page_content(pages[2]).unwrap(), selected.get_page_content(pages[3]).unwrap());
+    assert_ne!(selected.get_page_content(pages[1]).unwrap(), selected.get_page_content(pages[2]).unwrap());
+
+    // Selecting nothing is an error
+    let result = merge_pdfs(&MergeOptions {
+        input_paths: vec![fixture_path("1. NT Ladder - Google Docs.pdf")],
+        page_selections: vec![PageSelection::parse("-5").unwrap()],
+        output_path: temp_dir.path().join("empty.pdf"),
+        ..Default::default()
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_repeated_page_gets_own_annotations() {
+    use lopdf::Document;
+    use pdf_handouts::layout::PageDimensions;
+    use pdf_handouts::pages::PageSelection;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("linked.pdf");
+    let output_path = temp_dir.path().join("twice.pdf");
+    write_page_link_pdf(&input_path);
+
+    // Letter onto A4: scaled by 595/612 and centered vertically
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path],
+        page_selections: vec![PageSelection::parse("1,1").unwrap()],
+        output_path: output_path.clone(),
+        paper: Some(PageDimensions::a4()),
+        ..Default::default()
+    }).expect("Failed to merge");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().into_values().collect();
+    assert_eq!(pages.len(), 2);
+    let link = |page_id| {
+        doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap()[0].as_reference().unwrap()
+    };
+    assert_ne!(link(pages[0]), link(pages[1]));
+
+    // Each link is moved onto the new paper once, like its page content
+    let paper = PageDimensions::a4();
+    let scale = paper.width.pt() as f32 / 612.0;
+    for &page_id in &pages {
+        let rect: Vec<f32> = doc.get_dictionary(link(page_id)).unwrap().get(b"Rect").unwrap().as_array().unwrap()
+            .iter().map(|value| value.as_float().unwrap()).collect();
+        assert!((rect[2] - rect[0] - 612.0 * scale).abs() < 1e-2, "{:?}", rect);
+        assert!((rect[3] - rect[1] - 792.0 * scale).abs() < 1e-2, "{:?}", rect);
+    }
+}
+
+/// Build a one-page Letter PDF with a link annotation covering the whole page
+fn write_page_link_pdf(path: &std::path::Path) {
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let content_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 612 792 l S\n".to_vec()));
+    let link_id = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Link",
+        "Rect" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "QuadPoints" => vec![0.into(), 792.into(), 612.into(), 792.into(), 0.into(), 0.into(), 612.into(), 0.into()],
+        "A" => dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com") },
+    });
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+        "Annots" => vec![link_id.into()],
+    });
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1 }));
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("Failed to save input");
+}
+
+/// Build a PDF with `pages` pages and one bookmark per page ("Page 1", ...)
+fn write_bookmarked_pdf(path: &std::path::Path, pages: usize, title: Option<&str>) {
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let outlines_id = doc.new_object_id();
+    let mut kids = Vec::new();
+    let mut items = Vec::new();
+    for _ in 0..pages {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 10 10 l S\n".to_vec()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+        });
+        kids.push(Object::Reference(page_id));
+        items.push(doc.new_object_id());
+    }
+    for (i, &item_id) in items.iter().enumerate() {
+        let mut item = dictionary! {
+            "Title" => Object::string_literal(format!("Page {}", i + 1)),
+            "Parent" => outlines_id,
+            "Dest" => vec![kids[i].clone(), "Fit".into()],
+        };
+        if i > 0 {
+            item.set("Prev", items[i - 1]);
+        }
+        if i + 1 < items.len() {
+            item.set("Next", items[i + 1]);
+        }
+        doc.objects.insert(item_id, Object::Dictionary(item));
+    }
+    doc.objects.insert(outlines_id, Object::Dictionary(dictionary! {
+        "Type" => "Outlines",
+        "First" => items[0],
+        "Last" => items[pages - 1],
+        "Count" => pages as i64,
+    }));
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids,
+        "Count" => pages as i64,
+    }));
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Outlines" => outlines_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    if let Some(title) = title {
+        let info_id = doc.add_object(dictionary! { "Title" => Object::string_literal(title) });
+        doc.trailer.set("Info", info_id);
+    }
+    doc.save(path).expect("Failed to save input");
+}
+
+#[test]
+fn test_merge_combines_bookmarks() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pages::PageSelection;
+    use pdf_handouts::pdf::InputBookmarks;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let first = temp_dir.path().join("lesson-notes.pdf");
+    let second = temp_dir.path().join("deals.pdf");
+    write_bookmarked_pdf(&first, 2, None);
+    write_bookmarked_pdf(&second, 3, Some("Practice Deals"));
+
+    let output_path = temp_dir.path().join("bookmarked.pdf");
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![first, second],
+        page_selections: vec![PageSelection::all(), PageSelection::parse("2-3").unwrap()],
+        output_path: output_path.clone(),
+        bookmarks: InputBookmarks::Title,
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().into_values().collect();
+    assert_eq!(pages.len(), 4);
+
+    let title = |dict: &lopdf::Dictionary| String::from_utf8_lossy(dict.get(b"Title").unwrap().as_str().unwrap()).to_string();
+    let target = |dict: &lopdf::Dictionary| match dict.get(b"Dest").unwrap() {
+        Object::Array(dest) => dest[0].as_reference().unwrap(),
+        other => panic!("unexpected destination {:?}", other),
+    };
+    let children = |dict: &lopdf::Dictionary| {
+        let mut items = Vec::new();
+        let mut next = dict.get(b"First").ok().map(|first| first.as_reference().unwrap());
+        while let Some(id) = next {
+            let item = doc.get_dictionary(id).unwrap().clone();
+            next = item.get(b"Next").ok().map(|next| next.as_reference().unwrap());
+            items.push(item);
+        }
+        items
+    };
+
+    let catalog = doc.catalog().unwrap();
+    let root = doc.get_dictionary(catalog.get(b"Outlines").unwrap().as_reference().unwrap()).unwrap();
+    let inputs = children(root);
+    assert_eq!(inputs.iter().map(title).collect::<Vec<_>>(), vec!["lesson-notes", "Practice Deals"]);
+    assert_eq!(target(&inputs[0]), pages[0]);
+    assert_eq!(target(&inputs[1]), pages[2]);
+
+    // Source bookmarks sit under each input, remapped; "Page 1" of the deals was not selected
+    let deals = children(&inputs[1]);
+    assert_eq!(deals.iter().map(title).collect::<Vec<_>>(), vec!["Page 2", "Page 3"]);
+    assert_eq!(deals.iter().map(target).collect::<Vec<_>>(), vec![pages[2], pages[3]]);
+    assert_eq!(root.get(b"Count").unwrap().as_i64().unwrap(), 6);
+}
+
+#[test]
+fn test_merge_keeps_inherited_boxes_and_rotation() {
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    // Root node: letter MediaBox and shared Resources. A nested node below it
+    // overrides the MediaBox and adds a CropBox and rotation.
+    let mut doc = Document::with_version("1.7");
+    let root_id = doc.new_object_id();
+    let nested_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" });
+    let media_box_id = doc.add_object(vec![0.into(), 0.into(), 400.into(), 300.into()]);
+    let new_page = |doc: &mut Document, parent: lopdf::ObjectId, extra: lopdf::Dictionary| {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf (Hi) Tj ET\n".to_vec()));
+        let mut page = dictionary! { "Type" => "Page", "Parent" => parent, "Contents" => content_id };
+        page.extend(&extra);
+        Object::Reference(doc.add_object(page))
+    };
+    let nested_kids = vec![
+        new_page(&mut doc, nested_id, dictionary! {}),
+        new_page(&mut doc, nested_id, dictionary! { "Rotate" => 0 }),
+    ];
+    let plain = new_page(&mut doc, root_id, dictionary! {});
+    doc.objects.insert(nested_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Parent" => root_id,
+        "Kids" => nested_kids,
+        "Count" => 2,
+        "MediaBox" => media_box_id,
+        "CropBox" => vec![10.into(), 10.into(), 390.into(), 290.into()],
+        "Rotate" => 90,
+    }));
+    doc.objects.insert(root_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![Object::Reference(nested_id), plain],
+        "Count" => 3,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+    }));
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => root_id });
+    doc.trailer.set("Root", catalog_id);
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("nested.pdf");
+    let output_path = temp_dir.path().join("merged.pdf");
+    doc.save(&input_path).expect("Failed to save input");
+
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path.clone(), input_path],
+        output_path: output_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().into_values().collect();
+    assert_eq!(pages.len(), 6);
+
+    let numbers = |page: &lopdf::Dictionary, key: &[u8]| -> Vec<f32> {
+        let (_, value) = doc.dereference(page.get(key).unwrap()).unwrap();
+        value.as_array().unwrap().iter().map(|v| v.as_float().unwrap()).collect()
+    };
+    for chunk in pages.chunks(3) {
+        let nested = doc.get_dictionary(chunk[0]).unwrap();
+        assert_eq!(numbers(nested, b"MediaBox"), vec![0.0, 0.0, 400.0, 300.0]);
+        assert_eq!(numbers(nested, b"CropBox"), vec![10.0, 10.0, 390.0, 290.0]);
+        assert_eq!(nested.get(b"Rotate").unwrap().as_i64().unwrap(), 90);
+
+        // The page's own value wins over the inherited one
+        let own_rotation = doc.get_dictionary(chunk[1]).unwrap();
+        assert_eq!(own_rotation.get(b"Rotate").unwrap().as_i64().unwrap(), 0);
+
+        let plain = doc.get_dictionary(chunk[2]).unwrap();
+        assert_eq!(numbers(plain, b"MediaBox"), vec![0.0, 0.0, 612.0, 792.0]);
+        assert!(plain.get(b"Rotate").is_err());
+        assert!(plain.get(b"CropBox").is_err());
+
+        for page_id in chunk {
+            let page = doc.get_dictionary(*page_id).unwrap();
+            let (_, resources) = doc.dereference(page.get(b"Resources").unwrap()).unwrap();
+            assert!(resources.as_dict().unwrap().get(b"Font").is_ok());
+        }
+    }
+}
+
+/// Build a three-page PDF whose first page has a link to the named
+/// destination "answers" (page 2) and a direct link to page 3
+fn write_linked_pdf(path: &std::path::Path) {
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids = Vec::new();
+    for _ in 0..3 {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 10 10 l S\n".to_vec()));
+        kids.push(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+        }));
+    }
+    let rect = || vec![72.into(), 72.into(), 144.into(), 90.into()];
+    let named_link = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Link",
+        "Rect" => rect(),
+        "A" => dictionary! { "S" => "GoTo", "D" => Object::string_literal("answers") },
+    });
+    let page_link = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Link",
+        "Rect" => rect(),
+        "Dest" => vec![kids[2].into(), "Fit".into()],
+    });
+    doc.get_dictionary_mut(kids[0]).unwrap().set("Annots", vec![named_link.into(), page_link.into()]);
+
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+        "Count" => 3,
+    }));
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Names" => dictionary! {
+            "Dests" => dictionary! {
+                "Names" => vec![Object::string_literal("answers"), vec![kids[1].into(), "Fit".into()].into()],
+            },
+        },
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("Failed to save input");
+}
+
+#[test]
+fn test_merge_keeps_internal_links_and_named_destinations() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pages::PageSelection;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("linked.pdf");
+    let output_path = temp_dir.path().join("merged.pdf");
+    write_linked_pdf(&input_path);
+
+    // The second copy leaves out page 3
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path.clone(), input_path],
+        page_selections: vec![PageSelection::all(), PageSelection::parse("1-2").unwrap()],
+        output_path: output_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().into_values().collect();
+    assert_eq!(pages.len(), 5);
+
+    // Both copies of "answers" survive, the second under a new name
+    let catalog = doc.catalog().unwrap();
+    let names = catalog.get(b"Names").unwrap().as_dict().unwrap();
+    let dests = names.get(b"Dests").unwrap().as_dict().unwrap();
+    let entries = dests.get(b"Names").unwrap().as_array().unwrap();
+    let targets: Vec<(String, lopdf::ObjectId)> = entries.chunks(2)
+        .map(|pair| (
+            String::from_utf8_lossy(pair[0].as_str().unwrap()).to_string(),
+            pair[1].as_array().unwrap()[0].as_reference().unwrap(),
+        ))
+        .collect();
+    assert_eq!(targets, vec![("answers".to_string(), pages[1]), ("answers-2".to_string(), pages[4])]);
+
+    let links = |page_id| -> Vec<lopdf::Dictionary> {
+        match doc.get_dictionary(page_id).unwrap().get(b"Annots") {
+            Ok(Object::Array(annots)) => annots.iter()
+                .map(|annot| doc.get_dictionary(annot.as_reference().unwrap()).unwrap().clone())
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    let goto_name = |link: &lopdf::Dictionary| {
+        let action = doc.dereference(link.get(b"A").unwrap()).unwrap().1.as_dict().unwrap().clone();
+        String::from_utf8_lossy(action.get(b"D").unwrap().as_str().unwrap()).to_string()
+    };
+
+    // First copy: both links kept, the direct one still on the copy's page 3
+    let first = links(pages[0]);
+    assert_eq!(first.len(), 2);
+    assert_eq!(goto_name(&first[0]), "answers");
+    assert_eq!(first[1].get(b"Dest").unwrap().as_array().unwrap()[0].as_reference().unwrap(), pages[2]);
+
+    // Second copy: the named link follows the rename, the link to the missing page is gone
+    let second = links(pages[3]);
+    assert_eq!(second.len(), 1);
+    assert_eq!(goto_name(&second[0]), "answers-2");
+}
+
+#[test]
+fn test_merge_combines_form_fields() {
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    // A one-page worksheet with "Name" and "Answer 1" text fields
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let page_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" });
+    let fields: Vec<Object> = ["Name", "Answer 1"].iter().enumerate().map(|(i, name)| {
+        let y = 700 - 40 * i as i64;
+        doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "FT" => "Tx",
+            "T" => Object::string_literal(*name),
+            "Rect" => vec![72.into(), y.into(), 300.into(), (y + 20).into()],
+            "P" => page_id,
+            "DA" => Object::string_literal("/Helv 12 Tf 0 g"),
+        }).into()
+    }).collect();
+    let content_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 10 10 l S\n".to_vec()));
+    doc.objects.insert(page_id, Object::Dictionary(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+        "Annots" => fields.clone(),
+    }));
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1,
+    }));
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "AcroForm" => dictionary! {
+            "Fields" => fields,
+            "DR" => dictionary! { "Font" => dictionary! { "Helv" => font_id } },
+            "NeedAppearances" => true,
+        },
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("worksheet.pdf");
+    let output_path = temp_dir.path().join("merged.pdf");
+    doc.save(&input_path).expect("Failed to save input");
+
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path.clone(), input_path],
+        output_path: output_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let form = doc.catalog().unwrap().get(b"AcroForm").unwrap().as_dict().unwrap();
+    let names: Vec<String> = form.get(b"Fields").unwrap().as_array().unwrap().iter()
+        .map(|field| {
+            let field = doc.get_dictionary(field.as_reference().unwrap()).unwrap();
+            String::from_utf8_lossy(field.get(b"T").unwrap().as_str().unwrap()).to_string()
+        })
+        .collect();
+    assert_eq!(names, vec!["Name", "Answer 1", "2_Name", "2_Answer 1"]);
+    assert!(form.get(b"NeedAppearances").unwrap().as_bool().unwrap());
+
+    // The default resources still reach the font
+    let helv = form.get(b"DR").unwrap().as_dict().unwrap()
+        .get(b"Font").unwrap().as_dict().unwrap()
+        .get(b"Helv").unwrap().as_reference().unwrap();
+    assert!(doc.get_dictionary(helv).is_ok());
+}
+
+#[test]
+fn test_merge_reports_removed_objects() {
+    use lopdf::Document;
+    use pdf_handouts::pages::PageSelection;
+
+    let input_path = fixture_path("3. ABS4-2 Jacoby Transfers Handouts.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping prune test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let whole_path = temp_dir.path().join("whole.pdf");
+    let first_path = temp_dir.path().join("first.pdf");
+
+    let whole = merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path.clone()],
+        output_path: whole_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+    let first = merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path],
+        page_selections: vec![PageSelection::page(1)],
+        output_path: first_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    // The old catalog and page tree are always left behind
+    assert_eq!(whole.pages, 6);
+    assert!(whole.objects_removed >= 2);
+    // Leaving out five pages removes their content too, and the file shrinks
+    assert_eq!(first.pages, 1);
+    assert!(first.objects_removed > whole.objects_removed);
+    assert!(first.bytes_removed > whole.bytes_removed);
+    let size = |path: &PathBuf| std::fs::metadata(path).unwrap().len();
+    assert!(size(&first_path) < size(&whole_path));
+
+    // Everything in the output is reachable from the trailer (apart from the
+    // cross-reference stream the writer adds)
+    let doc = Document::load(&first_path).expect("Failed to load output");
+    let is_xref = |id: &lopdf::ObjectId| matches!(doc.get_object(*id), Ok(lopdf::Object::Stream(stream))
+        if stream.dict.get(b"Type").and_then(|t| t.as_name()).ok() == Some(b"XRef".as_slice()));
+    let mut doc_copy = doc.clone();
+    let pruned: Vec<_> = doc_copy.prune_objects().into_iter().filter(|id| !is_xref(id)).collect();
+    assert!(pruned.is_empty(), "{:?}", pruned);
+}
+
+#[test]
+fn test_merge_deduplicates_repeated_inputs() {
+    let input_path = fixture_path("3. ABS4-2 Jacoby Transfers Handouts.pdf");
+    if !input_path.exists() {
+        eprintln!("Skipping dedup test: fixture not found");
+        return;
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let shared_path = temp_dir.path().join("shared.pdf");
+    let copied_path = temp_dir.path().join("copied.pdf");
+    let inputs = vec![input_path.clone(), input_path.clone(), input_path];
+
+    let shared = merge_pdfs(&MergeOptions {
+        input_paths: inputs.clone(),
+        output_path: shared_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+    let copied = merge_pdfs(&MergeOptions {
+        input_paths: inputs,
+        output_path: copied_path.clone(),
+        deduplicate: false,
+        ..Default::default()
+    }).expect("Failed to merge PDFs");
+
+    assert_eq!(shared.pages, 18);
+    assert_eq!(copied.duplicates_removed, 0);
+    assert!(shared.duplicates_removed > 0);
+    assert!(shared.bytes_deduplicated > 0);
+    assert_eq!(shared.objects + shared.duplicates_removed, copied.objects);
+
+    // The second and third copies add little beyond their own page objects
+    let size = |path: &PathBuf| std::fs::metadata(path).unwrap().len();
+    let (shared_size, copied_size) = (size(&shared_path), size(&copied_path));
+    eprintln!("deduplicated: {} bytes, without: {} bytes", shared_size, copied_size);
+    assert!(shared_size * 2 < copied_size);
+    assert_eq!(pdf_handouts::pdf::count_pages(&shared_path).unwrap(), 18);
+}
+
+/// A bookmarked test PDF encrypted with RC4 (128-bit) and the given user password
+fn write_encrypted_pdf(path: &std::path::Path, pages: usize, user_password: &str) {
+    use lopdf::{Document, EncryptionState, EncryptionVersion, Object, Permissions, StringFormat};
+
+    write_bookmarked_pdf(path, pages, Some("Locked Handout"));
+    let mut doc = Document::load(path).expect("Failed to load test PDF");
+    let id = Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal);
+    doc.trailer.set("ID", vec![id.clone(), id]);
+    let version = EncryptionVersion::V2 {
+        document: &doc,
+        owner_password: "owner",
+        user_password,
+        key_length: 128,
+        permissions: Permissions::PRINTABLE,
+    };
+    let state = EncryptionState::try_from(version).expect("Failed to set up encryption");
+    doc.encrypt(&state).expect("Failed to encrypt");
+    doc.save(path).expect("Failed to save encrypted PDF");
+}
+
+#[test]
+fn test_encrypted_inputs() {
+    use pdf_handouts::pdf::{add_headers_footers, extract_metadata, extract_metadata_with_password, HeaderFooterOptions, InputPassword};
+    use pdf_handouts::Error;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let open_path = temp_dir.path().join("open.pdf");
+    let locked_path = temp_dir.path().join("locked.pdf");
+    write_encrypted_pdf(&open_path, 2, "");
+    write_encrypted_pdf(&locked_path, 3, "secret");
+
+    // An empty user password needs nothing from the caller
+    let metadata = extract_metadata(&open_path).expect("Failed to read empty-password PDF");
+    assert_eq!(metadata.page_count, 2);
+    assert_eq!(metadata.title.as_deref(), Some("Locked Handout"));
+
+    assert!(matches!(extract_metadata(&locked_path), Err(Error::Encrypted(path)) if path == locked_path));
+    assert!(matches!(extract_metadata_with_password(&locked_path, Some("wrong")), Err(Error::Encrypted(_))));
+    let metadata = extract_metadata_with_password(&locked_path, Some("secret")).expect("Failed to decrypt");
+    assert_eq!(metadata.page_count, 3);
+    assert_eq!(metadata.title.as_deref(), Some("Locked Handout"));
+
+    // Merging: passwords are matched to inputs by file name
+    let merged_path = temp_dir.path().join("merged.pdf");
+    let mut options = MergeOptions {
+        input_paths: vec![open_path.clone(), locked_path.clone()],
+        output_path: merged_path.clone(),
+        ..Default::default()
+    };
+    assert!(matches!(merge_pdfs(&options), Err(Error::Encrypted(_))));
+    options.passwords = vec![InputPassword::parse("locked.pdf=secret").unwrap()];
+    let report = merge_pdfs(&options).expect("Failed to merge encrypted PDFs");
+    assert_eq!(report.pages, 5);
+
+    // The output is not encrypted
+    let merged = lopdf::Document::load(&merged_path).expect("Failed to load merged PDF");
+    assert!(merged.trailer.get(b"Encrypt").is_err());
+    assert_eq!(merged.get_pages().len(), 5);
+    let last_page = *merged.get_pages().values().last().unwrap();
+    let content = merged.get_page_content(last_page).expect("Failed to read page content");
+    assert!(content.windows(8).any(|w| w == b"10 10 l "), "page content was not decrypted");
+
+    let headed_path = temp_dir.path().join("headed.pdf");
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page {page}".to_string()),
+        password: Some("secret".to_string()),
+        ..Default::default()
+    };
+    add_headers_footers(&locked_path, &headed_path, &options).expect("Failed to add headers to encrypted PDF");
+    let headed = lopdf::Document::load(&headed_path).expect("Failed to load output");
+    assert!(headed.trailer.get(b"Encrypt").is_err());
+    assert_eq!(count_pages(&headed_path).unwrap(), 3);
+}
+
+#[test]
+fn test_encrypted_output() {
+    use pdf_handouts::pdf::{add_headers_footers, load_document, EncryptionAlgorithm, HeaderFooterOptions, OutputEncryption, OutputPermissions};
+    use pdf_handouts::Error;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("input.pdf");
+    write_bookmarked_pdf(&input_path, 2, Some("Answer Key"));
+
+    // AES-256 with a user password: nobody opens it without the password
+    let merged_path = temp_dir.path().join("key.pdf");
+    merge_pdfs(&MergeOptions {
+        input_paths: vec![input_path.clone()],
+        output_path: merged_path.clone(),
+        encryption: Some(OutputEncryption {
+            user_password: "assistants".to_string(),
+            owner_password: "teacher".to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }).expect("Failed to merge");
+
+    // Merged files are written as PDF 1.5; AES-256 needs PDF 2.0
+    let header = |path: &std::path::Path| std::fs::read(path).unwrap()[..8].to_vec();
+    assert_eq!(header(&merged_path), b"%PDF-2.0");
+    assert!(matches!(load_document(&merged_path, None), Err(Error::Encrypted(_))));
+    let encrypted = lopdf::Document::load(&merged_path).expect("Failed to load");
+    let dict = encrypted.get_encrypted().expect("Output is not encrypted");
+    assert_eq!(dict.get(b"V").unwrap().as_i64().unwrap(), 5);
+    let doc = load_document(&merged_path, Some("assistants")).expect("Failed to open with user password");
+    assert_eq!(doc.get_pages().len(), 2);
+    let first_page = *doc.get_pages().values().next().unwrap();
+    let content = doc.get_page_content(first_page).expect("Failed to read page content");
+    assert!(content.windows(8).any(|w| w == b"10 10 l "), "page content was not decrypted");
+    let doc = load_document(&merged_path, Some("teacher")).expect("Failed to open with owner password");
+    assert_eq!(doc.get_pages().len(), 2);
+
+    // AES-128 with only a permission limit: opens freely, but may not be edited.
+    // The input declares PDF 1.4, older than AES-128 (PDF 1.6)
+    let mut old_input = lopdf::Document::load(&input_path).expect("Failed to load input");
+    old_input.version = "1.4".to_string();
+    old_input.save(&input_path).expect("Failed to save input");
+    let headed_path = temp_dir.path().join("handout.pdf");
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        encryption: Some(OutputEncryption {
+            algorithm: EncryptionAlgorithm::Aes128,
+            permissions: OutputPermissions::deny("modify").unwrap(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &headed_path, &options).expect("Failed to add headers");
+
+    let encrypted = lopdf::Document::load(&headed_path).expect("Failed to load");
+    let dict = encrypted.get_encrypted().expect("Output is not encrypted");
+    assert_eq!(dict.get(b"V").unwrap().as_i64().unwrap(), 4);
+    assert_eq!(header(&headed_path), b"%PDF-1.6");
+    let flags = dict.get(b"P").unwrap().as_i64().unwrap();
+    assert_eq!(flags & (1 << 3), 0, "modify should be denied");
+    assert_ne!(flags & (1 << 2), 0, "print should be allowed");
+    let doc = load_document(&headed_path, None).expect("Failed to open with empty user password");
+    assert_eq!(doc.get_pages().len(), 2);
+}
+
+#[test]
+fn test_duplex_blank_pages() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, HeaderFooterOptions};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let inputs: Vec<PathBuf> = [3, 2, 1].iter().enumerate().map(|(i, &pages)| {
+        let path = temp_dir.path().join(format!("lesson{}.pdf", i + 1));
+        write_bookmarked_pdf(&path, pages, None);
+        path
+    }).collect();
+
+    // 3 + blank + 2 + 1, then padded to 8 for a folded booklet
+    let merged_path = temp_dir.path().join("merged.pdf");
+    let report = merge_pdfs(&MergeOptions {
+        input_paths: inputs,
+        output_path: merged_path.clone(),
+        duplex: true,
+        pad_to_multiple: Some(4),
+        ..Default::default()
+    }).expect("Failed to merge");
+    assert_eq!(report.pages, 8);
+    assert_eq!(report.blank_pages, 2);
+
+    let doc = Document::load(&merged_path).expect("Failed to load merged PDF");
+    let pages: Vec<_> = doc.get_pages().values().copied().collect();
+    let has_content = |id| doc.get_dictionary(id).unwrap().get(b"Contents").is_ok();
+    let layout: Vec<bool> = pages.iter().map(|&id| has_content(id)).collect();
+    assert_eq!(layout, vec![true, true, true, false, true, true, true, false]);
+    let media_box = |id| doc.get_dictionary(id).unwrap().get(b"MediaBox").unwrap().clone();
+    assert_eq!(media_box(pages[3]), media_box(pages[2]));
+
+    // Blank pages get the notice and nothing else; the others keep their footers
+    let output_path = temp_dir.path().join("handout.pdf");
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page]".to_string()),
+        blank_page_text: Some("This page intentionally left blank".to_string()),
+        ..Default::default()
+    };
+    add_headers_footers(&merged_path, &output_path, &options).expect("Failed to add headers");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let overlay_text = |page_id| {
+        let (resources, _) = doc.get_page_resources(page_id).unwrap();
+        let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+        let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+        let mut xobject = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap().clone();
+        let _ = xobject.decompress();
+        String::from_utf8_lossy(&xobject.content).to_string()
+    };
+    let pages: Vec<_> = doc.get_pages().values().copied().collect();
+    let blank = overlay_text(pages[3]);
+    assert!(blank.contains("(This page intentionally left blank) Tj"));
+    assert!(!blank.contains("(Page 4) Tj"));
+    assert!(overlay_text(pages[4]).contains("(Page 5) Tj"));
+}
+
+#[test]
+fn test_cover_page() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, CoverOptions, HeaderFooterOptions};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input_path = temp_dir.path().join("lesson.pdf");
+    write_bookmarked_pdf(&input_path, 2, None);
+
+    // 1×1 gray PNG
+    let logo_path = temp_dir.path().join("logo.png");
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, 1, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[128]).unwrap();
+    }
+    std::fs::write(&logo_path, png_data).unwrap();
+
+    let output_path = temp_dir.path().join("handout.pdf");
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page] of [pages]".to_string()),
+        cover: Some(CoverOptions {
+            title: Some("Stayman".to_string()),
+            subtitle: Some("Finding a 4-4 major fit".to_string()),
+            presenter: Some("Rick Wilson".to_string()),
+            date: chrono::NaiveDate::from_ymd_opt(2026, 3, 14),
+            logo: Some(logo_path),
+            blank_back: false,
+        }),
+        ..Default::default()
+    };
+    add_headers_footers(&input_path, &output_path, &options).expect("Failed to add cover");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().values().copied().collect();
+    assert_eq!(pages.len(), 3);
+    let media_box = |id| doc.get_dictionary(id).unwrap().get(b"MediaBox").unwrap().as_array().unwrap()
+        .iter().map(|value| value.as_float().unwrap()).collect::<Vec<_>>();
+    assert_eq!(media_box(pages[0]), media_box(pages[1]));
+
+    // The cover has no footer; numbering starts on the first content page
+    let has_footer = |page_id| {
+        let (resources, _) = doc.get_page_resources(page_id).unwrap();
+        resources.and_then(|r| r.get(b"XObject").ok()).and_then(|x| x.as_dict().ok())
+            .is_some_and(|xobjects| xobjects.has(b"HeaderFooter"))
+    };
+    assert!(!has_footer(pages[0]));
+    let (resources, _) = doc.get_page_resources(pages[1]).unwrap();
+    let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+    let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+    let mut xobject = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap().clone();
+    let _ = xobject.decompress();
+    assert!(String::from_utf8_lossy(&xobject.content).contains("(Page 1 of 2) Tj"));
+
+    // The cover's own resources include the logo
+    let (resources, _) = doc.get_page_resources(pages[0]).unwrap();
+    let cover_resources = resources.map(|r| format!("{:?}", r)).unwrap_or_default();
+    assert!(cover_resources.contains("XObject"));
+}
+
+#[test]
+fn test_table_of_contents() {
+    use lopdf::{Document, Object};
+    use pdf_handouts::pdf::{add_headers_footers, HeaderFooterOptions, TocOptions};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let inputs: Vec<PathBuf> = [(3, Some("Opening Leads")), (2, None), (1, None)].iter().enumerate().map(|(i, &(pages, title))| {
+        let path = temp_dir.path().join(format!("lesson{}.pdf", i + 1));
+        write_bookmarked_pdf(&path, pages, title);
+        path
+    }).collect();
+
+    let merged_path = temp_dir.path().join("merged.pdf");
+    let report = merge_pdfs(&MergeOptions {
+        input_paths: inputs,
+        output_path: merged_path.clone(),
+        ..Default::default()
+    }).expect("Failed to merge");
+    let sections: Vec<(&str, usize, usize)> = report.sections.iter()
+        .map(|section| (section.title.as_str(), section.first_page, section.pages))
+        .collect();
+    assert_eq!(sections, vec![("Opening Leads", 1, 3), ("lesson2", 4, 2), ("lesson3", 6, 1)]);
+
+    let output_path = temp_dir.path().join("handout.pdf");
+    let options = HeaderFooterOptions {
+        footer_center: Some("Page [page] of [pages]".to_string()),
+        toc: Some(TocOptions::from_sections(&report.sections)),
+        ..Default::default()
+    };
+    add_headers_footers(&merged_path, &output_path, &options).expect("Failed to add contents");
+
+    let doc = Document::load(&output_path).expect("Failed to load output");
+    let pages: Vec<_> = doc.get_pages().values().copied().collect();
+    assert_eq!(pages.len(), 7);
+
+    // One link per input, to its first page
+    let annots = doc.get_dictionary(pages[0]).unwrap().get(b"Annots").unwrap().as_array().unwrap();
+    let targets: Vec<_> = annots.iter().map(|annot| {
+        let annot = doc.get_dictionary(annot.as_reference().unwrap()).unwrap();
+        assert_eq!(annot.get(b"Subtype").unwrap().as_name().unwrap(), b"Link");
+        annot.get(b"Dest").unwrap().as_array().unwrap()[0].as_reference().unwrap()
+    }).collect();
+    assert_eq!(targets, vec![pages[1], pages[4], pages[6]]);
+
+    // The contents page is numbered with the rest
+    let footer_text = |page_id| {
+        let (resources, _) = doc.get_page_resources(page_id).unwrap();
+        let xobjects = resources.unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+        let xobject_id = xobjects.get(b"HeaderFooter").and_then(Object::as_reference).unwrap();
+        let mut xobject = doc.get_object(xobject_id).and_then(Object::as_stream).unwrap().clone();
+        let _ = xobject.decompress();
+        String::from_utf8_lossy(&xobject.content).to_string()
+    };
+    assert!(footer_text(pages[0]).contains("(Page 1 of 7) Tj"));
+    assert!(footer_text(pages[4]).contains("(Page 5 of 7) Tj"));
+}